@@ -0,0 +1,251 @@
+//! Derive macros for `rs-plugin-common-interfaces`.
+//!
+//! `#[derive(ApplyRsIds)]` generates both `From<T> for RsIds` and `ApplyRsIds for T` from
+//! field attributes, so every entity maps its ids the same way:
+//!
+//! - `#[rs_id("tmdb")]` on an `Option<_>` field: stored under the given key.
+//!   `Option<String>` keeps the raw value, other types are parsed from the base value.
+//! - `#[rs_id("volume", detail)]`: written under the key, read back with `RsIds::find_detail`.
+//! - `#[rs_id(redseat)]` on the `String` id field: used as the local id, or parsed as
+//!   `key:value` when it already is an external id.
+//! - `#[rs_id(otherids)]` on the `Option<OtherIds>` field: receives every unknown key.
+//!
+//! When the local id is computed, use `#[rs_ids(redseat_fn = "method")]` on the struct instead
+//! of a `redseat` field; the id is then only exported, never applied back.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input, Data, DeriveInput, Fields, GenericArgument, Ident, LitStr, PathArguments,
+    Token, Type,
+};
+
+enum RsIdAttr {
+    Key { key: LitStr, detail: bool },
+    Redseat,
+    OtherIds,
+}
+
+impl Parse for RsIdAttr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            let key: LitStr = input.parse()?;
+            let mut detail = false;
+            if input.peek(Token![,]) {
+                input.parse::<Token![,]>()?;
+                let flag: Ident = input.parse()?;
+                if flag != "detail" {
+                    return Err(syn::Error::new(flag.span(), "expected `detail`"));
+                }
+                detail = true;
+            }
+            return Ok(RsIdAttr::Key { key, detail });
+        }
+        let ident: Ident = input.parse()?;
+        if ident == "redseat" {
+            Ok(RsIdAttr::Redseat)
+        } else if ident == "otherids" {
+            Ok(RsIdAttr::OtherIds)
+        } else {
+            Err(syn::Error::new(
+                ident.span(),
+                "expected a key string, `redseat` or `otherids`",
+            ))
+        }
+    }
+}
+
+struct KeyField {
+    ident: Ident,
+    key: LitStr,
+    detail: bool,
+    is_string: bool,
+}
+
+/// Returns the `T` of an `Option<T>` type.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+fn is_string(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.path.segments.last().is_some_and(|s| s.ident == "String"))
+}
+
+fn redseat_fn(input: &DeriveInput) -> syn::Result<Option<Ident>> {
+    let mut method = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("rs_ids")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("redseat_fn") {
+                let name: LitStr = meta.value()?.parse()?;
+                method = Some(Ident::new(&name.value(), name.span()));
+                Ok(())
+            } else {
+                Err(meta.error("expected `redseat_fn`"))
+            }
+        })?;
+    }
+    Ok(method)
+}
+
+#[proc_macro_derive(ApplyRsIds, attributes(rs_id, rs_ids))]
+pub fn derive_apply_rs_ids(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            name,
+            "ApplyRsIds can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            name,
+            "ApplyRsIds requires named fields",
+        ));
+    };
+
+    let redseat_method = redseat_fn(&input)?;
+    let mut redseat_field: Option<Ident> = None;
+    let mut other_field: Option<Ident> = None;
+    let mut key_fields: Vec<KeyField> = vec![];
+
+    for field in &fields.named {
+        let Some(attr) = field.attrs.iter().find(|a| a.path().is_ident("rs_id")) else {
+            continue;
+        };
+        let ident = field.ident.clone().expect("named field");
+        match attr.parse_args::<RsIdAttr>()? {
+            RsIdAttr::Redseat => {
+                if !is_string(&field.ty) {
+                    return Err(syn::Error::new_spanned(
+                        &field.ty,
+                        "`redseat` field must be a String",
+                    ));
+                }
+                redseat_field = Some(ident);
+            }
+            RsIdAttr::OtherIds => other_field = Some(ident),
+            RsIdAttr::Key { key, detail } => {
+                let inner = option_inner(&field.ty).ok_or_else(|| {
+                    syn::Error::new_spanned(&field.ty, "rs_id fields must be an Option")
+                })?;
+                key_fields.push(KeyField {
+                    ident,
+                    key,
+                    detail,
+                    is_string: is_string(inner),
+                });
+            }
+        }
+    }
+
+    if redseat_field.is_some() && redseat_method.is_some() {
+        return Err(syn::Error::new_spanned(
+            name,
+            "use either a `redseat` field or `redseat_fn`, not both",
+        ));
+    }
+
+    let rs_ids = quote!(::rs_plugin_common_interfaces::domain::rs_ids::RsIds);
+    let apply_trait = quote!(::rs_plugin_common_interfaces::domain::rs_ids::ApplyRsIds);
+
+    // -- From<T> for RsIds --
+
+    let compute_redseat = redseat_method
+        .as_ref()
+        .map(|method| quote!(let redseat = value.#method();));
+    let set_keys = key_fields.iter().map(|f| {
+        let (ident, key) = (&f.ident, &f.key);
+        quote!(if let Some(v) = value.#ident { ids.set(#key, v); })
+    });
+    let add_others = other_field.as_ref().map(|ident| {
+        quote! {
+            if let Some(other) = value.#ident {
+                for entry in other.into_vec() {
+                    let _ = ids.try_add(entry);
+                }
+            }
+        }
+    });
+    let redseat_source = match (&redseat_field, &redseat_method) {
+        (Some(ident), _) => Some(quote!(value.#ident)),
+        (None, Some(_)) => Some(quote!(redseat)),
+        (None, None) => None,
+    };
+    let add_redseat = redseat_source.map(|source| {
+        quote! {
+            if ids.try_add(#source.clone()).is_err() {
+                ids.set("redseat", #source);
+            }
+        }
+    });
+
+    // -- ApplyRsIds for T --
+
+    let apply_redseat = redseat_field.as_ref().map(|ident| {
+        quote!(if let Some(v) = ids.redseat() { self.#ident = v.to_string(); })
+    });
+    let apply_keys = key_fields.iter().map(|f| {
+        let (ident, key) = (&f.ident, &f.key);
+        let getter = if f.detail {
+            quote!(ids.find_detail(#key).and_then(|v| v.parse().ok()))
+        } else if f.is_string {
+            quote!(ids.get(#key).map(|v| v.to_string()))
+        } else {
+            quote!(ids.get(#key).and_then(|v| #rs_ids::split_details(v).0.parse().ok()))
+        };
+        quote!(if let Some(v) = #getter { self.#ident = Some(v); })
+    });
+    let known = key_fields.iter().map(|f| &f.key);
+    let apply_others = other_field.as_ref().map(|ident| {
+        quote! {
+            let known: &[&str] = &["redseat", #(#known),*];
+            let mut other = self.#ident.take().unwrap_or_default();
+            for (k, v) in ids.iter() {
+                if !known.contains(&k.as_str()) { other.add(k, v); }
+            }
+            if !other.as_slice().is_empty() { self.#ident = Some(other); }
+        }
+    });
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::core::convert::From<#name #ty_generics> for #rs_ids #where_clause {
+            fn from(value: #name #ty_generics) -> Self {
+                #compute_redseat
+                let mut ids = #rs_ids::default();
+                #(#set_keys)*
+                #add_others
+                #add_redseat
+                ids
+            }
+        }
+
+        impl #impl_generics #apply_trait for #name #ty_generics #where_clause {
+            fn apply_rs_ids(&mut self, ids: &#rs_ids) {
+                #apply_redseat
+                #(#apply_keys)*
+                #apply_others
+            }
+        }
+    })
+}
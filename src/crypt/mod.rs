@@ -0,0 +1,555 @@
+//! Encrypted media container, the layout described by [`Media::bytes_size`]:
+//!
+//! | bytes | content                                   |
+//! |-------|-------------------------------------------|
+//! | 16    | IV                                        |
+//! | 4     | encrypted thumb size `T` (big endian)     |
+//! | 4     | encrypted info size `I` (big endian)      |
+//! | 32    | thumb mimetype (UTF-8, zero padded)       |
+//! | 256   | file mimetype (UTF-8, zero padded)        |
+//! | T     | encrypted thumb                           |
+//! | I     | encrypted info                            |
+//! | ...   | encrypted file                            |
+//!
+//! Thumb, info and file are each encrypted with AES-256-CBC and PKCS#7 padding, all three
+//! starting from the header IV as existing containers do. Sharing the IV means sections
+//! starting with the same 16 bytes have the same first encrypted block; the IV must still be
+//! unique per file. The file is encrypted and decrypted as a stream.
+//!
+//! [`Media::bytes_size`]: crate::domain::media::Media::bytes_size
+
+use std::io::{self, Read, Write};
+
+use aes::{
+    cipher::{
+        block_padding::Pkcs7, generic_array::GenericArray, BlockDecryptMut, BlockEncryptMut,
+        KeyIvInit,
+    },
+    Aes256,
+};
+use serde::Serialize;
+
+type Encryptor = cbc::Encryptor<Aes256>;
+type Decryptor = cbc::Decryptor<Aes256>;
+
+pub const KEY_SIZE: usize = 32;
+pub const IV_SIZE: usize = 16;
+pub const THUMB_MIME_SIZE: usize = 32;
+pub const MIME_SIZE: usize = 256;
+pub const HEADER_SIZE: usize = IV_SIZE + 4 + 4 + THUMB_MIME_SIZE + MIME_SIZE;
+
+const BLOCK_SIZE: usize = 16;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Serialize, strum_macros::AsRefStr)]
+pub enum CryptError {
+    Io(String),
+    InvalidHeader(String),
+    /// Mimetype longer than its header field (field name, mimetype)
+    MimeTooLong(&'static str, String),
+    /// Wrong key or corrupted data
+    Decrypt,
+}
+
+// region:    --- Error Boilerplate
+
+impl core::fmt::Display for CryptError {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for CryptError {}
+
+// endregion: --- Error Boilerplate
+
+impl From<io::Error> for CryptError {
+    fn from(err: io::Error) -> Self {
+        CryptError::Io(err.to_string())
+    }
+}
+
+/// Size of `plain_size` bytes once encrypted (PKCS#7 always adds 1 to 16 bytes).
+pub fn encrypted_size(plain_size: u64) -> u64 {
+    (plain_size / BLOCK_SIZE as u64 + 1) * BLOCK_SIZE as u64
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EncryptedHeader {
+    pub iv: [u8; IV_SIZE],
+    /// Encrypted thumb size, 0 without thumb
+    pub thumb_size: u32,
+    /// Encrypted info size, 0 without info
+    pub info_size: u32,
+    pub thumb_mime: Option<String>,
+    pub mime: String,
+}
+
+impl EncryptedHeader {
+    /// Offset of the encrypted file from the start of the container.
+    pub fn file_offset(&self) -> u64 {
+        HEADER_SIZE as u64 + self.thumb_size as u64 + self.info_size as u64
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; HEADER_SIZE], CryptError> {
+        let mut bytes = [0u8; HEADER_SIZE];
+        bytes[..IV_SIZE].copy_from_slice(&self.iv);
+        bytes[IV_SIZE..IV_SIZE + 4].copy_from_slice(&self.thumb_size.to_be_bytes());
+        bytes[IV_SIZE + 4..IV_SIZE + 8].copy_from_slice(&self.info_size.to_be_bytes());
+        let thumb_mime = self.thumb_mime.as_deref().unwrap_or_default();
+        write_mime(
+            &mut bytes[IV_SIZE + 8..],
+            "thumbMime",
+            thumb_mime,
+            THUMB_MIME_SIZE,
+        )?;
+        write_mime(
+            &mut bytes[IV_SIZE + 8 + THUMB_MIME_SIZE..],
+            "mime",
+            &self.mime,
+            MIME_SIZE,
+        )?;
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(CryptError::InvalidHeader(format!(
+                "{} bytes, expected {}",
+                bytes.len(),
+                HEADER_SIZE
+            )));
+        }
+        let size = |offset: usize| {
+            u32::from_be_bytes(bytes[offset..offset + 4].try_into().expect("4 bytes"))
+        };
+        let header = EncryptedHeader {
+            iv: bytes[..IV_SIZE].try_into().expect("16 bytes"),
+            thumb_size: size(IV_SIZE),
+            info_size: size(IV_SIZE + 4),
+            thumb_mime: Some(read_mime(&bytes[IV_SIZE + 8..], THUMB_MIME_SIZE)?)
+                .filter(|mime| !mime.is_empty()),
+            mime: read_mime(&bytes[IV_SIZE + 8 + THUMB_MIME_SIZE..], MIME_SIZE)?,
+        };
+        // `is_multiple_of` needs Rust 1.87
+        #[allow(clippy::manual_is_multiple_of)]
+        if header.thumb_size as usize % BLOCK_SIZE != 0
+            || header.info_size as usize % BLOCK_SIZE != 0
+        {
+            return Err(CryptError::InvalidHeader(format!(
+                "sizes must be multiples of {}: thumb {} info {}",
+                BLOCK_SIZE, header.thumb_size, header.info_size
+            )));
+        }
+        Ok(header)
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, CryptError> {
+        let mut bytes = [0u8; HEADER_SIZE];
+        reader.read_exact(&mut bytes)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+fn write_mime(
+    target: &mut [u8],
+    field: &'static str,
+    mime: &str,
+    size: usize,
+) -> Result<(), CryptError> {
+    if mime.len() > size {
+        return Err(CryptError::MimeTooLong(field, mime.to_string()));
+    }
+    target[..mime.len()].copy_from_slice(mime.as_bytes());
+    Ok(())
+}
+
+fn read_mime(source: &[u8], size: usize) -> Result<String, CryptError> {
+    let field = &source[..size];
+    let end = field.iter().position(|b| *b == 0).unwrap_or(size);
+    String::from_utf8(field[..end].to_vec())
+        .map_err(|_| CryptError::InvalidHeader("mimetype is not UTF-8".to_string()))
+}
+
+/// Encrypt a whole (small) buffer: thumbs and infos.
+pub fn encrypt_bytes(key: &[u8; KEY_SIZE], iv: &[u8; IV_SIZE], data: &[u8]) -> Vec<u8> {
+    Encryptor::new(key.into(), iv.into()).encrypt_padded_vec_mut::<Pkcs7>(data)
+}
+
+pub fn decrypt_bytes(
+    key: &[u8; KEY_SIZE],
+    iv: &[u8; IV_SIZE],
+    data: &[u8],
+) -> Result<Vec<u8>, CryptError> {
+    Decryptor::new(key.into(), iv.into())
+        .decrypt_padded_vec_mut::<Pkcs7>(data)
+        .map_err(|_| CryptError::Decrypt)
+}
+
+/// Thumb and info stored next to an encrypted file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EncryptedExtras<'a> {
+    /// Thumb bytes and mimetype
+    pub thumb: Option<(&'a [u8], &'a str)>,
+    pub info: Option<&'a [u8]>,
+}
+
+/// Write the container for `reader` (a file of type `mime`) into `writer`.
+///
+/// The IV must be random and unique per file; it is stored in the header. Returns the header
+/// and the encrypted file size (the `size` stored on the media).
+pub fn encrypt<R: Read, W: Write>(
+    key: &[u8; KEY_SIZE],
+    iv: [u8; IV_SIZE],
+    mime: &str,
+    extras: EncryptedExtras,
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<(EncryptedHeader, u64), CryptError> {
+    let thumb = extras
+        .thumb
+        .map(|(thumb, _)| encrypt_bytes(key, &iv, thumb));
+    let info = extras.info.map(|info| encrypt_bytes(key, &iv, info));
+    let section_size = |section: &Option<Vec<u8>>| {
+        let size = section.as_ref().map(|s| s.len()).unwrap_or(0);
+        u32::try_from(size).map_err(|_| CryptError::InvalidHeader(format!("{} bytes", size)))
+    };
+    let header = EncryptedHeader {
+        iv,
+        thumb_size: section_size(&thumb)?,
+        info_size: section_size(&info)?,
+        thumb_mime: extras.thumb.map(|(_, mime)| mime.to_string()),
+        mime: mime.to_string(),
+    };
+    writer.write_all(&header.to_bytes()?)?;
+    for section in [thumb, info].iter().flatten() {
+        writer.write_all(section)?;
+    }
+
+    let mut encryptor = Encryptor::new(key.into(), (&iv).into());
+    let mut buffer = vec![0u8; CHUNK_SIZE + BLOCK_SIZE];
+    let mut pending = 0;
+    let mut written = 0u64;
+    loop {
+        let read = reader.read(&mut buffer[pending..CHUNK_SIZE])?;
+        if read == 0 {
+            let last = encryptor
+                .encrypt_padded_mut::<Pkcs7>(&mut buffer, pending)
+                .map_err(|_| CryptError::InvalidHeader("padding".to_string()))?;
+            writer.write_all(last)?;
+            written += last.len() as u64;
+            break;
+        }
+        pending += read;
+        let complete = pending - pending % BLOCK_SIZE;
+        for block in buffer[..complete].chunks_exact_mut(BLOCK_SIZE) {
+            encryptor.encrypt_block_mut(GenericArray::from_mut_slice(block));
+        }
+        writer.write_all(&buffer[..complete])?;
+        written += complete as u64;
+        buffer.copy_within(complete..pending, 0);
+        pending -= complete;
+    }
+    writer.flush()?;
+    Ok((header, written))
+}
+
+/// Streaming reader of a container: the header, thumb and info are read on creation and the
+/// decrypted file is then read through [`Read`].
+pub struct EncryptedReader<R> {
+    inner: R,
+    header: EncryptedHeader,
+    thumb: Option<Vec<u8>>,
+    info: Option<Vec<u8>>,
+    decryptor: Option<Decryptor>,
+    /// Encrypted bytes not decrypted yet (the last block is kept until the end for unpadding)
+    encrypted: Vec<u8>,
+    plain: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> EncryptedReader<R> {
+    pub fn new(key: &[u8; KEY_SIZE], mut inner: R) -> Result<Self, CryptError> {
+        let header = EncryptedHeader::read_from(&mut inner)?;
+        let mut section = |size: u32| -> Result<Option<Vec<u8>>, CryptError> {
+            if size == 0 {
+                return Ok(None);
+            }
+            // sizes come from the header: only allocate what is actually read
+            let mut encrypted = Vec::new();
+            (&mut inner).take(size as u64).read_to_end(&mut encrypted)?;
+            if encrypted.len() != size as usize {
+                return Err(CryptError::InvalidHeader(format!(
+                    "{} bytes section, {} available",
+                    size,
+                    encrypted.len()
+                )));
+            }
+            decrypt_bytes(key, &header.iv, &encrypted).map(Some)
+        };
+        let thumb = section(header.thumb_size)?;
+        let info = section(header.info_size)?;
+        Ok(EncryptedReader {
+            decryptor: Some(Decryptor::new(key.into(), (&header.iv).into())),
+            inner,
+            header,
+            thumb,
+            info,
+            encrypted: Vec::with_capacity(CHUNK_SIZE + BLOCK_SIZE),
+            plain: Vec::with_capacity(CHUNK_SIZE),
+            position: 0,
+        })
+    }
+
+    pub fn header(&self) -> &EncryptedHeader {
+        &self.header
+    }
+
+    pub fn thumb(&self) -> Option<&[u8]> {
+        self.thumb.as_deref()
+    }
+
+    pub fn info(&self) -> Option<&[u8]> {
+        self.info.as_deref()
+    }
+
+    /// Decrypt the next chunk into `plain`, returns false at the end of the file.
+    fn fill(&mut self) -> Result<bool, CryptError> {
+        let Some(decryptor) = self.decryptor.as_mut() else {
+            return Ok(false);
+        };
+        self.plain.clear();
+        self.position = 0;
+        let start = self.encrypted.len();
+        self.encrypted.resize(start + CHUNK_SIZE, 0);
+        let read = self.inner.read(&mut self.encrypted[start..])?;
+        self.encrypted.truncate(start + read);
+
+        if read == 0 {
+            let decryptor = self.decryptor.take().expect("checked above");
+            let last = decryptor
+                .decrypt_padded_mut::<Pkcs7>(&mut self.encrypted)
+                .map_err(|_| CryptError::Decrypt)?;
+            self.plain.extend_from_slice(last);
+            self.encrypted.clear();
+            return Ok(true);
+        }
+        let complete = (self.encrypted.len() - 1) / BLOCK_SIZE * BLOCK_SIZE;
+        for block in self.encrypted[..complete].chunks_exact_mut(BLOCK_SIZE) {
+            decryptor.decrypt_block_mut(GenericArray::from_mut_slice(block));
+        }
+        self.plain.extend_from_slice(&self.encrypted[..complete]);
+        self.encrypted.drain(..complete);
+        Ok(true)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for EncryptedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position >= self.plain.len() {
+            if !self
+                .fill()
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+            {
+                return Ok(0);
+            }
+        }
+        let available = &self.plain[self.position..];
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::domain::media::Media;
+
+    const KEY: [u8; KEY_SIZE] = [7; KEY_SIZE];
+    const IV: [u8; IV_SIZE] = [3; IV_SIZE];
+
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn header_roundtrip() {
+        let header = EncryptedHeader {
+            iv: IV,
+            thumb_size: 32,
+            info_size: 0,
+            thumb_mime: Some("image/webp".to_string()),
+            mime: "video/mp4".to_string(),
+        };
+        let bytes = header.to_bytes().unwrap();
+        assert_eq!(bytes.len(), 312);
+        assert_eq!(&bytes[16..20], &[0, 0, 0, 32]);
+        assert_eq!(EncryptedHeader::from_bytes(&bytes).unwrap(), header);
+        assert_eq!(header.file_offset(), 344);
+
+        let long = EncryptedHeader {
+            thumb_mime: Some("x".repeat(33)),
+            ..header
+        };
+        assert!(matches!(
+            long.to_bytes(),
+            Err(CryptError::MimeTooLong("thumbMime", _))
+        ));
+        assert!(EncryptedHeader::from_bytes(&bytes[..100]).is_err());
+    }
+
+    #[test]
+    fn container_roundtrip_matches_bytes_size() {
+        let thumb = sample(1000);
+        let info = b"{\"name\":\"holidays\"}".to_vec();
+        for len in [
+            0,
+            15,
+            16,
+            17,
+            CHUNK_SIZE - 1,
+            CHUNK_SIZE,
+            3 * CHUNK_SIZE + 5,
+        ] {
+            let file = sample(len);
+            let mut container = Vec::new();
+            let (header, size) = encrypt(
+                &KEY,
+                IV,
+                "video/mp4",
+                EncryptedExtras {
+                    thumb: Some((&thumb, "image/jpeg")),
+                    info: Some(&info),
+                },
+                &mut Cursor::new(&file),
+                &mut container,
+            )
+            .unwrap();
+            assert_eq!(size, encrypted_size(len as u64));
+            assert_eq!(container.len() as u64, header.file_offset() + size);
+
+            let mut reader = EncryptedReader::new(&KEY, Cursor::new(&container)).unwrap();
+            assert_eq!(reader.header(), &header);
+            assert_eq!(reader.thumb(), Some(&thumb[..]));
+            assert_eq!(reader.info(), Some(&info[..]));
+            let mut decrypted = Vec::new();
+            reader.read_to_end(&mut decrypted).unwrap();
+            assert_eq!(decrypted, file, "{} bytes", len);
+        }
+
+        let file = sample(5000);
+        let mut container = Vec::new();
+        let (header, size) = encrypt(
+            &KEY,
+            IV,
+            "image/jpeg",
+            EncryptedExtras {
+                thumb: Some((&thumb, "image/jpeg")),
+                info: None,
+            },
+            &mut Cursor::new(&file),
+            &mut container,
+        )
+        .unwrap();
+        let media = Media {
+            size: Some(size),
+            thumbsize: Some(header.thumb_size as u64),
+            iv: Some("iv".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(media.bytes_size(), Some(container.len() as u64));
+
+        let mut container = Vec::new();
+        let (header, size) = encrypt(
+            &KEY,
+            IV,
+            "image/jpeg",
+            EncryptedExtras {
+                thumb: Some((&thumb, "image/jpeg")),
+                info: Some(&info),
+            },
+            &mut Cursor::new(&file),
+            &mut container,
+        )
+        .unwrap();
+        let media = Media {
+            size: Some(size),
+            infosize: Some(header.info_size as u64),
+            ..media
+        };
+        assert_eq!(media.bytes_size(), Some(container.len() as u64));
+    }
+
+    #[test]
+    fn reads_sections_encrypted_with_the_header_iv() {
+        let thumb = sample(40);
+        let file = sample(100);
+        let header = EncryptedHeader {
+            iv: IV,
+            thumb_size: encrypted_size(40) as u32,
+            info_size: 0,
+            thumb_mime: Some("image/jpeg".to_string()),
+            mime: "text/plain".to_string(),
+        };
+        let container = [
+            header.to_bytes().unwrap().to_vec(),
+            encrypt_bytes(&KEY, &IV, &thumb),
+            encrypt_bytes(&KEY, &IV, &file),
+        ]
+        .concat();
+        let mut reader = EncryptedReader::new(&KEY, Cursor::new(&container)).unwrap();
+        assert_eq!(reader.thumb(), Some(&thumb[..]));
+        let mut decrypted = Vec::new();
+        reader.read_to_end(&mut decrypted).unwrap();
+        assert_eq!(decrypted, file);
+    }
+
+    #[test]
+    fn truncated_section_fails_without_allocating() {
+        let mut header = EncryptedHeader {
+            iv: IV,
+            thumb_size: u32::MAX - 15,
+            info_size: 0,
+            thumb_mime: Some("image/jpeg".to_string()),
+            mime: "video/mp4".to_string(),
+        }
+        .to_bytes()
+        .unwrap()
+        .to_vec();
+        header.extend(sample(32));
+        assert!(matches!(
+            EncryptedReader::new(&KEY, Cursor::new(&header)),
+            Err(CryptError::InvalidHeader(_))
+        ));
+    }
+
+    #[test]
+    fn wrong_key_fails() {
+        let mut container = Vec::new();
+        encrypt(
+            &KEY,
+            IV,
+            "text/plain",
+            EncryptedExtras::default(),
+            &mut Cursor::new(sample(100)),
+            &mut container,
+        )
+        .unwrap();
+        let mut reader = EncryptedReader::new(&[8; KEY_SIZE], Cursor::new(&container)).unwrap();
+        assert_eq!(reader.thumb(), None);
+        let mut decrypted = Vec::new();
+        assert!(reader.read_to_end(&mut decrypted).is_err());
+        assert_eq!(
+            decrypt_bytes(&KEY, &IV, &encrypt_bytes(&KEY, &IV, b"info")).unwrap(),
+            b"info".to_vec()
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{external_images::ExternalImage, other_ids::OtherIds, rs_ids::ApplyRsIds};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, ApplyRsIds)]
+#[serde(rename_all = "camelCase")]
+pub struct Album {
+    #[serde(default)]
+    #[rs_id(redseat)]
+    pub id: String,
+    pub name: String,
+    /// Album, single, EP, compilation...
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artists_ref: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artists: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub airdate: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracks: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discs: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genres: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overview: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("mbrel")]
+    pub musicbrainz_release_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("mbrg")]
+    pub musicbrainz_release_group_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("slug")]
+    pub slug: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id(otherids)]
+    pub otherids: Option<OtherIds>,
+
+    /// Cover artwork
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub images: Option<Vec<ExternalImage>>,
+
+    #[serde(default)]
+    pub modified: u64,
+    #[serde(default)]
+    pub added: u64,
+    #[serde(default)]
+    pub posterv: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Album;
+    use crate::domain::rs_ids::{ApplyRsIds, RsIds};
+
+    #[test]
+    fn album_rs_ids_roundtrip() {
+        let album = Album {
+            id: "album-1".to_string(),
+            name: "Album".to_string(),
+            musicbrainz_release_id: Some("f5093c06-23e3-404f-aeaa-40f72885ee3a".to_string()),
+            musicbrainz_release_group_id: Some("1dc4c347-a1db-32aa-b14f-bc9cc507b843".to_string()),
+            ..Default::default()
+        };
+        let ids = RsIds::from(album.clone());
+        assert_eq!(
+            ids.get("musicbrainzReleaseGroupId"),
+            Some("1dc4c347-a1db-32aa-b14f-bc9cc507b843")
+        );
+
+        let mut restored = Album::default();
+        restored.apply_rs_ids(&ids);
+        assert_eq!(restored.id, album.id);
+        assert_eq!(
+            restored.musicbrainz_release_id,
+            album.musicbrainz_release_id
+        );
+        assert_eq!(
+            restored.musicbrainz_release_group_id,
+            album.musicbrainz_release_group_id
+        );
+        assert_eq!(restored.otherids, None);
+    }
+}
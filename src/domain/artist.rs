@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{external_images::ExternalImage, other_ids::OtherIds, rs_ids::ApplyRsIds},
+    url::RsLink,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, ApplyRsIds)]
+#[serde(rename_all = "camelCase")]
+pub struct Artist {
+    #[serde(default)]
+    #[rs_id(redseat)]
+    pub id: String,
+    pub name: String,
+    /// Person, group, orchestra...
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alt: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socials: Option<Vec<RsLink>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genres: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bio: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("mbart")]
+    pub musicbrainz_artist_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("slug")]
+    pub slug: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id(otherids)]
+    pub otherids: Option<OtherIds>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub images: Option<Vec<ExternalImage>>,
+
+    #[serde(default)]
+    pub modified: u64,
+    #[serde(default)]
+    pub added: u64,
+    #[serde(default)]
+    pub posterv: u64,
+}
@@ -2,13 +2,15 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::domain::{
-    media::MediaItemReference, other_ids::OtherIds, person::Person, rs_ids::{ApplyRsIds, RsIds}, tag::Tag
+    media::MediaItemReference, other_ids::OtherIds, person::Person, rs_ids::ApplyRsIds, tag::Tag,
+    update::{ChangeSummary, Patch},
 };
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, ApplyRsIds)]
 #[serde(rename_all = "camelCase")]
 pub struct Book {
     #[serde(default)]
+    #[rs_id(redseat)]
     pub id: String,
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -17,8 +19,10 @@ pub struct Book {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub serie_ref: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("volume", detail)]
     pub volume: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("chapter", detail)]
     pub chapter: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub year: Option<u16>,
@@ -35,15 +39,21 @@ pub struct Book {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub original: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("isbn13")]
     pub isbn13: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("oleid")]
     pub openlibrary_edition_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("olwid")]
     pub openlibrary_work_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("gbvid")]
     pub google_books_volume_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("asin")]
     pub asin: Option<String>,
+    #[rs_id(otherids)]
     pub otherids: Option<OtherIds>,
     #[serde(default)]
     pub modified: u64,
@@ -51,84 +61,43 @@ pub struct Book {
     pub added: u64,
 }
 
-impl From<Book> for RsIds {
-    fn from(value: Book) -> Self {
-        let mut ids = RsIds::default();
-        if let Some(v) = value.isbn13 { ids.set("isbn13", v); }
-        if let Some(v) = value.openlibrary_edition_id { ids.set("oleid", v); }
-        if let Some(v) = value.openlibrary_work_id { ids.set("olwid", v); }
-        if let Some(v) = value.google_books_volume_id { ids.set("gbvid", v); }
-        if let Some(v) = value.asin { ids.set("asin", v); }
-        if let Some(v) = value.volume { ids.set("volume", v); }
-        if let Some(v) = value.chapter { ids.set("chapter", v); }
-        if let Some(other) = value.otherids {
-            for entry in other.into_vec() {
-                let _ = ids.try_add(entry);
-            }
-        }
-        if ids.try_add(value.id.clone()).is_err() {
-            ids.set("redseat", value.id);
-        }
-        ids
-    }
-}
-
-impl ApplyRsIds for Book {
-    fn apply_rs_ids(&mut self, ids: &RsIds) {
-        if let Some(redseat) = ids.redseat() {
-            self.id = redseat.to_string();
-        }
-        if let Some(isbn13) = ids.isbn13() {
-            self.isbn13 = Some(isbn13.to_string());
-        }
-        if let Some(oleid) = ids.openlibrary_edition_id() {
-            self.openlibrary_edition_id = Some(oleid.to_string());
-        }
-        if let Some(olwid) = ids.openlibrary_work_id() {
-            self.openlibrary_work_id = Some(olwid.to_string());
-        }
-        if let Some(gbvid) = ids.google_books_volume_id() {
-            self.google_books_volume_id = Some(gbvid.to_string());
-        }
-        if let Some(asin) = ids.asin() {
-            self.asin = Some(asin.to_string());
-        }
-        if let Some(volume) = ids.find_detail_f64("volume") {
-            self.volume = Some(volume);
-        }
-        if let Some(chapter) = ids.find_detail_f64("chapter") {
-            self.chapter = Some(chapter);
-        }
-        let known: &[&str] = &["redseat", "isbn13", "oleid", "olwid", "gbvid", "asin"];
-        let mut other = self.otherids.take().unwrap_or_default();
-        for (k, v) in ids.iter() {
-            if !known.contains(&k.as_str()) { other.add(k, v); }
-        }
-        if !other.as_slice().is_empty() { self.otherids = Some(other); }
-    }
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct BookForUpdate {
     pub name: Option<String>,
     #[serde(rename = "type")]
-    pub kind: Option<String>,
-    pub serie_ref: Option<String>,
-    pub volume: Option<f64>,
-    pub chapter: Option<f64>,
-    pub year: Option<u16>,
-    pub airdate: Option<i64>,
-    pub overview: Option<String>,
-    pub pages: Option<u32>,
-    pub params: Option<Value>,
-    pub lang: Option<String>,
-    pub original: Option<String>,
-    pub isbn13: Option<String>,
-    pub openlibrary_edition_id: Option<String>,
-    pub openlibrary_work_id: Option<String>,
-    pub google_books_volume_id: Option<String>,
-    pub asin: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub kind: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub serie_ref: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub volume: Patch<f64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub chapter: Patch<f64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub year: Patch<u16>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub airdate: Patch<i64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub overview: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub pages: Patch<u32>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub params: Patch<Value>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub lang: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub original: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub isbn13: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub openlibrary_edition_id: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub openlibrary_work_id: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub google_books_volume_id: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub asin: Patch<String>,
     pub otherids: Option<OtherIds>,
 
 
@@ -147,12 +116,51 @@ impl BookForUpdate {
     }
 }
 
+impl BookForUpdate {
+    /// Apply the patch to `book`. Tags and people are relations and are not applied.
+    pub fn apply_to(&self, book: &mut Book) -> ChangeSummary {
+        let mut changes = ChangeSummary::default();
+        changes.set("name", &mut book.name, self.name.clone());
+        changes.set_patch("type", &mut book.kind, self.kind.clone());
+        changes.set_patch("serieRef", &mut book.serie_ref, self.serie_ref.clone());
+        changes.set_patch("volume", &mut book.volume, self.volume);
+        changes.set_patch("chapter", &mut book.chapter, self.chapter);
+        changes.set_patch("year", &mut book.year, self.year);
+        changes.set_patch("airdate", &mut book.airdate, self.airdate);
+        changes.set_patch("overview", &mut book.overview, self.overview.clone());
+        changes.set_patch("pages", &mut book.pages, self.pages);
+        changes.set_patch("params", &mut book.params, self.params.clone());
+        changes.set_patch("lang", &mut book.lang, self.lang.clone());
+        changes.set_patch("original", &mut book.original, self.original.clone());
+        changes.set_patch("isbn13", &mut book.isbn13, self.isbn13.clone());
+        changes.set_patch(
+            "openlibraryEditionId",
+            &mut book.openlibrary_edition_id,
+            self.openlibrary_edition_id.clone(),
+        );
+        changes.set_patch(
+            "openlibraryWorkId",
+            &mut book.openlibrary_work_id,
+            self.openlibrary_work_id.clone(),
+        );
+        changes.set_patch(
+            "googleBooksVolumeId",
+            &mut book.google_books_volume_id,
+            self.google_books_volume_id.clone(),
+        );
+        changes.set_patch("asin", &mut book.asin, self.asin.clone());
+        changes.set_option("otherids", &mut book.otherids, self.otherids.clone());
+        changes
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Book;
+    use super::{Book, BookForUpdate};
     use crate::domain::{
         other_ids::OtherIds,
         rs_ids::{ApplyRsIds, RsIds},
+        update::Patch,
     };
     use serde_json::json;
 
@@ -214,4 +222,48 @@ mod tests {
         assert_eq!(book.volume, Some(3.0));
         assert_eq!(book.chapter, Some(7.0));
     }
+
+    #[test]
+    fn book_rs_ids_roundtrip() {
+        let book = Book {
+            id: "book-1".to_string(),
+            name: "Book".to_string(),
+            volume: Some(2.0),
+            chapter: Some(4.5),
+            isbn13: Some("9780143127741".to_string()),
+            openlibrary_edition_id: Some("OL1M".to_string()),
+            openlibrary_work_id: Some("OL1W".to_string()),
+            google_books_volume_id: Some("gb-1".to_string()),
+            asin: Some("B00TEST".to_string()),
+            otherids: Some(OtherIds(vec!["goodreads:41".to_string()])),
+            ..Default::default()
+        };
+        let ids = RsIds::from(book.clone());
+        assert_eq!(ids.redseat(), Some("book-1"));
+
+        let mut restored = Book::default();
+        restored.apply_rs_ids(&ids);
+        assert_eq!(restored.id, book.id);
+        assert_eq!(restored.volume, book.volume);
+        assert_eq!(restored.chapter, book.chapter);
+        assert_eq!(restored.isbn13, book.isbn13);
+        assert_eq!(restored.openlibrary_edition_id, book.openlibrary_edition_id);
+        assert_eq!(restored.openlibrary_work_id, book.openlibrary_work_id);
+        assert_eq!(restored.google_books_volume_id, book.google_books_volume_id);
+        assert_eq!(restored.asin, book.asin);
+        assert_eq!(restored.otherids, book.otherids);
+    }
+
+    #[test]
+    fn book_apply_to() {
+        let mut book = Book::default();
+        let changes = BookForUpdate {
+            name: Some("Dune".to_string()),
+            volume: Patch::Set(1.0),
+            ..Default::default()
+        }
+        .apply_to(&mut book);
+        assert_eq!(changes.fields, vec!["name", "volume"]);
+        assert!(BookForUpdate::default().apply_to(&mut book).is_empty());
+    }
 }
@@ -0,0 +1,268 @@
+//! Completeness of a serie: which episodes are owned, missing, duplicated or not aired yet,
+//! computed from the serie episode list and the `FileEpisode` references of its medias.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{episode::Episode, media::FileEpisode};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatedEpisode {
+    pub number: u32,
+    pub medias: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SeasonCoverage {
+    pub season: u32,
+    /// Episode numbers with at least one media
+    pub owned: Vec<u32>,
+    /// Aired episode numbers without media
+    pub missing: Vec<u32>,
+    /// Episode numbers with more than one media
+    pub duplicated: Vec<DuplicatedEpisode>,
+    /// Episode numbers without airdate or airing after `now`, and without media
+    pub unaired: Vec<u32>,
+}
+
+impl SeasonCoverage {
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// A media reference that could not be matched to a known episode.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UnmatchedFileEpisode {
+    pub media: String,
+    pub episode: FileEpisode,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SerieCoverage {
+    pub serie: String,
+    pub seasons: Vec<SeasonCoverage>,
+    pub unmatched: Vec<UnmatchedFileEpisode>,
+}
+
+impl SerieCoverage {
+    /// Compute coverage of serie `serie` from its episodes and `(media id, FileEpisode)` pairs.
+    ///
+    /// References of other series are ignored. `episode..=episode_to` ranges cover every
+    /// episode of multi-episode files. References without season use absolute numbering and are
+    /// matched against `Episode::abs`. Season-only references cannot be matched to episodes and
+    /// are reported in `unmatched`. `now` is compared to `Episode::airdate`.
+    pub fn compute(
+        serie: &str,
+        episodes: &[Episode],
+        files: &[(String, FileEpisode)],
+        now: i64,
+    ) -> Self {
+        let absolute: BTreeMap<u32, (u32, u32)> = episodes
+            .iter()
+            .filter_map(|e| e.abs.map(|abs| (abs, (e.season, e.number))))
+            .collect();
+        let mut medias: BTreeMap<(u32, u32), Vec<String>> = episodes
+            .iter()
+            .map(|e| ((e.season, e.number), vec![]))
+            .collect();
+        let mut last_numbers: BTreeMap<u32, u32> = BTreeMap::new();
+        for episode in episodes {
+            let last = last_numbers.entry(episode.season).or_default();
+            *last = (*last).max(episode.number);
+        }
+        let last_absolute = absolute.keys().next_back().copied().unwrap_or(0);
+        let mut unmatched = vec![];
+
+        for (media, file) in files.iter().filter(|(_, f)| f.id == serie) {
+            let Some(first) = file.episode else {
+                unmatched.push(UnmatchedFileEpisode {
+                    media: media.clone(),
+                    episode: file.clone(),
+                });
+                continue;
+            };
+            // ranges come from file names, never iterate past the known episodes
+            let known_last = match file.season {
+                Some(season) => last_numbers.get(&season).copied().unwrap_or(0),
+                None => last_absolute,
+            };
+            let last = file.episode_to.unwrap_or(first).max(first).min(known_last);
+            let mut matched = false;
+            for number in first..=last {
+                let key = match file.season {
+                    Some(season) => Some((season, number)),
+                    None => absolute.get(&number).copied(),
+                };
+                if let Some(owners) = key.and_then(|key| medias.get_mut(&key)) {
+                    if !owners.contains(media) {
+                        owners.push(media.clone());
+                    }
+                    matched = true;
+                }
+            }
+            if !matched {
+                unmatched.push(UnmatchedFileEpisode {
+                    media: media.clone(),
+                    episode: file.clone(),
+                });
+            }
+        }
+
+        let mut seasons: BTreeMap<u32, SeasonCoverage> = BTreeMap::new();
+        for episode in episodes {
+            let coverage = seasons
+                .entry(episode.season)
+                .or_insert_with(|| SeasonCoverage {
+                    season: episode.season,
+                    ..Default::default()
+                });
+            let owners = &medias[&(episode.season, episode.number)];
+            if !owners.is_empty() {
+                coverage.owned.push(episode.number);
+                if owners.len() > 1 {
+                    coverage.duplicated.push(DuplicatedEpisode {
+                        number: episode.number,
+                        medias: owners.clone(),
+                    });
+                }
+            } else if episode.airdate.is_some_and(|airdate| airdate <= now) {
+                coverage.missing.push(episode.number);
+            } else {
+                coverage.unaired.push(episode.number);
+            }
+        }
+        for coverage in seasons.values_mut() {
+            coverage.owned.sort_unstable();
+            coverage.owned.dedup();
+            coverage.missing.sort_unstable();
+            coverage.missing.dedup();
+            coverage.unaired.sort_unstable();
+            coverage.unaired.dedup();
+            coverage.duplicated.sort_by_key(|d| d.number);
+            coverage.duplicated.dedup_by_key(|d| d.number);
+        }
+
+        Self {
+            serie: serie.to_string(),
+            seasons: seasons.into_values().collect(),
+            unmatched,
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.seasons.iter().all(SeasonCoverage::is_complete)
+    }
+
+    /// `(season, number)` of every aired episode without media, for gap searches.
+    pub fn missing_episodes(&self) -> Vec<(u32, u32)> {
+        self.seasons
+            .iter()
+            .flat_map(|s| s.missing.iter().map(move |n| (s.season, *n)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn episode(season: u32, number: u32, abs: Option<u32>, airdate: Option<i64>) -> Episode {
+        Episode {
+            serie: "serie-1".to_string(),
+            season,
+            number,
+            abs,
+            airdate,
+            ..Default::default()
+        }
+    }
+
+    fn file(
+        media: &str,
+        serie: &str,
+        season: Option<u32>,
+        episode: Option<u32>,
+        episode_to: Option<u32>,
+    ) -> (String, FileEpisode) {
+        (
+            media.to_string(),
+            FileEpisode {
+                id: serie.to_string(),
+                season,
+                episode,
+                episode_to,
+            },
+        )
+    }
+
+    #[test]
+    fn coverage_per_season() {
+        let episodes = vec![
+            episode(1, 1, Some(1), Some(100)),
+            episode(1, 2, Some(2), Some(200)),
+            episode(1, 3, Some(3), Some(300)),
+            episode(2, 1, Some(4), Some(400)),
+            episode(2, 2, Some(5), Some(500)),
+            episode(2, 3, Some(6), None),
+        ];
+        let files = vec![
+            // double episode file
+            file("m1", "serie-1", Some(1), Some(1), Some(2)),
+            file("m2", "serie-1", Some(1), Some(2), None),
+            // absolute numbering
+            file("m3", "serie-1", None, Some(4), None),
+            file("m4", "serie-1", Some(2), None, None),
+            file("m5", "serie-1", Some(9), Some(1), None),
+            file("m6", "serie-2", Some(1), Some(3), None),
+        ];
+        let coverage = SerieCoverage::compute("serie-1", &episodes, &files, 450);
+
+        assert_eq!(coverage.seasons.len(), 2);
+        let s1 = &coverage.seasons[0];
+        assert_eq!(s1.owned, vec![1, 2]);
+        assert_eq!(s1.missing, vec![3]);
+        assert_eq!(
+            s1.duplicated,
+            vec![DuplicatedEpisode {
+                number: 2,
+                medias: vec!["m1".to_string(), "m2".to_string()]
+            }]
+        );
+        let s2 = &coverage.seasons[1];
+        assert_eq!(s2.owned, vec![1]);
+        assert!(s2.missing.is_empty());
+        assert_eq!(s2.unaired, vec![2, 3]);
+        assert!(s2.is_complete());
+
+        assert!(!coverage.is_complete());
+        assert_eq!(coverage.missing_episodes(), vec![(1, 3)]);
+        assert_eq!(
+            coverage
+                .unmatched
+                .iter()
+                .map(|u| u.media.as_str())
+                .collect::<Vec<_>>(),
+            vec!["m4", "m5"]
+        );
+    }
+
+    #[test]
+    fn coverage_clamps_file_ranges() {
+        let episodes = vec![episode(1, 1, Some(1), Some(100)), episode(1, 2, Some(2), Some(200))];
+        let files = vec![
+            file("m1", "serie-1", Some(1), Some(1), Some(u32::MAX)),
+            file("m2", "serie-1", None, Some(2), Some(u32::MAX)),
+            file("m3", "serie-1", Some(1), Some(5), Some(u32::MAX)),
+        ];
+        let coverage = SerieCoverage::compute("serie-1", &episodes, &files, 450);
+        assert_eq!(coverage.seasons[0].owned, vec![1, 2]);
+        assert_eq!(coverage.unmatched.len(), 1);
+        assert_eq!(coverage.unmatched[0].media, "m3");
+    }
+}
@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
+use crate::domain::{
+    book::Book, element_type::ElementType, movie::Movie, rs_ids::RsIds, serie::Serie,
+};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone, Display, EnumString)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum CreditRole {
+    #[default]
+    Cast,
+    Director,
+    Writer,
+    Producer,
+    Composer,
+    Creator,
+    Author,
+    Illustrator,
+    Translator,
+    #[strum(default)]
+    Other(String),
+}
+
+/// Participation of a person in a movie, serie, episode or book.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Credit {
+    /// Person id (local id or `key:value` external id)
+    pub person: String,
+    pub role: CreditRole,
+    /// Provider department (`Directing`, `Camera`, `Sound`...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub department: Option<String>,
+    /// Character played, for cast
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub character: Option<String>,
+    /// Billing order, lowest first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<u32>,
+}
+
+impl Credit {
+    pub fn is_cast(&self) -> bool {
+        self.role == CreditRole::Cast
+    }
+}
+
+/// Sort credits cast first, then by billing order (credits without order last).
+pub fn sort_credits(credits: &mut [Credit]) {
+    credits.sort_by_key(|c| (!c.is_cast(), c.order.is_none(), c.order));
+}
+
+/// A work a person is credited in, as returned by a person lookup.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FilmographyEntry {
+    /// `Movie`, `Serie` or `Book`
+    #[serde(rename = "type")]
+    pub kind: ElementType,
+    pub ids: RsIds,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<u16>,
+    pub role: CreditRole,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub department: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub character: Option<String>,
+    /// Number of episodes the person appears in, for series
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub episodes: Option<u32>,
+}
+
+impl FilmographyEntry {
+    pub fn from_movie(movie: &Movie, role: CreditRole) -> Self {
+        Self {
+            kind: ElementType::Movie,
+            ids: RsIds::from(movie.clone()),
+            name: movie.name.clone(),
+            year: movie.year,
+            role,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_serie(serie: &Serie, role: CreditRole) -> Self {
+        Self {
+            kind: ElementType::Serie,
+            ids: RsIds::from(serie.clone()),
+            name: serie.name.clone(),
+            year: serie.year,
+            role,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_book(book: &Book, role: CreditRole) -> Self {
+        Self {
+            kind: ElementType::Book,
+            ids: RsIds::from(book.clone()),
+            name: book.name.clone(),
+            year: book.year,
+            role,
+            ..Default::default()
+        }
+    }
+
+    /// True when the entry refers to a library item of the same type sharing an id.
+    pub fn matches(&self, kind: &ElementType, ids: &RsIds) -> bool {
+        &self.kind == kind && self.ids.has_common_id(ids)
+    }
+
+    /// Credit of `person` in this work.
+    pub fn to_credit(&self, person: &str) -> Credit {
+        Credit {
+            person: person.to_string(),
+            role: self.role.clone(),
+            department: self.department.clone(),
+            character: self.character.clone(),
+            order: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn credit_serialization_and_sort() {
+        let mut credits = vec![
+            Credit {
+                person: "tmdb:525".to_string(),
+                role: CreditRole::Director,
+                department: Some("Directing".to_string()),
+                ..Default::default()
+            },
+            Credit {
+                person: "tmdb:3".to_string(),
+                character: Some("Neo".to_string()),
+                order: Some(1),
+                ..Default::default()
+            },
+            Credit {
+                person: "tmdb:2".to_string(),
+                character: Some("Trinity".to_string()),
+                order: Some(0),
+                ..Default::default()
+            },
+        ];
+        sort_credits(&mut credits);
+        assert_eq!(
+            credits
+                .iter()
+                .map(|c| c.person.as_str())
+                .collect::<Vec<_>>(),
+            vec!["tmdb:2", "tmdb:3", "tmdb:525"]
+        );
+
+        let value = serde_json::to_value(&credits[2]).unwrap();
+        assert_eq!(
+            value,
+            json!({ "person": "tmdb:525", "role": "director", "department": "Directing" })
+        );
+        assert_eq!(
+            "letterer".parse::<CreditRole>().unwrap(),
+            CreditRole::Other("letterer".to_string())
+        );
+    }
+
+    #[test]
+    fn filmography_links_library_items_by_ids() {
+        let movie = Movie {
+            id: "tmdb:603".to_string(),
+            name: "The Matrix".to_string(),
+            year: Some(1999),
+            tmdb: Some(603),
+            ..Default::default()
+        };
+        let mut entry = FilmographyEntry::from_movie(&movie, CreditRole::Cast);
+        entry.character = Some("Neo".to_string());
+
+        let library = Movie {
+            id: "local-1".to_string(),
+            tmdb: Some(603),
+            ..Default::default()
+        };
+        assert!(entry.matches(&ElementType::Movie, &RsIds::from(library)));
+        assert!(!entry.matches(&ElementType::Serie, &RsIds::from_tmdb(603)));
+
+        let credit = entry.to_credit("person-1");
+        assert!(credit.is_cast());
+        assert_eq!(credit.character.as_deref(), Some("Neo"));
+    }
+}
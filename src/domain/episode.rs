@@ -1,13 +1,15 @@
 use crate::domain::{
     other_ids::OtherIds,
-    rs_ids::{ApplyRsIds, RsIds},
+    rs_ids::ApplyRsIds,
     tools::rating_serializer,
+    update::{append_vec, diff_list, diff_other_ids, overwrite_if_some, ChangeSummary, Patch},
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, ApplyRsIds)]
 #[serde(rename_all = "camelCase")]
+#[rs_ids(redseat_fn = "id")]
 pub struct Episode {
     pub serie: String,
     pub season: u32,
@@ -30,11 +32,17 @@ pub struct Episode {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub params: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("imdb")]
     pub imdb: Option<String>,
+    #[rs_id("slug")]
     pub slug: Option<String>,
+    #[rs_id("tmdb")]
     pub tmdb: Option<u64>,
+    #[rs_id("trakt")]
     pub trakt: Option<u64>,
+    #[rs_id("tvdb")]
     pub tvdb: Option<u64>,
+    #[rs_id(otherids)]
     pub otherids: Option<OtherIds>,
 
     #[serde(serialize_with = "rating_serializer")]
@@ -64,59 +72,145 @@ impl Episode {
     }
 }
 
-impl From<Episode> for RsIds {
-    fn from(value: Episode) -> Self {
-        let id = value.id();
-        let mut ids = RsIds::default();
-        if let Some(v) = value.trakt { ids.set("trakt", v); }
-        if let Some(v) = value.slug { ids.set("slug", v); }
-        if let Some(v) = value.tvdb { ids.set("tvdb", v); }
-        if let Some(v) = value.imdb { ids.set("imdb", v); }
-        if let Some(v) = value.tmdb { ids.set("tmdb", v); }
-        if let Some(other) = value.otherids {
-            for entry in other.into_vec() {
-                let _ = ids.try_add(entry);
-            }
-        }
-        if ids.try_add(id.clone()).is_err() {
-            ids.set("redseat", id);
-        }
-        ids
-    }
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeForUpdate {
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub abs: Patch<u32>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub name: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub overview: Patch<String>,
+    pub alt: Option<Vec<String>>,
+    pub add_alts: Option<Vec<String>>,
+    pub remove_alts: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub airdate: Patch<i64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub duration: Patch<u64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub params: Patch<Value>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub imdb: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub slug: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub tmdb: Patch<u64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub trakt: Patch<u64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub tvdb: Patch<u64>,
+    pub otherids: Option<OtherIds>,
+    pub add_otherids: Option<Vec<String>>,
+    pub remove_otherids: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub imdb_rating: Patch<f32>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub imdb_votes: Patch<u64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub trakt_rating: Patch<f32>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub trakt_votes: Patch<u64>,
 }
 
-impl ApplyRsIds for Episode {
-    fn apply_rs_ids(&mut self, ids: &RsIds) {
-        if let Some(trakt) = ids.trakt() {
-            self.trakt = Some(trakt);
-        }
-        if let Some(slug) = ids.slug() {
-            self.slug = Some(slug.to_string());
-        }
-        if let Some(tvdb) = ids.tvdb() {
-            self.tvdb = Some(tvdb);
-        }
-        if let Some(imdb) = ids.imdb() {
-            self.imdb = Some(imdb.to_string());
-        }
-        if let Some(tmdb) = ids.tmdb() {
-            self.tmdb = Some(tmdb);
-        }
-        let known: &[&str] = &["trakt", "slug", "tvdb", "imdb", "tmdb"];
-        let mut other = self.otherids.take().unwrap_or_default();
-        for (k, v) in ids.iter() {
-            if !known.contains(&k.as_str()) { other.add(k, v); }
+impl EpisodeForUpdate {
+    pub fn merge_from(&mut self, patch: Self) {
+        self.abs.merge_from(patch.abs);
+        self.name.merge_from(patch.name);
+        self.overview.merge_from(patch.overview);
+        overwrite_if_some(&mut self.alt, patch.alt);
+        append_vec(&mut self.add_alts, patch.add_alts);
+        append_vec(&mut self.remove_alts, patch.remove_alts);
+        self.airdate.merge_from(patch.airdate);
+        self.duration.merge_from(patch.duration);
+        self.params.merge_from(patch.params);
+        self.imdb.merge_from(patch.imdb);
+        self.slug.merge_from(patch.slug);
+        self.tmdb.merge_from(patch.tmdb);
+        self.trakt.merge_from(patch.trakt);
+        self.tvdb.merge_from(patch.tvdb);
+        overwrite_if_some(&mut self.otherids, patch.otherids);
+        append_vec(&mut self.add_otherids, patch.add_otherids);
+        append_vec(&mut self.remove_otherids, patch.remove_otherids);
+        self.imdb_rating.merge_from(patch.imdb_rating);
+        self.imdb_votes.merge_from(patch.imdb_votes);
+        self.trakt_rating.merge_from(patch.trakt_rating);
+        self.trakt_votes.merge_from(patch.trakt_votes);
+    }
+
+    /// Apply the patch to `episode`; `serie`, `season` and `number` identify it and are not
+    /// part of the patch.
+    pub fn apply_to(&self, episode: &mut Episode) -> ChangeSummary {
+        let mut changes = ChangeSummary::default();
+        changes.set_patch("abs", &mut episode.abs, self.abs);
+        changes.set_patch("name", &mut episode.name, self.name.clone());
+        changes.set_patch("overview", &mut episode.overview, self.overview.clone());
+        changes.set_list(
+            "alt",
+            &mut episode.alt,
+            self.alt.clone(),
+            self.add_alts.clone(),
+            self.remove_alts.clone(),
+        );
+        changes.set_patch("airdate", &mut episode.airdate, self.airdate);
+        changes.set_patch("duration", &mut episode.duration, self.duration);
+        changes.set_patch("params", &mut episode.params, self.params.clone());
+        changes.set_patch("imdb", &mut episode.imdb, self.imdb.clone());
+        changes.set_patch("slug", &mut episode.slug, self.slug.clone());
+        changes.set_patch("tmdb", &mut episode.tmdb, self.tmdb);
+        changes.set_patch("trakt", &mut episode.trakt, self.trakt);
+        changes.set_patch("tvdb", &mut episode.tvdb, self.tvdb);
+        changes.set_other_ids(
+            "otherids",
+            &mut episode.otherids,
+            self.otherids.clone(),
+            self.add_otherids.clone(),
+            self.remove_otherids.clone(),
+        );
+        changes.set_patch("imdbRating", &mut episode.imdb_rating, self.imdb_rating);
+        changes.set_patch("imdbVotes", &mut episode.imdb_votes, self.imdb_votes);
+        changes.set_patch("traktRating", &mut episode.trakt_rating, self.trakt_rating);
+        changes.set_patch("traktVotes", &mut episode.trakt_votes, self.trakt_votes);
+        changes
+    }
+
+    /// Patch turning `old` into `new`. Values removed in `new` are cleared.
+    pub fn diff(old: &Episode, new: &Episode) -> Self {
+        let (add_alts, remove_alts) = diff_list(&old.alt, &new.alt);
+        let (add_otherids, remove_otherids) = diff_other_ids(&old.otherids, &new.otherids);
+        Self {
+            abs: Patch::diff(&old.abs, &new.abs),
+            name: Patch::diff(&old.name, &new.name),
+            overview: Patch::diff(&old.overview, &new.overview),
+            alt: None,
+            add_alts,
+            remove_alts,
+            airdate: Patch::diff(&old.airdate, &new.airdate),
+            duration: Patch::diff(&old.duration, &new.duration),
+            params: Patch::diff(&old.params, &new.params),
+            imdb: Patch::diff(&old.imdb, &new.imdb),
+            slug: Patch::diff(&old.slug, &new.slug),
+            tmdb: Patch::diff(&old.tmdb, &new.tmdb),
+            trakt: Patch::diff(&old.trakt, &new.trakt),
+            tvdb: Patch::diff(&old.tvdb, &new.tvdb),
+            otherids: None,
+            add_otherids,
+            remove_otherids,
+            imdb_rating: Patch::diff(&old.imdb_rating, &new.imdb_rating),
+            imdb_votes: Patch::diff(&old.imdb_votes, &new.imdb_votes),
+            trakt_rating: Patch::diff(&old.trakt_rating, &new.trakt_rating),
+            trakt_votes: Patch::diff(&old.trakt_votes, &new.trakt_votes),
         }
-        if !other.as_slice().is_empty() { self.otherids = Some(other); }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Episode;
+    use super::{Episode, EpisodeForUpdate};
     use crate::domain::{
         other_ids::OtherIds,
         rs_ids::{ApplyRsIds, RsIds},
+        update::Patch,
     };
     use serde_json::json;
 
@@ -173,4 +267,68 @@ mod tests {
         assert_eq!(episode.slug.as_deref(), Some("episode-slug"));
         assert_eq!(episode.tmdb, Some(22));
     }
+
+    #[test]
+    fn episode_rs_ids_roundtrip() {
+        let episode = Episode {
+            serie: "serie-1".to_string(),
+            season: 2,
+            number: 3,
+            imdb: Some("tt0000003".to_string()),
+            slug: Some("episode-slug".to_string()),
+            tmdb: Some(31),
+            trakt: Some(32),
+            tvdb: Some(33),
+            otherids: Some(OtherIds(vec!["tvmaze:34".to_string()])),
+            ..Default::default()
+        };
+        let ids = RsIds::from(episode.clone());
+        assert_eq!(ids.redseat(), Some("serie-1x2x3"));
+
+        let mut restored = Episode {
+            serie: "serie-1".to_string(),
+            season: 2,
+            number: 3,
+            ..Default::default()
+        };
+        restored.apply_rs_ids(&ids);
+        assert_eq!(restored.imdb, episode.imdb);
+        assert_eq!(restored.slug, episode.slug);
+        assert_eq!(restored.tmdb, episode.tmdb);
+        assert_eq!(restored.trakt, episode.trakt);
+        assert_eq!(restored.tvdb, episode.tvdb);
+        // The redseat id is derived from serie/season/number and must not leak into otherids
+        assert_eq!(restored.otherids, episode.otherids);
+    }
+
+    #[test]
+    fn episode_update_merge_and_apply() {
+        let mut patch = EpisodeForUpdate {
+            name: Patch::Set("Pilot".to_string()),
+            add_alts: Some(vec!["Episode 1".to_string()]),
+            ..Default::default()
+        };
+        patch.merge_from(EpisodeForUpdate {
+            name: Patch::Set("The Pilot".to_string()),
+            add_alts: Some(vec!["Ep. 1".to_string()]),
+            ..Default::default()
+        });
+        let mut episode = Episode {
+            serie: "serie-1".to_string(),
+            season: 1,
+            number: 1,
+            ..Default::default()
+        };
+        let changes = patch.apply_to(&mut episode);
+        assert_eq!(episode.name.as_deref(), Some("The Pilot"));
+        assert_eq!(
+            episode.alt,
+            Some(vec!["Episode 1".to_string(), "Ep. 1".to_string()])
+        );
+        assert_eq!(changes.fields, vec!["name", "alt"]);
+        assert_eq!(
+            EpisodeForUpdate::diff(&episode, &episode),
+            EpisodeForUpdate::default()
+        );
+    }
 }
@@ -0,0 +1,323 @@
+//! Comparison, matching and clustering of [`FaceEmbedding`]s.
+//!
+//! Similarities are cosine similarities in `[-1, 1]`, higher is closer. Embeddings of
+//! different lengths (different models) are never compared.
+
+use serde::{Deserialize, Serialize};
+
+use super::media::{FaceBBox, FaceEmbedding};
+
+/// Default minimum similarity for a face to be matched to a person or clustered with another
+/// face, suited to ArcFace-like models.
+pub const DEFAULT_FACE_SIMILARITY: f32 = 0.5;
+
+/// Cosine similarity, `None` when lengths differ or a vector is null.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return None;
+    }
+    Some((dot / (norm_a.sqrt() * norm_b.sqrt())).clamp(-1.0, 1.0))
+}
+
+/// Euclidean distance, `None` when lengths differ.
+pub fn l2_distance(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.len() != b.len() {
+        return None;
+    }
+    Some(
+        a.iter()
+            .zip(b)
+            .map(|(x, y)| (x - y) * (x - y))
+            .sum::<f32>()
+            .sqrt(),
+    )
+}
+
+/// Scale `vector` to a unit length (unchanged if null).
+pub fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+impl FaceBBox {
+    pub fn width(&self) -> f32 {
+        (self.x2 - self.x1).abs()
+    }
+
+    pub fn height(&self) -> f32 {
+        (self.y2 - self.y1).abs()
+    }
+
+    pub fn area(&self) -> f32 {
+        self.width() * self.height()
+    }
+}
+
+impl FaceEmbedding {
+    pub fn similarity(&self, other: &FaceEmbedding) -> Option<f32> {
+        cosine_similarity(&self.embedding, &other.embedding)
+    }
+
+    pub fn distance(&self, other: &FaceEmbedding) -> Option<f32> {
+        l2_distance(&self.embedding, &other.embedding)
+    }
+
+    /// Closest labeled face (with a `person_id`) with a similarity of at least `threshold`.
+    pub fn match_person(&self, labeled: &[FaceEmbedding], threshold: f32) -> Option<FaceMatch> {
+        labeled
+            .iter()
+            .filter(|face| face.id != self.id)
+            .filter_map(|face| {
+                let person_id = face.person_id.as_ref()?;
+                let similarity = self.similarity(face)?;
+                (similarity >= threshold).then(|| FaceMatch {
+                    person_id: person_id.clone(),
+                    face_id: face.id.clone(),
+                    similarity,
+                })
+            })
+            .max_by(|a, b| a.similarity.total_cmp(&b.similarity))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceMatch {
+    pub person_id: String,
+    /// Labeled face the match was made on
+    pub face_id: String,
+    pub similarity: f32,
+}
+
+/// Faces good enough to be matched or clustered. Faces missing a checked value pass.
+///
+/// `pose` is `(yaw, pitch, roll)` in degrees, and sizes are in `bbox` units.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceQualityFilter {
+    pub min_confidence: Option<f32>,
+    /// Minimum of the bbox width and height
+    pub min_size: Option<f32>,
+    pub max_yaw: Option<f32>,
+    pub max_pitch: Option<f32>,
+    pub max_roll: Option<f32>,
+}
+
+impl Default for FaceQualityFilter {
+    fn default() -> Self {
+        FaceQualityFilter {
+            min_confidence: Some(0.6),
+            min_size: None,
+            max_yaw: Some(45.0),
+            max_pitch: Some(30.0),
+            max_roll: None,
+        }
+    }
+}
+
+impl FaceQualityFilter {
+    pub fn accepts(&self, face: &FaceEmbedding) -> bool {
+        let at_least = |min: Option<f32>, value: Option<f32>| match (min, value) {
+            (Some(min), Some(value)) => value >= min,
+            _ => true,
+        };
+        let at_most = |max: Option<f32>, value: Option<f32>| match (max, value) {
+            (Some(max), Some(value)) => value.abs() <= max,
+            _ => true,
+        };
+        let bbox = face.bbox.as_ref();
+        let pose = face.pose;
+        at_least(self.min_confidence, face.confidence)
+            && at_least(self.min_size, bbox.map(|b| b.width().min(b.height())))
+            && at_most(self.max_yaw, pose.map(|p| p.0))
+            && at_most(self.max_pitch, pose.map(|p| p.1))
+            && at_most(self.max_roll, pose.map(|p| p.2))
+    }
+
+    pub fn filter<'a>(&self, faces: &'a [FaceEmbedding]) -> Vec<&'a FaceEmbedding> {
+        faces.iter().filter(|face| self.accepts(face)).collect()
+    }
+}
+
+/// Propose new person groups from unlabeled faces, as lists of face ids (DBSCAN).
+///
+/// Two faces are neighbours with a similarity of at least `threshold`; a group needs a face
+/// with `min_faces` neighbours or more (itself included), other faces are left out. Faces
+/// already having a `person_id` are ignored. Groups are sorted by size, largest first.
+pub fn cluster_faces(
+    faces: &[FaceEmbedding],
+    threshold: f32,
+    min_faces: usize,
+) -> Vec<Vec<String>> {
+    let faces: Vec<&FaceEmbedding> = faces.iter().filter(|f| f.person_id.is_none()).collect();
+    let neighbours: Vec<Vec<usize>> = faces
+        .iter()
+        .map(|face| {
+            faces
+                .iter()
+                .enumerate()
+                .filter(|(_, other)| face.similarity(other).is_some_and(|s| s >= threshold))
+                .map(|(index, _)| index)
+                .collect()
+        })
+        .collect();
+
+    let mut clusters: Vec<Option<usize>> = vec![None; faces.len()];
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for start in 0..faces.len() {
+        if clusters[start].is_some() || neighbours[start].len() < min_faces {
+            continue;
+        }
+        let group = groups.len();
+        let mut members = vec![];
+        let mut queue = vec![start];
+        clusters[start] = Some(group);
+        while let Some(index) = queue.pop() {
+            members.push(index);
+            // border faces join the group but do not expand it
+            if neighbours[index].len() < min_faces {
+                continue;
+            }
+            for &neighbour in &neighbours[index] {
+                if clusters[neighbour].is_none() {
+                    clusters[neighbour] = Some(group);
+                    queue.push(neighbour);
+                }
+            }
+        }
+        members.sort();
+        groups.push(members);
+    }
+
+    groups.sort_by_key(|members| std::cmp::Reverse(members.len()));
+    groups
+        .into_iter()
+        .map(|members| members.into_iter().map(|i| faces[i].id.clone()).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn face(id: &str, embedding: Vec<f32>, person_id: Option<&str>) -> FaceEmbedding {
+        FaceEmbedding {
+            id: id.to_string(),
+            embedding,
+            person_id: person_id.map(|p| p.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn similarities() {
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]), Some(1.0));
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]), Some(0.0));
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[-1.0, 0.0]), Some(-1.0));
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[1.0, 0.0, 0.0]), None);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), None);
+        assert_eq!(l2_distance(&[0.0, 3.0], &[4.0, 0.0]), Some(5.0));
+
+        let mut vector = vec![3.0, 4.0];
+        normalize(&mut vector);
+        assert_eq!(vector, vec![0.6, 0.8]);
+    }
+
+    #[test]
+    fn matches_nearest_person() {
+        let labeled = vec![
+            face("l1", vec![1.0, 0.0, 0.0], Some("alice")),
+            face("l2", vec![0.9, 0.1, 0.0], Some("alice")),
+            face("l3", vec![0.0, 1.0, 0.0], Some("bob")),
+            face("l4", vec![0.95, 0.05, 0.0], None),
+            face("l5", vec![1.0, 0.0], Some("carol")),
+        ];
+        let unknown = face("u1", vec![0.1, 0.9, 0.1], None);
+        let found = unknown
+            .match_person(&labeled, DEFAULT_FACE_SIMILARITY)
+            .unwrap();
+        assert_eq!(
+            (found.person_id.as_str(), found.face_id.as_str()),
+            ("bob", "l3")
+        );
+
+        let alice = face("u2", vec![0.92, 0.08, 0.0], None);
+        assert_eq!(alice.match_person(&labeled, 0.9).unwrap().face_id, "l2");
+        let nobody = face("u3", vec![0.0, 0.0, 1.0], None);
+        assert_eq!(nobody.match_person(&labeled, DEFAULT_FACE_SIMILARITY), None);
+    }
+
+    #[test]
+    fn filters_quality() {
+        let filter = FaceQualityFilter {
+            min_size: Some(40.0),
+            ..Default::default()
+        };
+        let mut good = face("good", vec![1.0], None);
+        good.confidence = Some(0.9);
+        good.pose = Some((10.0, -5.0, 30.0));
+        good.bbox = Some(FaceBBox {
+            x1: 10.0,
+            y1: 10.0,
+            x2: 60.0,
+            y2: 70.0,
+            ..Default::default()
+        });
+        assert!(filter.accepts(&good));
+        assert!(filter.accepts(&face("unknown", vec![1.0], None)));
+
+        let profile = FaceEmbedding {
+            pose: Some((-60.0, 0.0, 0.0)),
+            ..good.clone()
+        };
+        let blurry = FaceEmbedding {
+            confidence: Some(0.3),
+            ..good.clone()
+        };
+        let small = FaceEmbedding {
+            bbox: Some(FaceBBox {
+                x2: 30.0,
+                ..good.bbox.clone().unwrap()
+            }),
+            ..good.clone()
+        };
+        let faces = vec![good, profile, blurry, small];
+        let kept: Vec<&str> = filter
+            .filter(&faces)
+            .iter()
+            .map(|f| f.id.as_str())
+            .collect();
+        assert_eq!(kept, vec!["good"]);
+        assert_eq!(faces[3].bbox.as_ref().unwrap().area(), 20.0 * 60.0);
+    }
+
+    #[test]
+    fn clusters_unlabeled_faces() {
+        let faces = vec![
+            face("a1", vec![1.0, 0.0, 0.0], None),
+            face("b1", vec![0.0, 1.0, 0.0], None),
+            face("a2", vec![0.95, 0.1, 0.0], None),
+            face("noise", vec![0.0, 0.0, 1.0], None),
+            face("a3", vec![0.9, 0.0, 0.1], None),
+            face("b2", vec![0.1, 0.95, 0.0], None),
+            face("labeled", vec![1.0, 0.0, 0.0], Some("alice")),
+        ];
+        assert_eq!(
+            cluster_faces(&faces, 0.9, 2),
+            vec![vec!["a1", "a2", "a3"], vec!["b1", "b2"]]
+        );
+        assert_eq!(cluster_faces(&faces, 0.9, 3), vec![vec!["a1", "a2", "a3"]]);
+        assert_eq!(cluster_faces(&faces, 0.9, 1).len(), 3);
+    }
+}
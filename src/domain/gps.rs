@@ -0,0 +1,456 @@
+//! GPS positions: parsing, formatting, distances and geohash.
+//!
+//! Positions are stored as `"lat,long"` in decimal degrees (WGS 84).
+
+use std::{fmt, str::FromStr};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Mean earth radius in meters
+pub const EARTH_RADIUS: f64 = 6_371_008.8;
+
+const GEOHASH_ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+#[derive(Debug, Serialize, strum_macros::AsRefStr)]
+pub enum GpsError {
+    InvalidFormat(String),
+    LatitudeOutOfRange(f64),
+    LongitudeOutOfRange(f64),
+}
+
+// region:    --- Error Boilerplate
+
+impl core::fmt::Display for GpsError {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for GpsError {}
+
+// endregion: --- Error Boilerplate
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RsGpsPosition {
+    pub lat: f64,
+    pub long: f64,
+}
+
+impl RsGpsPosition {
+    pub fn new(lat: f64, long: f64) -> Result<Self, GpsError> {
+        let position = Self { lat, long };
+        position.validate()?;
+        Ok(position)
+    }
+
+    pub fn validate(&self) -> Result<(), GpsError> {
+        if !(-90.0..=90.0).contains(&self.lat) {
+            return Err(GpsError::LatitudeOutOfRange(self.lat));
+        }
+        if !(-180.0..=180.0).contains(&self.long) {
+            return Err(GpsError::LongitudeOutOfRange(self.long));
+        }
+        Ok(())
+    }
+
+    /// Position from EXIF `GPSLatitude`/`GPSLongitude` rationals (degrees, minutes, seconds)
+    /// and their `S`/`W` references.
+    pub fn from_exif(
+        lat: &[(u32, u32)],
+        lat_ref: &str,
+        long: &[(u32, u32)],
+        long_ref: &str,
+    ) -> Result<Self, GpsError> {
+        fn degrees(parts: &[(u32, u32)], negative: bool) -> Result<f64, GpsError> {
+            let values = parts
+                .iter()
+                .map(|(num, denom)| match denom {
+                    0 => Err(GpsError::InvalidFormat(format!("{}/0", num))),
+                    _ => Ok(*num as f64 / *denom as f64),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let [degrees, minutes, seconds] = match values[..] {
+                [d] => [d, 0.0, 0.0],
+                [d, m] => [d, m, 0.0],
+                [d, m, s] => [d, m, s],
+                _ => return Err(GpsError::InvalidFormat(format!("{:?}", parts))),
+            };
+            let value = degrees + minutes / 60.0 + seconds / 3600.0;
+            Ok(if negative { -value } else { value })
+        }
+        Self::new(
+            degrees(lat, lat_ref.trim().eq_ignore_ascii_case("S"))?,
+            degrees(long, long_ref.trim().eq_ignore_ascii_case("W"))?,
+        )
+    }
+
+    /// Degrees, minutes and seconds: `48°51'24.0"N 2°21'3.0"E`.
+    pub fn to_dms_string(&self) -> String {
+        fn dms(value: f64, positive: char, negative: char) -> String {
+            let hemisphere = if value < 0.0 { negative } else { positive };
+            let total = (value.abs() * 36000.0).round() as u64;
+            let (degrees, minutes, tenths) = (total / 36000, total % 36000 / 600, total % 600);
+            format!(
+                "{}°{}'{}.{}\"{}",
+                degrees,
+                minutes,
+                tenths / 10,
+                tenths % 10,
+                hemisphere
+            )
+        }
+        format!("{} {}", dms(self.lat, 'N', 'S'), dms(self.long, 'E', 'W'))
+    }
+
+    /// Great-circle (haversine) distance in meters.
+    pub fn distance_to(&self, other: &RsGpsPosition) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlong = (other.long - self.long).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlong / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * a.sqrt().min(1.0).asin()
+    }
+
+    pub fn is_within(&self, other: &RsGpsPosition, meters: f64) -> bool {
+        self.distance_to(other) <= meters
+    }
+
+    /// Geohash of `precision` characters (12 is below the centimeter).
+    pub fn geohash(&self, precision: usize) -> String {
+        let mut lat = (-90.0, 90.0);
+        let mut long = (-180.0, 180.0);
+        let mut hash = String::with_capacity(precision);
+        let mut bits = 0;
+        let mut index = 0;
+        let mut even = true;
+        while hash.len() < precision {
+            let (range, value) = if even {
+                (&mut long, self.long)
+            } else {
+                (&mut lat, self.lat)
+            };
+            let mid = (range.0 + range.1) / 2.0;
+            index <<= 1;
+            if value >= mid {
+                index |= 1;
+                range.0 = mid;
+            } else {
+                range.1 = mid;
+            }
+            even = !even;
+            bits += 1;
+            if bits == 5 {
+                hash.push(GEOHASH_ALPHABET[index] as char);
+                bits = 0;
+                index = 0;
+            }
+        }
+        hash
+    }
+
+    /// Center of a geohash cell.
+    pub fn from_geohash(hash: &str) -> Result<Self, GpsError> {
+        let mut lat = (-90.0, 90.0);
+        let mut long = (-180.0, 180.0);
+        let mut even = true;
+        for c in hash.to_lowercase().bytes() {
+            let index = GEOHASH_ALPHABET
+                .iter()
+                .position(|a| *a == c)
+                .ok_or_else(|| GpsError::InvalidFormat(hash.to_string()))?;
+            for bit in (0..5).rev() {
+                let range = if even { &mut long } else { &mut lat };
+                let mid = (range.0 + range.1) / 2.0;
+                if index >> bit & 1 == 1 {
+                    range.0 = mid;
+                } else {
+                    range.1 = mid;
+                }
+                even = !even;
+            }
+        }
+        Ok(Self {
+            lat: (lat.0 + lat.1) / 2.0,
+            long: (long.0 + long.1) / 2.0,
+        })
+    }
+}
+
+/// `lat,long` in decimal degrees, the storage format.
+impl fmt::Display for RsGpsPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.lat, self.long)
+    }
+}
+
+/// Parse decimal (`48.8566, 2.3522` or `48.8566 2.3522`) or DMS
+/// (`48°51'24"N 2°21'8"E`, `N 48° 51.4' E 2° 21.13'`) positions.
+impl FromStr for RsGpsPosition {
+    type Err = GpsError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let position = Self::parse_unchecked(value)?;
+        position.validate()?;
+        Ok(position)
+    }
+}
+
+impl RsGpsPosition {
+    /// Parse like [`FromStr`] without the range checks, to read back stored values.
+    pub(crate) fn parse_unchecked(value: &str) -> Result<Self, GpsError> {
+        let parts: Vec<&str> = value
+            .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .collect();
+        if let [lat, long] = parts[..] {
+            if let (Ok(lat), Ok(long)) = (lat.parse::<f64>(), long.parse::<f64>()) {
+                return Ok(Self { lat, long });
+            }
+        }
+
+        // hemispheres are either all prefixes or all suffixes, a single pattern would give the
+        // prefix of the longitude to the latitude as a suffix
+        const DMS: &str = r#"(-?\d+(?:\.\d+)?)\s*°\s*(?:(\d+(?:\.\d+)?)\s*['′]\s*)?(?:(\d+(?:\.\d+)?)\s*(?:"|″|'')\s*)?"#;
+        let re = if value
+            .trim_start()
+            .starts_with(['N', 'S', 'E', 'W', 'n', 's', 'e', 'w'])
+        {
+            Regex::new(&format!(r"(?i)([NSEW])\s*{}", DMS))
+        } else {
+            Regex::new(&format!(r"(?i)()\s*{}([NSEW])?", DMS))
+        }
+        .unwrap();
+        let coordinates: Vec<(f64, Option<char>)> = re
+            .captures_iter(value)
+            .map(|caps| {
+                let number = |index: usize| {
+                    caps.get(index)
+                        .and_then(|m| m.as_str().parse::<f64>().ok())
+                        .unwrap_or(0.0)
+                };
+                let degrees = number(2);
+                let value = degrees.abs() + number(3) / 60.0 + number(4) / 3600.0;
+                let hemisphere = caps
+                    .get(1)
+                    .filter(|m| !m.is_empty())
+                    .or(caps.get(5))
+                    .and_then(|m| m.as_str().to_uppercase().chars().next());
+                let negative = degrees < 0.0 || matches!(hemisphere, Some('S') | Some('W'));
+                (if negative { -value } else { value }, hemisphere)
+            })
+            .collect();
+        match coordinates[..] {
+            [(long, Some('E' | 'W')), (lat, _)] => Ok(Self { lat, long }),
+            [(lat, _), (long, _)] => Ok(Self { lat, long }),
+            _ => Err(GpsError::InvalidFormat(value.to_string())),
+        }
+    }
+}
+
+/// Latitude/longitude rectangle. `min_long > max_long` crosses the antimeridian.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GpsBoundingBox {
+    pub min_lat: f64,
+    pub min_long: f64,
+    pub max_lat: f64,
+    pub max_long: f64,
+}
+
+impl GpsBoundingBox {
+    /// Smallest box containing the circle of `meters` around `center`, to prefilter
+    /// `distance_to` queries (`lat BETWEEN ? AND ?`).
+    pub fn around(center: &RsGpsPosition, meters: f64) -> Self {
+        let dlat = (meters / EARTH_RADIUS).to_degrees();
+        let min_lat = center.lat - dlat;
+        let max_lat = center.lat + dlat;
+        if min_lat <= -90.0 || max_lat >= 90.0 {
+            return Self {
+                min_lat: min_lat.max(-90.0),
+                min_long: -180.0,
+                max_lat: max_lat.min(90.0),
+                max_long: 180.0,
+            };
+        }
+        let dlong = dlat / center.lat.to_radians().cos();
+        if dlong >= 180.0 {
+            return Self {
+                min_lat,
+                min_long: -180.0,
+                max_lat,
+                max_long: 180.0,
+            };
+        }
+        let wrap = |long: f64| {
+            if long < -180.0 {
+                long + 360.0
+            } else if long > 180.0 {
+                long - 360.0
+            } else {
+                long
+            }
+        };
+        Self {
+            min_lat,
+            min_long: wrap(center.long - dlong),
+            max_lat,
+            max_long: wrap(center.long + dlong),
+        }
+    }
+
+    pub fn contains(&self, position: &RsGpsPosition) -> bool {
+        let in_lat = (self.min_lat..=self.max_lat).contains(&position.lat);
+        let in_long = if self.min_long <= self.max_long {
+            (self.min_long..=self.max_long).contains(&position.long)
+        } else {
+            position.long >= self.min_long || position.long <= self.max_long
+        };
+        in_lat && in_long
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_positions() {
+        let paris = RsGpsPosition::new(48.8566, 2.3522).unwrap();
+        assert_eq!("48.8566, 2.3522".parse::<RsGpsPosition>().unwrap(), paris);
+        assert_eq!("48.8566 2.3522".parse::<RsGpsPosition>().unwrap(), paris);
+        assert_eq!(paris.to_string().parse::<RsGpsPosition>().unwrap(), paris);
+
+        let dms: RsGpsPosition = "48°51'24\"N 2°21'8\"E".parse().unwrap();
+        assert!(dms.distance_to(&paris) < 10.0);
+        let dms: RsGpsPosition = "N 48° 51.4' W 2° 21.13'".parse().unwrap();
+        assert!((dms.long + 2.3522).abs() < 0.001);
+        let swapped: RsGpsPosition = "2°21'8\"E, 48°51'24\"N".parse().unwrap();
+        assert!(swapped.distance_to(&paris) < 10.0);
+        assert_eq!(paris.to_dms_string(), "48°51'23.8\"N 2°21'7.9\"E");
+        assert!(RsGpsPosition::from_str(
+            &RsGpsPosition::new(-33.8688, -151.2093)
+                .unwrap()
+                .to_dms_string()
+        )
+        .unwrap()
+        .is_within(&RsGpsPosition::new(-33.8688, -151.2093).unwrap(), 5.0));
+
+        assert!(matches!(
+            "91,0".parse::<RsGpsPosition>(),
+            Err(GpsError::LatitudeOutOfRange(_))
+        ));
+        assert!(matches!(
+            "0,181".parse::<RsGpsPosition>(),
+            Err(GpsError::LongitudeOutOfRange(_))
+        ));
+        assert!(matches!(
+            "somewhere".parse::<RsGpsPosition>(),
+            Err(GpsError::InvalidFormat(_))
+        ));
+
+        let exif = RsGpsPosition::from_exif(
+            &[(48, 1), (51, 1), (2400, 100)],
+            "N",
+            &[(2, 1), (21, 1), (800, 100)],
+            "W",
+        )
+        .unwrap();
+        assert!((exif.lat - 48.8567).abs() < 0.0001);
+        assert!((exif.long + 2.3522).abs() < 0.0001);
+        assert!(RsGpsPosition::from_exif(&[(48, 0)], "N", &[(2, 1)], "E").is_err());
+    }
+
+    #[test]
+    fn distances_and_boxes() {
+        let paris = RsGpsPosition::new(48.8566, 2.3522).unwrap();
+        let london = RsGpsPosition::new(51.5074, -0.1278).unwrap();
+        let distance = paris.distance_to(&london);
+        assert!((distance - 343_500.0).abs() < 1_000.0, "{}", distance);
+        assert!(!paris.is_within(&london, 300_000.0));
+
+        let bbox = GpsBoundingBox::around(&paris, 400_000.0);
+        assert!(bbox.contains(&london));
+        assert!(!GpsBoundingBox::around(&paris, 200_000.0).contains(&london));
+
+        let fiji = RsGpsPosition::new(-17.7, 179.9).unwrap();
+        let bbox = GpsBoundingBox::around(&fiji, 50_000.0);
+        assert!(bbox.min_long > bbox.max_long);
+        assert!(bbox.contains(&RsGpsPosition::new(-17.7, -179.9).unwrap()));
+        assert!(!bbox.contains(&RsGpsPosition::new(-17.7, 170.0).unwrap()));
+    }
+
+    #[test]
+    fn geohash_roundtrip() {
+        let position = RsGpsPosition::new(57.64911, 10.40744).unwrap();
+        assert_eq!(position.geohash(11), "u4pruydqqvj");
+        let center = RsGpsPosition::from_geohash("u4pruydqqvj").unwrap();
+        assert!(center.distance_to(&position) < 1.0);
+        assert!(RsGpsPosition::from_geohash("u4a").is_err());
+    }
+
+    #[test]
+    fn media_coordinates_from_gps() {
+        use crate::domain::{
+            media::{Media, MediaForUpdate},
+            update::Patch,
+        };
+        let update = MediaForUpdate {
+            gps: Patch::Set("48°51'24\"N 2°21'8\"W".to_string()),
+            ..Default::default()
+        };
+        let (lat, long) = update.coordinates();
+        assert!(matches!(lat, Patch::Set(lat) if (lat - 48.8567).abs() < 0.0001));
+        assert!(matches!(long, Patch::Set(long) if (long + 2.3522).abs() < 0.0001));
+
+        let explicit = MediaForUpdate {
+            lat: Patch::Set(10.0),
+            gps: Patch::Set("48.85,2.35".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(explicit.coordinates(), (Patch::Set(10.0), Patch::Set(2.35)));
+        let invalid = MediaForUpdate {
+            gps: Patch::Set("95,2.35".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(invalid.coordinates(), (Patch::Unchanged, Patch::Unchanged));
+
+        let mut media = Media::default();
+        update.apply_to(&mut media);
+        assert!(media
+            .position()
+            .unwrap()
+            .is_within(&RsGpsPosition::new(48.8567, -2.3522).unwrap(), 10.0));
+        MediaForUpdate {
+            gps: Patch::Clear,
+            ..Default::default()
+        }
+        .apply_to(&mut media);
+        assert_eq!(media.position(), None);
+    }
+
+    #[cfg(feature = "rusqlite")]
+    #[test]
+    fn test_rusqlite_roundtrip_gps() -> rusqlite::Result<()> {
+        use rusqlite::Connection;
+
+        let conn = Connection::open_in_memory()?;
+        conn.execute("CREATE TABLE test_gps (gps TEXT NOT NULL)", [])?;
+        let position = RsGpsPosition::new(48.8566, 2.3522).unwrap();
+        conn.execute("INSERT INTO test_gps (gps) VALUES (?1)", [&position])?;
+        let loaded: RsGpsPosition =
+            conn.query_row("SELECT gps FROM test_gps LIMIT 1", [], |row| row.get(0))?;
+        assert_eq!(loaded, position);
+
+        // out of range rows written before validation still load, new ones are refused
+        conn.execute("INSERT INTO test_gps (gps) VALUES ('95,2.35')", [])?;
+        let legacy: RsGpsPosition =
+            conn.query_row("SELECT gps FROM test_gps WHERE gps LIKE '95%'", [], |row| row.get(0))?;
+        assert_eq!(legacy, RsGpsPosition { lat: 95.0, long: 2.35 });
+        assert!(conn
+            .execute("INSERT INTO test_gps (gps) VALUES (?1)", [&legacy])
+            .is_err());
+        Ok(())
+    }
+}
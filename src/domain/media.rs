@@ -5,7 +5,12 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use strum_macros::EnumString;
 
-use crate::domain::backup::BackupFile;
+use crate::domain::{
+    backup::BackupFile,
+    update::{append_vec, overwrite_if_some, ChangeSummary, Patch},
+};
+
+pub use super::gps::RsGpsPosition;
 
 pub const DEFAULT_MIME: &str = "application/octet-stream";
 
@@ -207,6 +212,9 @@ pub struct Media {
     pub thumbv: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub thumbsize: Option<u64>,
+    /// Encrypted info size of an encrypted media
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub infosize: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub iv: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -239,6 +247,7 @@ impl Media {
         .unwrap_or(&0)
     }
 
+    /// Stored size: the file size, or the whole container for encrypted medias (`iv` set).
     pub fn bytes_size(&self) -> Option<u64> {
         if self.iv.is_none() {
             self.size
@@ -251,22 +260,29 @@ impl Media {
             //T Bytes for the encrypted thumb
             //I Bytes for the encrypted info
             if let Some(file_size) = self.size {
-                Some(file_size + 16 + 4 + 4 + 32 + 256 + self.thumbsize.unwrap_or(0) + 0)
+                Some(
+                    file_size
+                        + 16
+                        + 4
+                        + 4
+                        + 32
+                        + 256
+                        + self.thumbsize.unwrap_or(0)
+                        + self.infosize.unwrap_or(0),
+                )
             } else {
                 None
             }
         }
     }
-}
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
-#[serde(rename_all = "camelCase")]
-pub struct RsGpsPosition {
-    pub lat: f64,
-    pub long: f64,
+    /// Position from `lat`/`long` when both are set and in range.
+    pub fn position(&self) -> Option<RsGpsPosition> {
+        RsGpsPosition::new(self.lat?, self.long?).ok()
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct FaceEmbedding {
     pub id: String,
@@ -301,7 +317,8 @@ pub struct FaceBBox {
 #[serde(rename_all = "camelCase")]
 pub struct MediaForUpdate {
     pub name: Option<String>,
-    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub description: Patch<String>,
     pub mimetype: Option<String>,
     pub kind: Option<FileType>,
     pub size: Option<u64>,
@@ -319,6 +336,7 @@ pub struct MediaForUpdate {
     pub mp: Option<u32>,
     pub vcodecs: Option<Vec<String>>,
     pub acodecs: Option<Vec<String>>,
+    pub achan: Option<Vec<usize>>,
     pub fps: Option<f64>,
     pub bitrate: Option<u64>,
     pub focal: Option<u64>,
@@ -347,23 +365,32 @@ pub struct MediaForUpdate {
     pub remove_people: Option<Vec<String>>,
     pub people_lookup: Option<Vec<String>>,
 
-    pub long: Option<f64>,
-    pub lat: Option<f64>,
-    pub gps: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub long: Patch<f64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub lat: Patch<f64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub gps: Patch<String>,
 
-    pub origin: Option<RsLink>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub origin: Patch<RsLink>,
     pub origin_url: Option<String>,
     #[serde(default)]
     pub ignore_origin_duplicate: bool,
 
-    pub movie: Option<String>,
-    pub book: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub movie: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub book: Patch<String>,
 
-    pub lang: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub lang: Patch<String>,
 
-    pub rating: Option<u16>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub rating: Patch<u16>,
 
     pub thumbsize: Option<usize>,
+    pub infosize: Option<u64>,
     pub iv: Option<String>,
 
     pub uploader: Option<String>,
@@ -376,100 +403,166 @@ pub struct MediaForUpdate {
 
 
 impl MediaForUpdate {
+    /// Resolved `lat`/`long` patches: explicit values win, the missing ones come from `gps`
+    /// (decimal or DMS, see [`RsGpsPosition`]). A cleared `gps` clears them; an unparsable one
+    /// changes nothing.
+    pub fn coordinates(&self) -> (Patch<f64>, Patch<f64>) {
+        let (lat, long) = match self.gps.as_ref() {
+            Patch::Set(gps) => match gps.parse::<RsGpsPosition>() {
+                Ok(position) => (Patch::Set(position.lat), Patch::Set(position.long)),
+                Err(_) => (Patch::Unchanged, Patch::Unchanged),
+            },
+            Patch::Clear => (Patch::Clear, Patch::Clear),
+            Patch::Unchanged => (Patch::Unchanged, Patch::Unchanged),
+        };
+        (
+            if self.lat.is_unchanged() { lat } else { self.lat },
+            if self.long.is_unchanged() { long } else { self.long },
+        )
+    }
+
+    /// Apply the patch to `media`.
+    ///
+    /// `gps` is used when `lat`/`long` are not given (see [`MediaForUpdate::coordinates`]).
+    /// Relations (tags, series, people, movie, book), `season`/`episode` and upload/origin
+    /// handling fields are not applied.
+    pub fn apply_to(&self, media: &mut Media) -> ChangeSummary {
+        let mut changes = ChangeSummary::default();
+        changes.set("name", &mut media.name, self.name.clone());
+        changes.set_patch("description", &mut media.description, self.description.clone());
+        changes.set("mimetype", &mut media.mimetype, self.mimetype.clone());
+        changes.set("type", &mut media.kind, self.kind.clone());
+        changes.set_option("size", &mut media.size, self.size);
+
+        changes.set_option("md5", &mut media.md5, self.md5.clone());
+
+        changes.set_option("modified", &mut media.modified, self.modified);
+        changes.set_option("created", &mut media.created, self.created);
+
+        changes.set_option("width", &mut media.width, self.width.map(|v| v as usize));
+        changes.set_option("height", &mut media.height, self.height.map(|v| v as usize));
+        changes.set_option(
+            "orientation",
+            &mut media.orientation,
+            self.orientation.map(|v| v as usize),
+        );
+        changes.set_option("colorSpace", &mut media.color_space, self.color_space.clone());
+        changes.set_option("icc", &mut media.icc, self.icc.clone());
+        changes.set_option("mp", &mut media.mp, self.mp);
+        changes.set_option("vcodecs", &mut media.vcodecs, self.vcodecs.clone());
+        changes.set_option("acodecs", &mut media.acodecs, self.acodecs.clone());
+        changes.set_option("achan", &mut media.achan, self.achan.clone());
+        changes.set_option("fps", &mut media.fps, self.fps);
+        changes.set_option("bitrate", &mut media.bitrate, self.bitrate);
+        changes.set_option("focal", &mut media.focal, self.focal);
+        changes.set_option("iso", &mut media.iso, self.iso);
+        changes.set_option("model", &mut media.model, self.model.clone());
+        changes.set_option("sspeed", &mut media.sspeed, self.sspeed.clone());
+        changes.set_option("fNumber", &mut media.f_number, self.f_number);
+
+        changes.set_option("pages", &mut media.pages, self.pages);
+        changes.set_option("duration", &mut media.duration, self.duration.map(|v| v as usize));
+        changes.set_option("progress", &mut media.progress, self.progress);
+
+        let (lat, long) = self.coordinates();
+        changes.set_patch("lat", &mut media.lat, lat);
+        changes.set_patch("long", &mut media.long, long);
+
+        changes.set_patch("origin", &mut media.origin, self.origin.clone());
+        changes.set_patch("lang", &mut media.lang, self.lang.clone());
+        changes.set_patch("rating", &mut media.rating, self.rating.map(|v| v as f32));
+
+        changes.set_option("thumbsize", &mut media.thumbsize, self.thumbsize.map(|v| v as u64));
+        changes.set_option("infosize", &mut media.infosize, self.infosize);
+        changes.set_option("iv", &mut media.iv, self.iv.clone());
+
+        changes.set_option("uploader", &mut media.uploader, self.uploader.clone());
+        changes.set_option("uploadkey", &mut media.uploadkey, self.uploadkey.clone());
+
+        changes.set_option("originalHash", &mut media.original_hash, self.original_hash.clone());
+        changes.set_option("originalId", &mut media.original_id, self.original_id.clone());
+        changes
+    }
+
     /// Merge `patch` into `self`.
     ///
     /// Semantics:
     /// - For most `Option<T>` fields: overwrite only when `patch.field` is `Some`.
     /// - For "list-like update" fields (`add_*`, `remove_*`, `*_lookup`): append vectors.
     /// - For `ignore_origin_duplicate` (bool): OR (once true, stays true).
-    pub fn merge_from(&mut self, mut patch: Self) {
-        // Overwrite `dst` only if `src` is Some(..)
-        fn overwrite_if_some<T>(dst: &mut Option<T>, src: &mut Option<T>) {
-            if src.is_some() {
-                *dst = src.take();
-            }
-        }
-
-        // Append vectors when both sides are Some(vec).
-        // If `dst` is None, it becomes src (if any).
-        fn append_vec<T>(dst: &mut Option<Vec<T>>, src: &mut Option<Vec<T>>) {
-            match (dst.as_mut(), src.take()) {
-                (Some(d), Some(mut s)) => d.append(&mut s),
-                (None, Some(s)) => *dst = Some(s),
-                _ => {}
-            }
-        }
-
+    pub fn merge_from(&mut self, patch: Self) {
         // ----- Scalar / metadata fields (overwrite-if-some) -----
-        overwrite_if_some(&mut self.name, &mut patch.name);
-        overwrite_if_some(&mut self.description, &mut patch.description);
-        overwrite_if_some(&mut self.mimetype, &mut patch.mimetype);
-        overwrite_if_some(&mut self.kind, &mut patch.kind);
-        overwrite_if_some(&mut self.size, &mut patch.size);
-
-        overwrite_if_some(&mut self.md5, &mut patch.md5);
-
-        overwrite_if_some(&mut self.modified, &mut patch.modified);
-        overwrite_if_some(&mut self.created, &mut patch.created);
-
-        overwrite_if_some(&mut self.width, &mut patch.width);
-        overwrite_if_some(&mut self.height, &mut patch.height);
-        overwrite_if_some(&mut self.orientation, &mut patch.orientation);
-        overwrite_if_some(&mut self.color_space, &mut patch.color_space);
-        overwrite_if_some(&mut self.icc, &mut patch.icc);
-        overwrite_if_some(&mut self.mp, &mut patch.mp);
-        overwrite_if_some(&mut self.vcodecs, &mut patch.vcodecs);
-        overwrite_if_some(&mut self.acodecs, &mut patch.acodecs);
-        overwrite_if_some(&mut self.fps, &mut patch.fps);
-        overwrite_if_some(&mut self.bitrate, &mut patch.bitrate);
-        overwrite_if_some(&mut self.focal, &mut patch.focal);
-        overwrite_if_some(&mut self.iso, &mut patch.iso);
-        overwrite_if_some(&mut self.model, &mut patch.model);
-        overwrite_if_some(&mut self.sspeed, &mut patch.sspeed);
-        overwrite_if_some(&mut self.f_number, &mut patch.f_number);
-
-        overwrite_if_some(&mut self.pages, &mut patch.pages);
-        overwrite_if_some(&mut self.duration, &mut patch.duration);
-        overwrite_if_some(&mut self.progress, &mut patch.progress);
-
-        overwrite_if_some(&mut self.season, &mut patch.season);
-        overwrite_if_some(&mut self.episode, &mut patch.episode);
-
-        overwrite_if_some(&mut self.long, &mut patch.long);
-        overwrite_if_some(&mut self.lat, &mut patch.lat);
-        overwrite_if_some(&mut self.gps, &mut patch.gps);
-
-        overwrite_if_some(&mut self.origin, &mut patch.origin);
-        overwrite_if_some(&mut self.origin_url, &mut patch.origin_url);
-
-        overwrite_if_some(&mut self.movie, &mut patch.movie);
-        overwrite_if_some(&mut self.book, &mut patch.book);
-
-        overwrite_if_some(&mut self.lang, &mut patch.lang);
-        overwrite_if_some(&mut self.rating, &mut patch.rating);
-
-        overwrite_if_some(&mut self.thumbsize, &mut patch.thumbsize);
-        overwrite_if_some(&mut self.iv, &mut patch.iv);
-
-        overwrite_if_some(&mut self.uploader, &mut patch.uploader);
-        overwrite_if_some(&mut self.uploadkey, &mut patch.uploadkey);
-        overwrite_if_some(&mut self.upload_id, &mut patch.upload_id);
-
-        overwrite_if_some(&mut self.original_hash, &mut patch.original_hash);
-        overwrite_if_some(&mut self.original_id, &mut patch.original_id);
+        overwrite_if_some(&mut self.name, patch.name);
+        self.description.merge_from(patch.description);
+        overwrite_if_some(&mut self.mimetype, patch.mimetype);
+        overwrite_if_some(&mut self.kind, patch.kind);
+        overwrite_if_some(&mut self.size, patch.size);
+
+        overwrite_if_some(&mut self.md5, patch.md5);
+
+        overwrite_if_some(&mut self.modified, patch.modified);
+        overwrite_if_some(&mut self.created, patch.created);
+
+        overwrite_if_some(&mut self.width, patch.width);
+        overwrite_if_some(&mut self.height, patch.height);
+        overwrite_if_some(&mut self.orientation, patch.orientation);
+        overwrite_if_some(&mut self.color_space, patch.color_space);
+        overwrite_if_some(&mut self.icc, patch.icc);
+        overwrite_if_some(&mut self.mp, patch.mp);
+        overwrite_if_some(&mut self.vcodecs, patch.vcodecs);
+        overwrite_if_some(&mut self.acodecs, patch.acodecs);
+        overwrite_if_some(&mut self.achan, patch.achan);
+        overwrite_if_some(&mut self.fps, patch.fps);
+        overwrite_if_some(&mut self.bitrate, patch.bitrate);
+        overwrite_if_some(&mut self.focal, patch.focal);
+        overwrite_if_some(&mut self.iso, patch.iso);
+        overwrite_if_some(&mut self.model, patch.model);
+        overwrite_if_some(&mut self.sspeed, patch.sspeed);
+        overwrite_if_some(&mut self.f_number, patch.f_number);
+
+        overwrite_if_some(&mut self.pages, patch.pages);
+        overwrite_if_some(&mut self.duration, patch.duration);
+        overwrite_if_some(&mut self.progress, patch.progress);
+
+        overwrite_if_some(&mut self.season, patch.season);
+        overwrite_if_some(&mut self.episode, patch.episode);
+
+        self.long.merge_from(patch.long);
+        self.lat.merge_from(patch.lat);
+        self.gps.merge_from(patch.gps);
+
+        self.origin.merge_from(patch.origin);
+        overwrite_if_some(&mut self.origin_url, patch.origin_url);
+
+        self.movie.merge_from(patch.movie);
+        self.book.merge_from(patch.book);
+
+        self.lang.merge_from(patch.lang);
+        self.rating.merge_from(patch.rating);
+
+        overwrite_if_some(&mut self.thumbsize, patch.thumbsize);
+        overwrite_if_some(&mut self.infosize, patch.infosize);
+        overwrite_if_some(&mut self.iv, patch.iv);
+
+        overwrite_if_some(&mut self.uploader, patch.uploader);
+        overwrite_if_some(&mut self.uploadkey, patch.uploadkey);
+        overwrite_if_some(&mut self.upload_id, patch.upload_id);
+
+        overwrite_if_some(&mut self.original_hash, patch.original_hash);
+        overwrite_if_some(&mut self.original_id, patch.original_id);
 
         // ----- List-like update fields (append) -----
-        append_vec(&mut self.add_tags, &mut patch.add_tags);
-        append_vec(&mut self.remove_tags, &mut patch.remove_tags);
-        append_vec(&mut self.tags_lookup, &mut patch.tags_lookup);
+        append_vec(&mut self.add_tags, patch.add_tags);
+        append_vec(&mut self.remove_tags, patch.remove_tags);
+        append_vec(&mut self.tags_lookup, patch.tags_lookup);
 
-        append_vec(&mut self.add_series, &mut patch.add_series);
-        append_vec(&mut self.remove_series, &mut patch.remove_series);
-        append_vec(&mut self.series_lookup, &mut patch.series_lookup);
+        append_vec(&mut self.add_series, patch.add_series);
+        append_vec(&mut self.remove_series, patch.remove_series);
+        append_vec(&mut self.series_lookup, patch.series_lookup);
 
-        append_vec(&mut self.add_people, &mut patch.add_people);
-        append_vec(&mut self.remove_people, &mut patch.remove_people);
-        append_vec(&mut self.people_lookup, &mut patch.people_lookup);
+        append_vec(&mut self.add_people, patch.add_people);
+        append_vec(&mut self.remove_people, patch.remove_people);
+        append_vec(&mut self.people_lookup, patch.people_lookup);
 
         // ----- Non-Option field -----
         self.ignore_origin_duplicate |= patch.ignore_origin_duplicate;
@@ -486,18 +579,18 @@ impl MediaForUpdate {
 impl From<Media> for MediaForUpdate {
     fn from(value: Media) -> Self {
         MediaForUpdate {
-            description: value.description,
+            description: value.description.into(),
             add_people: None,
             add_tags: None,
-            long: value.long,
-            lat: value.lat,
+            long: value.long.into(),
+            lat: value.lat.into(),
             created: value.created,
-            origin: value.origin,
+            origin: value.origin.into(),
             add_series: None,
             pages: value.pages,
             original_hash: value.original_hash.or(value.md5),
             original_id: Some(value.original_id.unwrap_or(value.id)),
-            book: None,
+            book: Patch::Unchanged,
             ..Default::default()
         }
     }
@@ -530,7 +623,7 @@ impl From<RsRequest> for MediaForUpdate {
         MediaForUpdate {
             name: value.filename_or_extract_from_url(),
             mimetype: value.mime,
-            description: value.description,
+            description: value.description.into(),
             ignore_origin_duplicate: value.ignore_origin_duplicate,
             size: value.size,
             // Use the new lookup fields for database text search
@@ -538,7 +631,7 @@ impl From<RsRequest> for MediaForUpdate {
             tags_lookup: value.tags_lookup,
             series_lookup: value.albums_lookup,
             add_series,
-            movie: value.movie,
+            movie: value.movie.into(),
             season,
             episode,
             ..Default::default()
@@ -546,3 +639,24 @@ impl From<RsRequest> for MediaForUpdate {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn media_apply_to() {
+        let mut media = Media {
+            width: Some(100),
+            ..Default::default()
+        };
+        let changes = MediaForUpdate {
+            width: Some(100),
+            height: Some(50),
+            gps: Patch::Set("48.85, 2.35".to_string()),
+            ..Default::default()
+        }
+        .apply_to(&mut media);
+        assert_eq!(changes.fields, vec!["height", "lat", "long"]);
+        assert_eq!(media.lat, Some(48.85));
+    }
+}
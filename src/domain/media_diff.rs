@@ -0,0 +1,456 @@
+//! Field by field diff between two versions of a `Media`.
+//!
+//! Values are compared on their serialized (camelCase) form. `faces` and `backups` are compared
+//! item by item using their `id`, `origin` key by key; the detail is kept in
+//! `MediaFieldChange::nested` while `old`/`new` always hold the whole field value.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::domain::media::{Media, MediaForUpdate};
+
+/// Fields compared item by item, keyed by the item `id`.
+const KEYED_LIST_FIELDS: [&str; 2] = ["faces", "backups"];
+
+/// Fields of `Media` that have a `MediaForUpdate` counterpart.
+const UPDATABLE_FIELDS: [&str; 39] = [
+    "name",
+    "description",
+    "type",
+    "mimetype",
+    "size",
+    "modified",
+    "created",
+    "rating",
+    "md5",
+    "width",
+    "height",
+    "focal",
+    "iso",
+    "colorSpace",
+    "icc",
+    "mp",
+    "sspeed",
+    "fNumber",
+    "orientation",
+    "duration",
+    "acodecs",
+    "achan",
+    "vcodecs",
+    "fps",
+    "bitrate",
+    "long",
+    "lat",
+    "model",
+    "pages",
+    "progress",
+    "thumbsize",
+    "infosize",
+    "iv",
+    "origin",
+    "lang",
+    "uploader",
+    "uploadkey",
+    "originalHash",
+    "originalId",
+];
+
+/// Updatable fields that a `MediaForUpdate` can also clear (its `Patch` fields).
+const CLEARABLE_FIELDS: [&str; 6] = ["description", "rating", "long", "lat", "origin", "lang"];
+
+/// Change inside a structured field: a face or backup (`path` is the item id) or an `origin`
+/// key. `Null` marks an added or removed value.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaNestedChange {
+    pub path: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaFieldChange {
+    /// Serialized field name (`fNumber`, `type`...)
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nested: Vec<MediaNestedChange>,
+}
+
+impl MediaFieldChange {
+    pub fn inverted(&self) -> Self {
+        Self {
+            field: self.field.clone(),
+            old: self.new.clone(),
+            new: self.old.clone(),
+            nested: self
+                .nested
+                .iter()
+                .map(|change| MediaNestedChange {
+                    path: change.path.clone(),
+                    old: change.new.clone(),
+                    new: change.old.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// True when the change can be carried by a `MediaForUpdate`: removing a value needs a
+    /// clearable field, and the new value must fit the update field type (`orientation` is a
+    /// `u8` there).
+    pub fn is_updatable(&self) -> bool {
+        self.to_update().is_some()
+    }
+
+    /// Patch of this change alone, `None` when it cannot be carried.
+    fn to_update(&self) -> Option<MediaForUpdate> {
+        let field = self.field.as_str();
+        if !UPDATABLE_FIELDS.contains(&field)
+            || (self.new.is_null() && !CLEARABLE_FIELDS.contains(&field))
+        {
+            return None;
+        }
+        let value = match (field, &self.new) {
+            ("rating", Value::Number(rating)) => Value::from(rating.as_f64()?.round() as u16),
+            _ => self.new.clone(),
+        };
+        // `MediaForUpdate` does not rename `kind`
+        let key = if field == "type" { "kind" } else { field };
+        let mut patch = Map::new();
+        patch.insert(key.to_string(), value);
+        serde_json::from_value(Value::Object(patch)).ok()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaDiff {
+    pub changes: Vec<MediaFieldChange>,
+}
+
+impl MediaDiff {
+    /// Changes turning `old` into `new`, sorted by field name. The media `id` is not compared.
+    pub fn compute(old: &Media, new: &Media) -> Self {
+        let old = to_map(old);
+        let new = to_map(new);
+        let mut fields: Vec<&String> = old.keys().chain(new.keys()).collect();
+        fields.sort();
+        fields.dedup();
+
+        let changes = fields
+            .into_iter()
+            .filter(|field| field.as_str() != "id")
+            .filter_map(|field| {
+                let old = old.get(field).cloned().unwrap_or(Value::Null);
+                let new = new.get(field).cloned().unwrap_or(Value::Null);
+                if old == new {
+                    return None;
+                }
+                let nested = if KEYED_LIST_FIELDS.contains(&field.as_str()) {
+                    diff_keyed_list(&old, &new)
+                } else if old.is_object() || new.is_object() {
+                    diff_object(&old, &new)
+                } else {
+                    vec![]
+                };
+                Some(MediaFieldChange {
+                    field: field.clone(),
+                    old,
+                    new,
+                    nested,
+                })
+            })
+            .collect();
+        Self { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn get(&self, field: &str) -> Option<&MediaFieldChange> {
+        self.changes.iter().find(|change| change.field == field)
+    }
+
+    pub fn fields(&self) -> Vec<&str> {
+        self.changes
+            .iter()
+            .map(|change| change.field.as_str())
+            .collect()
+    }
+
+    /// Diff undoing this one.
+    pub fn inverted(&self) -> Self {
+        Self {
+            changes: self
+                .changes
+                .iter()
+                .map(MediaFieldChange::inverted)
+                .collect(),
+        }
+    }
+
+    /// Changed fields a `MediaForUpdate` cannot carry (`faces`, `backups`, `thumb`...), including
+    /// removed values of fields it cannot clear (`width`, `duration`...) and values out of the
+    /// update field range. When not empty, [`MediaDiff::to_update`] only applies part of the
+    /// diff.
+    pub fn non_updatable_fields(&self) -> Vec<&str> {
+        self.changes
+            .iter()
+            .filter(|change| !change.is_updatable())
+            .map(|change| change.field.as_str())
+            .collect()
+    }
+
+    /// Patch applying the changes of the updatable fields.
+    ///
+    /// Clearing only goes through for `Patch` fields (`description`, `lat`, `origin`...), other
+    /// removed values and values not fitting the update are left out and listed by
+    /// [`MediaDiff::non_updatable_fields`]. `rating` is rounded.
+    pub fn to_update(&self) -> MediaForUpdate {
+        let mut update = MediaForUpdate::default();
+        for patch in self.changes.iter().filter_map(MediaFieldChange::to_update) {
+            update.merge_from(patch);
+        }
+        update
+    }
+}
+
+/// One line per change for audit logs; nested changes get their own lines.
+impl fmt::Display for MediaDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = vec![];
+        for change in &self.changes {
+            if change.nested.is_empty() {
+                lines.push(format!(
+                    "{}: {} -> {}",
+                    change.field, change.old, change.new
+                ));
+            } else if KEYED_LIST_FIELDS.contains(&change.field.as_str()) {
+                for nested in &change.nested {
+                    let action = match (&nested.old, &nested.new) {
+                        (Value::Null, _) => "added",
+                        (_, Value::Null) => "removed",
+                        _ => "changed",
+                    };
+                    lines.push(format!("{}[{}]: {}", change.field, nested.path, action));
+                }
+            } else {
+                for nested in &change.nested {
+                    lines.push(format!(
+                        "{}.{}: {} -> {}",
+                        change.field, nested.path, nested.old, nested.new
+                    ));
+                }
+            }
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+fn to_map(media: &Media) -> Map<String, Value> {
+    match serde_json::to_value(media) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+fn diff_keyed_list(old: &Value, new: &Value) -> Vec<MediaNestedChange> {
+    fn items(list: &Value) -> Vec<(String, &Value)> {
+        list.as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .map(|item| {
+                        let id = item.get("id").and_then(Value::as_str).unwrap_or_default();
+                        (id.to_string(), item)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    let old = items(old);
+    let new = items(new);
+    let mut changes = vec![];
+    for (id, old_item) in &old {
+        match new.iter().find(|(new_id, _)| new_id == id) {
+            Some((_, new_item)) if new_item == old_item => {}
+            Some((_, new_item)) => changes.push(MediaNestedChange {
+                path: id.clone(),
+                old: (*old_item).clone(),
+                new: (*new_item).clone(),
+            }),
+            None => changes.push(MediaNestedChange {
+                path: id.clone(),
+                old: (*old_item).clone(),
+                new: Value::Null,
+            }),
+        }
+    }
+    for (id, new_item) in &new {
+        if !old.iter().any(|(old_id, _)| old_id == id) {
+            changes.push(MediaNestedChange {
+                path: id.clone(),
+                old: Value::Null,
+                new: (*new_item).clone(),
+            });
+        }
+    }
+    changes
+}
+
+fn diff_object(old: &Value, new: &Value) -> Vec<MediaNestedChange> {
+    let empty = Map::new();
+    let old = old.as_object().unwrap_or(&empty);
+    let new = new.as_object().unwrap_or(&empty);
+    let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter_map(|key| {
+            let old = old.get(key).cloned().unwrap_or(Value::Null);
+            let new = new.get(key).cloned().unwrap_or(Value::Null);
+            (old != new).then(|| MediaNestedChange {
+                path: key.clone(),
+                old,
+                new,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{backup::BackupFile, media::FaceEmbedding, update::Patch},
+        url::RsLink,
+    };
+
+    fn media() -> Media {
+        Media {
+            id: "media-1".to_string(),
+            name: "IMG_001.jpg".to_string(),
+            description: Some("Beach".to_string()),
+            rating: Some(3.0),
+            origin: Some(RsLink {
+                platform: "instagram".to_string(),
+                id: "abc".to_string(),
+                ..Default::default()
+            }),
+            faces: Some(
+                ["face-1", "face-2"]
+                    .map(|id| FaceEmbedding {
+                        id: id.to_string(),
+                        embedding: vec![0.5],
+                        ..Default::default()
+                    })
+                    .to_vec(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn diff_nested_fields() {
+        let old = media();
+        let mut new = media();
+        new.id = "media-2".to_string();
+        new.description = None;
+        new.origin.as_mut().unwrap().user = Some("someone".to_string());
+        let faces = new.faces.as_mut().unwrap();
+        faces[0].person_id = Some("person-1".to_string());
+        faces[1].id = "face-3".to_string();
+        new.backups = Some(vec![BackupFile {
+            backup: "nas".to_string(),
+            library: None,
+            file: "file-1".to_string(),
+            id: "backup-1".to_string(),
+            path: "/backup".to_string(),
+            hash: "h".to_string(),
+            sourcehash: "s".to_string(),
+            size: 10,
+            modified: 0,
+            added: 0,
+            iv: None,
+            thumb_size: None,
+            info_size: None,
+            error: None,
+        }]);
+
+        let diff = MediaDiff::compute(&old, &new);
+        assert_eq!(
+            diff.fields(),
+            vec!["backups", "description", "faces", "origin"]
+        );
+        assert_eq!(diff.non_updatable_fields(), vec!["backups", "faces"]);
+        let faces = &diff.get("faces").unwrap().nested;
+        assert_eq!(
+            faces.iter().map(|c| c.path.as_str()).collect::<Vec<_>>(),
+            vec!["face-1", "face-2", "face-3"]
+        );
+        assert_eq!(
+            diff.to_string(),
+            [
+                "backups[backup-1]: added",
+                "description: \"Beach\" -> null",
+                "faces[face-1]: changed",
+                "faces[face-2]: removed",
+                "faces[face-3]: added",
+                "origin.user: null -> \"someone\"",
+            ]
+            .join("\n")
+        );
+        assert!(MediaDiff::compute(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn diff_to_update_and_undo() {
+        let old = media();
+        let mut new = media();
+        new.description = None;
+        new.rating = Some(5.0);
+        new.width = Some(1920);
+        new.origin = None;
+        new.original_hash = Some("hash".to_string());
+
+        let diff = MediaDiff::compute(&old, &new);
+        let update = diff.to_update();
+        assert_eq!(update.description, Patch::Clear);
+        assert_eq!(update.rating, Patch::Set(5));
+        assert_eq!(update.width, Some(1920));
+        assert_eq!(update.original_hash, Some("hash".to_string()));
+
+        let mut media = old.clone();
+        update.apply_to(&mut media);
+        assert_eq!(media, new);
+
+        assert!(diff.non_updatable_fields().is_empty());
+
+        let undo = diff.inverted();
+        assert_eq!(undo.get("rating").unwrap().new, Value::from(3.0));
+        assert_eq!(undo.non_updatable_fields(), vec!["originalHash", "width"]);
+        undo.to_update().apply_to(&mut media);
+        assert_eq!(media.description, old.description);
+        assert_eq!(media.origin, old.origin);
+        assert_eq!(media.rating, old.rating);
+        // width cannot be cleared through MediaForUpdate: the undo is partial
+        assert_eq!(media.width, Some(1920));
+
+        let value = serde_json::to_value(&diff).unwrap();
+        assert_eq!(serde_json::from_value::<MediaDiff>(value).unwrap(), diff);
+
+        // `MediaForUpdate.orientation` is a u8
+        let rotated = Media {
+            orientation: Some(300),
+            ..old.clone()
+        };
+        let diff = MediaDiff::compute(&old, &rotated);
+        assert_eq!(diff.non_updatable_fields(), vec!["orientation"]);
+        assert_eq!(diff.to_update(), MediaForUpdate::default());
+    }
+}
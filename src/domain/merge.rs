@@ -0,0 +1,569 @@
+//! Field level merge of the same entity returned by several metadata plugins.
+//!
+//! Rules, applied field by field:
+//! - scalar fields: first non-empty value, plugins ordered by `MergePriorities` for that field
+//! - `alt`, `socials` and `otherids`: union of all sources (priority order, no duplicates)
+//! - ratings: taken together with their vote count from the source with the most votes
+//!
+//! Local fields (`id`, `modified`, `added`, image versions...) come from the highest
+//! priority source. The result records which plugin supplied each merged field.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{
+    book::Book, episode::Episode, movie::Movie, other_ids::OtherIds, person::Person, serie::Serie,
+};
+
+/// Plugin order used to pick field values, globally and per field.
+///
+/// Plugins missing from an order rank after listed ones, in the order sources were given.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MergePriorities {
+    pub default: Vec<String>,
+    /// Field name (as serialized, e.g. `overview`, `imdbRating`) → plugin order
+    #[serde(default)]
+    pub fields: HashMap<String, Vec<String>>,
+}
+
+impl MergePriorities {
+    pub fn new(default: Vec<String>) -> Self {
+        Self {
+            default,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Override the plugin order for a single field.
+    pub fn with_field(mut self, field: &str, order: Vec<String>) -> Self {
+        self.fields.insert(field.to_string(), order);
+        self
+    }
+
+    fn rank(&self, field: &str, plugin: &str) -> usize {
+        let order = self.fields.get(field).unwrap_or(&self.default);
+        order
+            .iter()
+            .position(|p| p == plugin)
+            .unwrap_or(order.len())
+    }
+}
+
+/// A merged entity and, for each merged field, the plugin that supplied its value.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataMerge<T> {
+    pub item: T,
+    /// Field name → plugin. Union fields list every contributing plugin, comma separated.
+    pub provenance: BTreeMap<String, String>,
+}
+
+pub trait MergeMetadata: Sized + Clone {
+    /// Merge `(plugin, entity)` pairs. Returns `None` when `sources` is empty.
+    fn merge_metadata(
+        sources: &[(String, Self)],
+        priorities: &MergePriorities,
+    ) -> Option<MetadataMerge<Self>>;
+}
+
+struct Merger<'a, T> {
+    sources: &'a [(String, T)],
+    priorities: &'a MergePriorities,
+    provenance: BTreeMap<String, String>,
+}
+
+impl<'a, T> Merger<'a, T> {
+    fn new(sources: &'a [(String, T)], priorities: &'a MergePriorities) -> Option<Self> {
+        if sources.is_empty() {
+            return None;
+        }
+        Some(Self {
+            sources,
+            priorities,
+            provenance: BTreeMap::new(),
+        })
+    }
+
+    /// Sources sorted by priority for `field` (stable: ties keep the input order).
+    fn ordered(&self, field: &str) -> Vec<&'a (String, T)> {
+        let mut ordered: Vec<&(String, T)> = self.sources.iter().collect();
+        ordered.sort_by_key(|(plugin, _)| self.priorities.rank(field, plugin));
+        ordered
+    }
+
+    /// Highest priority source overall, used for fields that are not merged.
+    fn base(&self) -> &'a T {
+        &self.ordered("")[0].1
+    }
+
+    fn record(&mut self, field: &str, plugin: &str) {
+        self.provenance
+            .insert(field.to_string(), plugin.to_string());
+    }
+
+    fn first<V: Clone>(&mut self, field: &str, get: impl Fn(&T) -> &Option<V>) -> Option<V> {
+        let (plugin, value) = self
+            .ordered(field)
+            .into_iter()
+            .find_map(|(plugin, source)| get(source).as_ref().map(|v| (plugin, v)))?;
+        self.record(field, plugin);
+        Some(value.clone())
+    }
+
+    fn first_str(&mut self, field: &str, get: impl Fn(&T) -> &Option<String>) -> Option<String> {
+        self.first(field, |source| match get(source) {
+            Some(value) if !value.trim().is_empty() => get(source),
+            _ => &None,
+        })
+    }
+
+    fn text(&mut self, field: &str, get: impl Fn(&T) -> &String) -> Option<String> {
+        let (plugin, value) = self
+            .ordered(field)
+            .into_iter()
+            .map(|(plugin, source)| (plugin, get(source)))
+            .find(|(_, value)| !value.trim().is_empty())?;
+        self.record(field, plugin);
+        Some(value.clone())
+    }
+
+    fn union<V: Clone>(
+        &mut self,
+        field: &str,
+        get: impl Fn(&T) -> &Option<Vec<V>>,
+        same: impl Fn(&V, &V) -> bool,
+    ) -> Option<Vec<V>> {
+        let mut merged: Vec<V> = vec![];
+        let mut plugins: Vec<&str> = vec![];
+        for (plugin, source) in self.ordered(field) {
+            let Some(values) = get(source) else { continue };
+            let before = merged.len();
+            for value in values {
+                if !merged.iter().any(|existing| same(existing, value)) {
+                    merged.push(value.clone());
+                }
+            }
+            if merged.len() > before {
+                plugins.push(plugin);
+            }
+        }
+        if merged.is_empty() {
+            return None;
+        }
+        self.record(field, &plugins.join(","));
+        Some(merged)
+    }
+
+    fn union_strings(
+        &mut self,
+        field: &str,
+        get: impl Fn(&T) -> &Option<Vec<String>>,
+    ) -> Option<Vec<String>> {
+        self.union(field, get, |a, b| a.eq_ignore_ascii_case(b))
+    }
+
+    /// Union of other ids; the highest priority plugin wins when a key has several values.
+    fn union_other_ids(
+        &mut self,
+        field: &str,
+        get: impl Fn(&T) -> &Option<OtherIds>,
+    ) -> Option<OtherIds> {
+        let mut merged = OtherIds::default();
+        let mut plugins: Vec<&str> = vec![];
+        for (plugin, source) in self.ordered(field) {
+            let Some(ids) = get(source) else { continue };
+            let before = merged.as_slice().len();
+            for entry in ids.as_slice() {
+                if let Some((key, value)) = entry.split_once(':') {
+                    if !merged.has_key(key) {
+                        merged.add(key, value);
+                    }
+                }
+            }
+            if merged.as_slice().len() > before {
+                plugins.push(plugin);
+            }
+        }
+        if merged.as_slice().is_empty() {
+            return None;
+        }
+        self.record(field, &plugins.join(","));
+        Some(merged)
+    }
+
+    /// Rating and vote count from the source with the most votes.
+    /// Falls back to the first rating by priority when no source has votes.
+    fn rating<V: PartialOrd + Copy>(
+        &mut self,
+        rating_field: &str,
+        votes_field: &str,
+        get: impl Fn(&T) -> (Option<f32>, Option<V>),
+    ) -> (Option<f32>, Option<V>) {
+        let mut best: Option<(&str, Option<f32>, V)> = None;
+        for (plugin, source) in self.ordered(votes_field) {
+            if let (rating, Some(votes)) = get(source) {
+                if best
+                    .as_ref()
+                    .is_none_or(|(_, _, best_votes)| votes > *best_votes)
+                {
+                    best = Some((plugin, rating, votes));
+                }
+            }
+        }
+        match best {
+            Some((plugin, rating, votes)) => {
+                self.record(votes_field, plugin);
+                if rating.is_some() {
+                    self.record(rating_field, plugin);
+                }
+                (rating, Some(votes))
+            }
+            None => {
+                let rating = self
+                    .ordered(rating_field)
+                    .into_iter()
+                    .find_map(|(plugin, source)| get(source).0.map(|rating| (plugin, rating)));
+                match rating {
+                    Some((plugin, rating)) => {
+                        self.record(rating_field, plugin);
+                        (Some(rating), None)
+                    }
+                    None => (None, None),
+                }
+            }
+        }
+    }
+
+    fn finish(self, item: T) -> MetadataMerge<T> {
+        MetadataMerge {
+            item,
+            provenance: self.provenance,
+        }
+    }
+}
+
+impl MergeMetadata for Movie {
+    fn merge_metadata(
+        sources: &[(String, Self)],
+        priorities: &MergePriorities,
+    ) -> Option<MetadataMerge<Self>> {
+        let mut m = Merger::new(sources, priorities)?;
+        let mut item = m.base().clone();
+        if let Some(name) = m.text("name", |s| &s.name) {
+            item.name = name;
+        }
+        item.kind = m.first("type", |s| &s.kind);
+        item.year = m.first("year", |s| &s.year);
+        item.airdate = m.first("airdate", |s| &s.airdate);
+        item.digitalairdate = m.first("digitalairdate", |s| &s.digitalairdate);
+        item.duration = m.first("duration", |s| &s.duration);
+        item.overview = m.first_str("overview", |s| &s.overview);
+        item.country = m.first_str("country", |s| &s.country);
+        item.status = m.first("status", |s| &s.status);
+        item.imdb = m.first_str("imdb", |s| &s.imdb);
+        item.slug = m.first_str("slug", |s| &s.slug);
+        item.tmdb = m.first("tmdb", |s| &s.tmdb);
+        item.trakt = m.first("trakt", |s| &s.trakt);
+        item.otherids = m.union_other_ids("otherids", |s| &s.otherids);
+        item.lang = m.first_str("lang", |s| &s.lang);
+        item.original = m.first_str("original", |s| &s.original);
+        (item.imdb_rating, item.imdb_votes) =
+            m.rating("imdbRating", "imdbVotes", |s| (s.imdb_rating, s.imdb_votes));
+        (item.trakt_rating, item.trakt_votes) = m.rating("traktRating", "traktVotes", |s| {
+            (s.trakt_rating, s.trakt_votes)
+        });
+        item.trailer = m.first("trailer", |s| &s.trailer);
+        Some(m.finish(item))
+    }
+}
+
+impl MergeMetadata for Serie {
+    fn merge_metadata(
+        sources: &[(String, Self)],
+        priorities: &MergePriorities,
+    ) -> Option<MetadataMerge<Self>> {
+        let mut m = Merger::new(sources, priorities)?;
+        let mut item = m.base().clone();
+        if let Some(name) = m.text("name", |s| &s.name) {
+            item.name = name;
+        }
+        item.kind = m.first("type", |s| &s.kind);
+        item.alt = m.union_strings("alt", |s| &s.alt);
+        item.status = m.first("status", |s| &s.status);
+        item.imdb = m.first_str("imdb", |s| &s.imdb);
+        item.slug = m.first_str("slug", |s| &s.slug);
+        item.tmdb = m.first("tmdb", |s| &s.tmdb);
+        item.trakt = m.first("trakt", |s| &s.trakt);
+        item.tvdb = m.first("tvdb", |s| &s.tvdb);
+        item.otherids = m.union_other_ids("otherids", |s| &s.otherids);
+        item.openlibrary_work_id = m.first_str("openlibraryWorkId", |s| &s.openlibrary_work_id);
+        item.anilist_manga_id = m.first("anilistMangaId", |s| &s.anilist_manga_id);
+        item.mangadex_manga_uuid = m.first_str("mangadexMangaUuid", |s| &s.mangadex_manga_uuid);
+        item.myanimelist_manga_id = m.first("myanimelistMangaId", |s| &s.myanimelist_manga_id);
+        (item.imdb_rating, item.imdb_votes) =
+            m.rating("imdbRating", "imdbVotes", |s| (s.imdb_rating, s.imdb_votes));
+        (item.trakt_rating, item.trakt_votes) = m.rating("traktRating", "traktVotes", |s| {
+            (s.trakt_rating, s.trakt_votes)
+        });
+        item.trailer = m.first_str("trailer", |s| &s.trailer);
+        item.year = m.first("year", |s| &s.year);
+        Some(m.finish(item))
+    }
+}
+
+impl MergeMetadata for Episode {
+    fn merge_metadata(
+        sources: &[(String, Self)],
+        priorities: &MergePriorities,
+    ) -> Option<MetadataMerge<Self>> {
+        let mut m = Merger::new(sources, priorities)?;
+        let mut item = m.base().clone();
+        item.abs = m.first("abs", |s| &s.abs);
+        item.name = m.first_str("name", |s| &s.name);
+        item.overview = m.first_str("overview", |s| &s.overview);
+        item.alt = m.union_strings("alt", |s| &s.alt);
+        item.airdate = m.first("airdate", |s| &s.airdate);
+        item.duration = m.first("duration", |s| &s.duration);
+        item.imdb = m.first_str("imdb", |s| &s.imdb);
+        item.slug = m.first_str("slug", |s| &s.slug);
+        item.tmdb = m.first("tmdb", |s| &s.tmdb);
+        item.trakt = m.first("trakt", |s| &s.trakt);
+        item.tvdb = m.first("tvdb", |s| &s.tvdb);
+        item.otherids = m.union_other_ids("otherids", |s| &s.otherids);
+        (item.imdb_rating, item.imdb_votes) =
+            m.rating("imdbRating", "imdbVotes", |s| (s.imdb_rating, s.imdb_votes));
+        (item.trakt_rating, item.trakt_votes) = m.rating("traktRating", "traktVotes", |s| {
+            (s.trakt_rating, s.trakt_votes)
+        });
+        Some(m.finish(item))
+    }
+}
+
+impl MergeMetadata for Book {
+    fn merge_metadata(
+        sources: &[(String, Self)],
+        priorities: &MergePriorities,
+    ) -> Option<MetadataMerge<Self>> {
+        let mut m = Merger::new(sources, priorities)?;
+        let mut item = m.base().clone();
+        if let Some(name) = m.text("name", |s| &s.name) {
+            item.name = name;
+        }
+        item.kind = m.first_str("type", |s| &s.kind);
+        item.volume = m.first("volume", |s| &s.volume);
+        item.chapter = m.first("chapter", |s| &s.chapter);
+        item.year = m.first("year", |s| &s.year);
+        item.airdate = m.first("airdate", |s| &s.airdate);
+        item.overview = m.first_str("overview", |s| &s.overview);
+        item.pages = m.first("pages", |s| &s.pages);
+        item.lang = m.first_str("lang", |s| &s.lang);
+        item.original = m.first_str("original", |s| &s.original);
+        item.isbn13 = m.first_str("isbn13", |s| &s.isbn13);
+        item.openlibrary_edition_id =
+            m.first_str("openlibraryEditionId", |s| &s.openlibrary_edition_id);
+        item.openlibrary_work_id = m.first_str("openlibraryWorkId", |s| &s.openlibrary_work_id);
+        item.google_books_volume_id =
+            m.first_str("googleBooksVolumeId", |s| &s.google_books_volume_id);
+        item.asin = m.first_str("asin", |s| &s.asin);
+        item.otherids = m.union_other_ids("otherids", |s| &s.otherids);
+        Some(m.finish(item))
+    }
+}
+
+impl MergeMetadata for Person {
+    fn merge_metadata(
+        sources: &[(String, Self)],
+        priorities: &MergePriorities,
+    ) -> Option<MetadataMerge<Self>> {
+        let mut m = Merger::new(sources, priorities)?;
+        let mut item = m.base().clone();
+        if let Some(name) = m.text("name", |s| &s.name) {
+            item.name = name;
+        }
+        item.socials = m.union(
+            "socials",
+            |s| &s.socials,
+            |a, b| a.platform == b.platform && a.id == b.id,
+        );
+        item.kind = m.first_str("type", |s| &s.kind);
+        item.alt = m.union_strings("alt", |s| &s.alt);
+        item.portrait = m.first_str("portrait", |s| &s.portrait);
+        item.birthday = m.first("birthday", |s| &s.birthday);
+        item.imdb = m.first_str("imdb", |s| &s.imdb);
+        item.slug = m.first_str("slug", |s| &s.slug);
+        item.tmdb = m.first("tmdb", |s| &s.tmdb);
+        item.trakt = m.first("trakt", |s| &s.trakt);
+        item.death = m.first("death", |s| &s.death);
+        item.gender = m.first("gender", |s| &s.gender);
+        item.country = m.first_str("country", |s| &s.country);
+        item.bio = m.first_str("bio", |s| &s.bio);
+        item.otherids = m.union_other_ids("otherids", |s| &s.otherids);
+        Some(m.finish(item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::url::RsLink;
+
+    fn sources<T>(items: Vec<(&str, T)>) -> Vec<(String, T)> {
+        items
+            .into_iter()
+            .map(|(plugin, item)| (plugin.to_string(), item))
+            .collect()
+    }
+
+    #[test]
+    fn movie_merge_prefers_non_empty_by_priority() {
+        let tmdb = Movie {
+            id: "tmdb-local".to_string(),
+            name: "The Matrix".to_string(),
+            overview: Some("".to_string()),
+            tmdb: Some(603),
+            year: Some(1999),
+            imdb_rating: Some(8.7),
+            imdb_votes: Some(100),
+            otherids: Some(OtherIds(vec!["tvmaze:1".to_string()])),
+            ..Default::default()
+        };
+        let trakt = Movie {
+            name: "Matrix".to_string(),
+            overview: Some("A hacker learns the truth.".to_string()),
+            trakt: Some(481),
+            year: Some(1998),
+            imdb_rating: Some(8.5),
+            imdb_votes: Some(2000),
+            otherids: Some(OtherIds(vec![
+                "tvmaze:2".to_string(),
+                "letterboxd:matrix".to_string(),
+            ])),
+            ..Default::default()
+        };
+        let priorities = MergePriorities::new(vec!["tmdb".to_string(), "trakt".to_string()]);
+        let merged = Movie::merge_metadata(
+            &sources(vec![("trakt", trakt), ("tmdb", tmdb)]),
+            &priorities,
+        )
+        .unwrap();
+
+        let movie = merged.item;
+        assert_eq!(movie.id, "tmdb-local");
+        assert_eq!(movie.name, "The Matrix");
+        assert_eq!(movie.year, Some(1999));
+        assert_eq!(
+            movie.overview.as_deref(),
+            Some("A hacker learns the truth.")
+        );
+        assert_eq!(movie.tmdb, Some(603));
+        assert_eq!(movie.trakt, Some(481));
+        assert_eq!(movie.imdb_rating, Some(8.5));
+        assert_eq!(movie.imdb_votes, Some(2000));
+        assert_eq!(
+            movie.otherids,
+            Some(OtherIds(vec![
+                "tvmaze:1".to_string(),
+                "letterboxd:matrix".to_string()
+            ]))
+        );
+
+        let provenance = merged.provenance;
+        assert_eq!(provenance.get("name").map(String::as_str), Some("tmdb"));
+        assert_eq!(
+            provenance.get("overview").map(String::as_str),
+            Some("trakt")
+        );
+        assert_eq!(
+            provenance.get("imdbVotes").map(String::as_str),
+            Some("trakt")
+        );
+        assert_eq!(
+            provenance.get("otherids").map(String::as_str),
+            Some("tmdb,trakt")
+        );
+        assert_eq!(provenance.get("digitalairdate"), None);
+    }
+
+    #[test]
+    fn serie_merge_per_field_priority_and_alt_union() {
+        let anilist = Serie {
+            name: "Shingeki no Kyojin".to_string(),
+            alt: Some(vec!["Attack on Titan".to_string(), "AoT".to_string()]),
+            anilist_manga_id: Some(16498),
+            ..Default::default()
+        };
+        let tvdb = Serie {
+            name: "Attack on Titan".to_string(),
+            alt: Some(vec![
+                "attack on titan".to_string(),
+                "L'Attaque des Titans".to_string(),
+            ]),
+            tvdb: Some(267440),
+            year: Some(2013),
+            ..Default::default()
+        };
+        let priorities = MergePriorities::new(vec!["anilist".to_string()])
+            .with_field("name", vec!["tvdb".to_string()]);
+        let merged = Serie::merge_metadata(
+            &sources(vec![("anilist", anilist), ("tvdb", tvdb)]),
+            &priorities,
+        )
+        .unwrap();
+
+        assert_eq!(merged.item.name, "Attack on Titan");
+        assert_eq!(
+            merged.item.alt,
+            Some(vec![
+                "Attack on Titan".to_string(),
+                "AoT".to_string(),
+                "L'Attaque des Titans".to_string()
+            ])
+        );
+        assert_eq!(merged.item.anilist_manga_id, Some(16498));
+        assert_eq!(merged.item.tvdb, Some(267440));
+        assert_eq!(merged.item.year, Some(2013));
+        assert_eq!(
+            merged.provenance.get("name").map(String::as_str),
+            Some("tvdb")
+        );
+        assert_eq!(
+            merged.provenance.get("alt").map(String::as_str),
+            Some("anilist,tvdb")
+        );
+    }
+
+    #[test]
+    fn person_merge_socials_union() {
+        let link = |platform: &str, id: &str| RsLink {
+            platform: platform.to_string(),
+            id: id.to_string(),
+            ..Default::default()
+        };
+        let a = Person {
+            name: "Keanu Reeves".to_string(),
+            socials: Some(vec![link("instagram", "keanu")]),
+            ..Default::default()
+        };
+        let b = Person {
+            name: "Keanu Reeves".to_string(),
+            socials: Some(vec![link("instagram", "keanu"), link("x", "keanu")]),
+            bio: Some("Actor".to_string()),
+            ..Default::default()
+        };
+        let merged = Person::merge_metadata(
+            &sources(vec![("a", a), ("b", b)]),
+            &MergePriorities::default(),
+        )
+        .unwrap();
+        assert_eq!(merged.item.socials.map(|s| s.len()), Some(2));
+        assert_eq!(merged.item.bio.as_deref(), Some("Actor"));
+    }
+
+    #[test]
+    fn merge_without_sources() {
+        assert!(Book::merge_metadata(&[], &MergePriorities::default()).is_none());
+        assert!(Episode::merge_metadata(&[], &MergePriorities::default()).is_none());
+    }
+}
@@ -2,19 +2,32 @@ use serde::{Deserialize, Serialize};
 
 use crate::domain::{media::{FileEpisode, MediaItemReference}, movie::Movie, person::Person, serie::Serie, tag::Tag};
 
+pub mod album;
+pub mod artist;
 pub mod backup;
 pub mod book;
+pub mod coverage;
+pub mod credit;
 pub mod element_type;
 pub mod episode;
 pub mod external_images;
+pub mod face;
+pub mod gps;
 pub mod media;
+pub mod media_diff;
+pub mod merge;
 pub mod movie;
+pub mod numbering;
 pub mod other_ids;
 pub mod person;
 pub mod rs_ids;
+pub mod season;
 pub mod serie;
+pub mod song;
 pub mod tag;
+pub mod tag_tree;
 pub mod tools;
+pub mod update;
 
 #[cfg(feature = "rusqlite")]
 pub mod rusqlite;
@@ -25,6 +38,7 @@ pub enum MediaElement {
     Media(media::Media),
     Movie(movie::Movie),
     Episode(episode::Episode),
+    Season(season::Season),
     Serie(serie::Serie),
     Book(book::Book),
 }
@@ -50,6 +64,12 @@ pub struct Relations {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub people: Option<Vec<MediaItemReference>>,
+    /// Cast and crew of the item; persons are in `people`/`people_details`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credits: Option<Vec<credit::Credit>>,
+    /// Works of a person, from person lookups
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filmography: Option<Vec<credit::FilmographyEntry>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<Vec<MediaItemReference>>,
     
@@ -2,7 +2,10 @@ use crate::domain::tools::rating_serializer;
 use crate::{
     domain::{
         other_ids::OtherIds,
-        rs_ids::{ApplyRsIds, RsIds},
+        rs_ids::ApplyRsIds,
+        update::{
+            append_vec, diff_other_ids, diff_value, overwrite_if_some, ChangeSummary, Patch,
+        },
     },
     url::RsLink,
 };
@@ -27,10 +30,11 @@ pub enum MovieStatus {
     Unknown,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, ApplyRsIds)]
 #[serde(rename_all = "camelCase")]
 pub struct Movie {
     #[serde(default)]
+    #[rs_id(redseat)]
     pub id: String,
 
     pub name: String,
@@ -45,10 +49,15 @@ pub struct Movie {
     pub country: Option<String>,
     pub status: Option<MovieStatus>,
 
+    #[rs_id("imdb")]
     pub imdb: Option<String>,
+    #[rs_id("slug")]
     pub slug: Option<String>,
+    #[rs_id("tmdb")]
     pub tmdb: Option<u64>,
+    #[rs_id("trakt")]
     pub trakt: Option<u64>,
+    #[rs_id(otherids)]
     pub otherids: Option<OtherIds>,
 
     pub lang: Option<String>,
@@ -81,57 +90,157 @@ pub struct Movie {
     pub cardv: u64,
 }
 
-impl From<Movie> for RsIds {
-    fn from(value: Movie) -> Self {
-        let mut ids = RsIds::default();
-        if let Some(v) = value.trakt { ids.set("trakt", v); }
-        if let Some(v) = value.slug { ids.set("slug", v); }
-        if let Some(v) = value.imdb { ids.set("imdb", v); }
-        if let Some(v) = value.tmdb { ids.set("tmdb", v); }
-        if let Some(other) = value.otherids {
-            for entry in other.into_vec() {
-                let _ = ids.try_add(entry);
-            }
-        }
-        if ids.try_add(value.id.clone()).is_err() {
-            ids.set("redseat", value.id);
-        }
-        ids
-    }
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MovieForUpdate {
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub kind: Patch<Value>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub year: Patch<u16>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub airdate: Patch<i64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub digitalairdate: Patch<i64>,
+
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub duration: Patch<u32>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub overview: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub country: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub status: Patch<MovieStatus>,
+
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub imdb: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub slug: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub tmdb: Patch<u64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub trakt: Patch<u64>,
+
+    pub otherids: Option<OtherIds>,
+    pub add_otherids: Option<Vec<String>>,
+    pub remove_otherids: Option<Vec<String>>,
+
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub lang: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub original: Patch<String>,
+
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub imdb_rating: Patch<f32>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub imdb_votes: Patch<u64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub trakt_rating: Patch<f32>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub trakt_votes: Patch<u32>,
+
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub trailer: Patch<RsLink>,
 }
 
-impl ApplyRsIds for Movie {
-    fn apply_rs_ids(&mut self, ids: &RsIds) {
-        if let Some(redseat) = ids.redseat() {
-            self.id = redseat.to_string();
-        }
-        if let Some(trakt) = ids.trakt() {
-            self.trakt = Some(trakt);
-        }
-        if let Some(slug) = ids.slug() {
-            self.slug = Some(slug.to_string());
-        }
-        if let Some(imdb) = ids.imdb() {
-            self.imdb = Some(imdb.to_string());
-        }
-        if let Some(tmdb) = ids.tmdb() {
-            self.tmdb = Some(tmdb);
-        }
-        let known: &[&str] = &["redseat", "trakt", "slug", "imdb", "tmdb"];
-        let mut other = self.otherids.take().unwrap_or_default();
-        for (k, v) in ids.iter() {
-            if !known.contains(&k.as_str()) { other.add(k, v); }
+impl MovieForUpdate {
+    pub fn merge_from(&mut self, patch: Self) {
+        overwrite_if_some(&mut self.name, patch.name);
+        self.kind.merge_from(patch.kind);
+        self.year.merge_from(patch.year);
+        self.airdate.merge_from(patch.airdate);
+        self.digitalairdate.merge_from(patch.digitalairdate);
+        self.duration.merge_from(patch.duration);
+        self.overview.merge_from(patch.overview);
+        self.country.merge_from(patch.country);
+        self.status.merge_from(patch.status);
+        self.imdb.merge_from(patch.imdb);
+        self.slug.merge_from(patch.slug);
+        self.tmdb.merge_from(patch.tmdb);
+        self.trakt.merge_from(patch.trakt);
+        overwrite_if_some(&mut self.otherids, patch.otherids);
+        append_vec(&mut self.add_otherids, patch.add_otherids);
+        append_vec(&mut self.remove_otherids, patch.remove_otherids);
+        self.lang.merge_from(patch.lang);
+        self.original.merge_from(patch.original);
+        self.imdb_rating.merge_from(patch.imdb_rating);
+        self.imdb_votes.merge_from(patch.imdb_votes);
+        self.trakt_rating.merge_from(patch.trakt_rating);
+        self.trakt_votes.merge_from(patch.trakt_votes);
+        self.trailer.merge_from(patch.trailer);
+    }
+
+    pub fn apply_to(&self, movie: &mut Movie) -> ChangeSummary {
+        let mut changes = ChangeSummary::default();
+        changes.set("name", &mut movie.name, self.name.clone());
+        changes.set_patch("type", &mut movie.kind, self.kind.clone());
+        changes.set_patch("year", &mut movie.year, self.year);
+        changes.set_patch("airdate", &mut movie.airdate, self.airdate);
+        changes.set_patch("digitalairdate", &mut movie.digitalairdate, self.digitalairdate);
+        changes.set_patch("duration", &mut movie.duration, self.duration);
+        changes.set_patch("overview", &mut movie.overview, self.overview.clone());
+        changes.set_patch("country", &mut movie.country, self.country.clone());
+        changes.set_patch("status", &mut movie.status, self.status.clone());
+        changes.set_patch("imdb", &mut movie.imdb, self.imdb.clone());
+        changes.set_patch("slug", &mut movie.slug, self.slug.clone());
+        changes.set_patch("tmdb", &mut movie.tmdb, self.tmdb);
+        changes.set_patch("trakt", &mut movie.trakt, self.trakt);
+        changes.set_other_ids(
+            "otherids",
+            &mut movie.otherids,
+            self.otherids.clone(),
+            self.add_otherids.clone(),
+            self.remove_otherids.clone(),
+        );
+        changes.set_patch("lang", &mut movie.lang, self.lang.clone());
+        changes.set_patch("original", &mut movie.original, self.original.clone());
+        changes.set_patch("imdbRating", &mut movie.imdb_rating, self.imdb_rating);
+        changes.set_patch("imdbVotes", &mut movie.imdb_votes, self.imdb_votes);
+        changes.set_patch("traktRating", &mut movie.trakt_rating, self.trakt_rating);
+        changes.set_patch("traktVotes", &mut movie.trakt_votes, self.trakt_votes);
+        changes.set_patch("trailer", &mut movie.trailer, self.trailer.clone());
+        changes
+    }
+
+    /// Patch turning `old` into `new`. Values removed in `new` are cleared.
+    pub fn diff(old: &Movie, new: &Movie) -> Self {
+        let (add_otherids, remove_otherids) = diff_other_ids(&old.otherids, &new.otherids);
+        Self {
+            name: diff_value(&old.name, &new.name),
+            kind: Patch::diff(&old.kind, &new.kind),
+            year: Patch::diff(&old.year, &new.year),
+            airdate: Patch::diff(&old.airdate, &new.airdate),
+            digitalairdate: Patch::diff(&old.digitalairdate, &new.digitalairdate),
+            duration: Patch::diff(&old.duration, &new.duration),
+            overview: Patch::diff(&old.overview, &new.overview),
+            country: Patch::diff(&old.country, &new.country),
+            status: Patch::diff(&old.status, &new.status),
+            imdb: Patch::diff(&old.imdb, &new.imdb),
+            slug: Patch::diff(&old.slug, &new.slug),
+            tmdb: Patch::diff(&old.tmdb, &new.tmdb),
+            trakt: Patch::diff(&old.trakt, &new.trakt),
+            otherids: None,
+            add_otherids,
+            remove_otherids,
+            lang: Patch::diff(&old.lang, &new.lang),
+            original: Patch::diff(&old.original, &new.original),
+            imdb_rating: Patch::diff(&old.imdb_rating, &new.imdb_rating),
+            imdb_votes: Patch::diff(&old.imdb_votes, &new.imdb_votes),
+            trakt_rating: Patch::diff(&old.trakt_rating, &new.trakt_rating),
+            trakt_votes: Patch::diff(&old.trakt_votes, &new.trakt_votes),
+            trailer: Patch::diff(&old.trailer, &new.trailer),
         }
-        if !other.as_slice().is_empty() { self.otherids = Some(other); }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Movie;
+    use super::{Movie, MovieForUpdate};
     use crate::domain::{
         other_ids::OtherIds,
         rs_ids::{ApplyRsIds, RsIds},
+        update::Patch,
     };
     use serde_json::json;
 
@@ -184,4 +293,75 @@ mod tests {
         assert_eq!(movie.slug.as_deref(), Some("movie-slug"));
         assert_eq!(movie.tmdb, Some(10));
     }
+
+    #[test]
+    fn movie_rs_ids_roundtrip() {
+        let movie = Movie {
+            id: "movie-1".to_string(),
+            name: "Movie".to_string(),
+            imdb: Some("tt0000001".to_string()),
+            slug: Some("movie-slug".to_string()),
+            tmdb: Some(11),
+            trakt: Some(12),
+            otherids: Some(OtherIds(vec!["tvmaze:13".to_string()])),
+            ..Default::default()
+        };
+        let ids = RsIds::from(movie.clone());
+        assert_eq!(ids.redseat(), Some("movie-1"));
+
+        let mut restored = Movie::default();
+        restored.apply_rs_ids(&ids);
+        assert_eq!(restored.id, movie.id);
+        assert_eq!(restored.imdb, movie.imdb);
+        assert_eq!(restored.slug, movie.slug);
+        assert_eq!(restored.tmdb, movie.tmdb);
+        assert_eq!(restored.trakt, movie.trakt);
+        assert_eq!(restored.otherids, movie.otherids);
+    }
+
+    #[test]
+    fn movie_diff_applies_back() {
+        let old = Movie {
+            id: "movie-1".to_string(),
+            name: "Movie".to_string(),
+            year: Some(1999),
+            country: Some("US".to_string()),
+            otherids: Some(OtherIds(vec![
+                "tvmaze:1".to_string(),
+                "letterboxd:movie".to_string(),
+            ])),
+            ..Default::default()
+        };
+        let new = Movie {
+            name: "The Movie".to_string(),
+            overview: Some("Overview".to_string()),
+            country: None,
+            otherids: Some(OtherIds(vec!["tvmaze:2".to_string()])),
+            ..old.clone()
+        };
+        let patch = MovieForUpdate::diff(&old, &new);
+        assert_eq!(patch.year, Patch::Unchanged);
+        assert_eq!(patch.country, Patch::Clear);
+        assert_eq!(patch.add_otherids, Some(vec!["tvmaze:2".to_string()]));
+        assert_eq!(
+            patch.remove_otherids,
+            Some(vec!["letterboxd:movie".to_string()])
+        );
+
+        let mut updated = old.clone();
+        let changes = patch.apply_to(&mut updated);
+        assert_eq!(updated, new);
+        assert_eq!(changes.fields, vec!["name", "overview", "country", "otherids"]);
+        assert!(MovieForUpdate::diff(&new, &updated) == MovieForUpdate::default());
+
+        let mut merged = MovieForUpdate {
+            year: Patch::Set(2000),
+            add_otherids: Some(vec!["imdb:tt1".to_string()]),
+            ..Default::default()
+        };
+        merged.merge_from(patch);
+        assert_eq!(merged.year, Patch::Set(2000));
+        assert_eq!(merged.name.as_deref(), Some("The Movie"));
+        assert_eq!(merged.add_otherids.map(|ids| ids.len()), Some(2));
+    }
 }
@@ -0,0 +1,262 @@
+//! Conversions between absolute, scene and season/episode numbering of a serie.
+//!
+//! Anime releases are usually named by absolute number (`One.Piece.1071.1080p.mkv`) while
+//! metadata providers order episodes by season. An `EpisodeNumbering` table maps one to the
+//! other for a serie, and optionally for the provider whose seasons it follows.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{episode::Episode, media::FileEpisode};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeNumberingEntry {
+    pub season: u32,
+    pub number: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub abs: Option<u32>,
+    /// Season used by release groups when it differs from the provider season
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scene_season: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scene_number: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeNumbering {
+    pub serie: String,
+    /// Provider whose season ordering is used (`tvdb`, `tmdb`...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    pub entries: Vec<EpisodeNumberingEntry>,
+}
+
+impl EpisodeNumbering {
+    /// Build the table from `Episode.abs` of the serie episodes.
+    pub fn from_episodes(serie: &str, episodes: &[Episode]) -> Self {
+        let mut entries: Vec<EpisodeNumberingEntry> = episodes
+            .iter()
+            .filter(|e| e.serie == serie)
+            .map(|e| EpisodeNumberingEntry {
+                season: e.season,
+                number: e.number,
+                abs: e.abs,
+                ..Default::default()
+            })
+            .collect();
+        entries.sort_by_key(|e| (e.season, e.number));
+        Self {
+            serie: serie.to_string(),
+            provider: None,
+            entries,
+        }
+    }
+
+    /// Season and episode of absolute episode `abs`.
+    pub fn to_season_episode(&self, abs: u32) -> Option<(u32, u32)> {
+        self.entries
+            .iter()
+            .find(|e| e.abs == Some(abs))
+            .map(|e| (e.season, e.number))
+    }
+
+    pub fn to_absolute(&self, season: u32, number: u32) -> Option<u32> {
+        self.entries
+            .iter()
+            .find(|e| e.season == season && e.number == number)
+            .and_then(|e| e.abs)
+    }
+
+    /// Provider season and episode of scene `season`/`number`.
+    pub fn resolve_scene(&self, season: u32, number: u32) -> Option<(u32, u32)> {
+        self.entries
+            .iter()
+            .find(|e| e.scene_season == Some(season) && e.scene_number == Some(number))
+            .map(|e| (e.season, e.number))
+    }
+
+    /// Scene season and episode of provider `season`/`number`, itself when not remapped.
+    pub fn to_scene(&self, season: u32, number: u32) -> (u32, u32) {
+        self.entries
+            .iter()
+            .find(|e| e.season == season && e.number == number)
+            .map(|e| {
+                (
+                    e.scene_season.unwrap_or(e.season),
+                    e.scene_number.unwrap_or(e.number),
+                )
+            })
+            .unwrap_or((season, number))
+    }
+
+    /// Convert a reference without season (absolute numbering) to season/episode.
+    ///
+    /// Ranges spanning two seasons are cut at the end of the first one.
+    /// Returns `None` when the absolute number is unknown.
+    pub fn resolve_file_episode(&self, file: &FileEpisode) -> Option<FileEpisode> {
+        if file.season.is_some() {
+            return Some(file.clone());
+        }
+        let (season, episode) = self.to_season_episode(file.episode?)?;
+        let episode_to = file
+            .episode_to
+            .and_then(|to| self.to_season_episode(to))
+            .map(|(to_season, to_episode)| {
+                if to_season == season {
+                    to_episode
+                } else {
+                    self.entries
+                        .iter()
+                        .filter(|e| e.season == season)
+                        .map(|e| e.number)
+                        .max()
+                        .unwrap_or(episode)
+                }
+            })
+            .filter(|to| *to > episode);
+        Some(FileEpisode {
+            id: file.id.clone(),
+            season: Some(season),
+            episode: Some(episode),
+            episode_to,
+        })
+    }
+
+    /// Season and episode of a release filename: `SxxEyy` is taken as scene numbering,
+    /// otherwise the absolute number is looked up.
+    pub fn resolve_filename(&self, filename: &str) -> Option<(u32, u32)> {
+        let re = Regex::new(r"(?i)s(\d+)e(\d+)").unwrap();
+        if let Some(caps) = re.captures(filename) {
+            let season = caps[1].parse::<u32>().ok()?;
+            let number = caps[2].parse::<u32>().ok()?;
+            return Some(
+                self.resolve_scene(season, number)
+                    .unwrap_or((season, number)),
+            );
+        }
+        self.to_season_episode(parse_absolute_number(filename)?)
+    }
+}
+
+/// Extract the absolute episode number of a release filename (`One.Piece.1071.1080p.mkv`,
+/// `[Group] Show - 12v2 [1080p].mkv`).
+///
+/// Takes the first standalone number of 1 to 4 digits, optionally prefixed by `E`/`EP` or
+/// followed by a `v2` revision. Resolutions (`1080p`) and codecs (`x264`) are not standalone
+/// numbers. A `19xx`/`20xx` number is a year when it is in parentheses or brackets
+/// (`Show (2023) - 12`) or when another number is present (`Show.2023.EP04`), otherwise it
+/// is an episode (`One.Piece.1999.1080p`).
+pub fn parse_absolute_number(filename: &str) -> Option<u32> {
+    let re = Regex::new(r"(?i)^(?:e|ep)?(\d{1,4})(?:v\d)?$").unwrap();
+    let is_delimiter = |c: char| c.is_whitespace() || "._-[]()".contains(c);
+    let mut candidates = vec![];
+    let mut start = 0;
+    for (index, c) in filename.char_indices().chain([(filename.len(), ' ')]) {
+        if !is_delimiter(c) {
+            continue;
+        }
+        if let Some(caps) = re.captures(&filename[start..index]) {
+            let digits = &caps[1];
+            let year_like =
+                digits.len() == 4 && (digits.starts_with("19") || digits.starts_with("20"));
+            let before = filename[..start].chars().next_back();
+            let enclosed = matches!(before, Some('(') | Some('[')) && matches!(c, ')' | ']');
+            if let Ok(number) = digits.parse::<u32>() {
+                candidates.push((number, year_like, year_like && enclosed));
+            }
+        }
+        start = index + c.len_utf8();
+    }
+    let has_episode = candidates.iter().any(|(_, year_like, _)| !year_like);
+    candidates
+        .into_iter()
+        .find(|(_, year_like, enclosed)| !(*enclosed || (*year_like && has_episode)))
+        .map(|(number, _, _)| number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbering() -> EpisodeNumbering {
+        let episodes: Vec<Episode> = [(1, 1, 1), (1, 2, 2), (2, 1, 3), (2, 2, 4)]
+            .into_iter()
+            .map(|(season, number, abs)| Episode {
+                serie: "serie-1".to_string(),
+                season,
+                number,
+                abs: Some(abs),
+                ..Default::default()
+            })
+            .collect();
+        let mut numbering = EpisodeNumbering::from_episodes("serie-1", &episodes);
+        numbering.entries[2].scene_season = Some(1);
+        numbering.entries[2].scene_number = Some(3);
+        numbering
+    }
+
+    #[test]
+    fn absolute_and_scene_conversions() {
+        let numbering = numbering();
+        assert_eq!(numbering.to_season_episode(3), Some((2, 1)));
+        assert_eq!(numbering.to_season_episode(9), None);
+        assert_eq!(numbering.to_absolute(2, 2), Some(4));
+        assert_eq!(numbering.resolve_scene(1, 3), Some((2, 1)));
+        assert_eq!(numbering.to_scene(2, 1), (1, 3));
+        assert_eq!(numbering.to_scene(1, 1), (1, 1));
+    }
+
+    #[test]
+    fn resolve_absolute_file_episode() {
+        let numbering = numbering();
+        let file = FileEpisode {
+            id: "serie-1".to_string(),
+            season: None,
+            episode: Some(2),
+            episode_to: Some(3),
+        };
+        assert_eq!(
+            numbering.resolve_file_episode(&file),
+            Some(FileEpisode {
+                id: "serie-1".to_string(),
+                season: Some(1),
+                episode: Some(2),
+                episode_to: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_and_resolve_filenames() {
+        assert_eq!(
+            parse_absolute_number("One.Piece.1071.1080p.WEB.x264.mkv"),
+            Some(1071)
+        );
+        assert_eq!(
+            parse_absolute_number("[Group] Show - 12v2 [1080p].mkv"),
+            Some(12)
+        );
+        assert_eq!(parse_absolute_number("Show.2023.EP04.720p.mkv"), Some(4));
+        assert_eq!(parse_absolute_number("Show.1080p.x265.mkv"), None);
+        assert_eq!(parse_absolute_number("One.Piece.1999.1080p.mkv"), Some(1999));
+        assert_eq!(parse_absolute_number("One Piece - 2001 [1080p].mkv"), Some(2001));
+        assert_eq!(parse_absolute_number("Show (2023) [1080p].mkv"), None);
+        assert_eq!(parse_absolute_number("Show.2023.1071.1080p.mkv"), Some(1071));
+
+        let numbering = numbering();
+        assert_eq!(
+            numbering.resolve_filename("Show.004.1080p.mkv"),
+            Some((2, 2))
+        );
+        assert_eq!(
+            numbering.resolve_filename("Show.S01E03.1080p.mkv"),
+            Some((2, 1))
+        );
+        assert_eq!(
+            numbering.resolve_filename("Show.S02E02.1080p.mkv"),
+            Some((2, 2))
+        );
+    }
+}
@@ -1,11 +1,23 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::{domain::{other_ids::OtherIds, rs_ids::{ApplyRsIds, RsIds}}, url::RsLink, Gender};
+use crate::{
+    domain::{
+        other_ids::OtherIds,
+        rs_ids::ApplyRsIds,
+        update::{
+            append_vec, diff_list, diff_other_ids, diff_value, overwrite_if_some,
+            ChangeSummary, Patch,
+        },
+    },
+    url::RsLink,
+    Gender,
+};
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, ApplyRsIds)]
 #[serde(rename_all = "camelCase")]
 pub struct Person {
+    #[rs_id(redseat)]
     pub id: String,
     pub name: String,
     pub socials: Option<Vec<RsLink>>,
@@ -26,9 +38,13 @@ pub struct Person {
     #[serde(default)]
     pub generated: bool,
 
+    #[rs_id("imdb")]
     pub imdb: Option<String>,
+    #[rs_id("slug")]
     pub slug: Option<String>,
+    #[rs_id("tmdb")]
     pub tmdb: Option<u64>,
+    #[rs_id("trakt")]
     pub trakt: Option<u64>,
 
     pub death: Option<i64>,
@@ -36,39 +52,211 @@ pub struct Person {
     pub country: Option<String>,
     pub bio: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id(otherids)]
+    pub otherids: Option<OtherIds>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonForUpdate {
+    pub name: Option<String>,
+    pub socials: Option<Vec<RsLink>>,
+    pub add_socials: Option<Vec<RsLink>>,
+    pub remove_socials: Option<Vec<RsLink>>,
+    #[serde(rename = "type")]
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub kind: Patch<String>,
+    pub alt: Option<Vec<String>>,
+    pub add_alts: Option<Vec<String>>,
+    pub remove_alts: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub portrait: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub params: Patch<Value>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub birthday: Patch<i64>,
+    pub generated: Option<bool>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub imdb: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub slug: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub tmdb: Patch<u64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub trakt: Patch<u64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub death: Patch<i64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub gender: Patch<Gender>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub country: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub bio: Patch<String>,
     pub otherids: Option<OtherIds>,
+    pub add_otherids: Option<Vec<String>>,
+    pub remove_otherids: Option<Vec<String>>,
 }
 
-impl ApplyRsIds for Person {
-    fn apply_rs_ids(&mut self, ids: &RsIds) {
-        if let Some(trakt) = ids.trakt() { self.trakt = Some(trakt); }
-        if let Some(slug) = ids.slug() { self.slug = Some(slug.to_string()); }
-        if let Some(imdb) = ids.imdb() { self.imdb = Some(imdb.to_string()); }
-        if let Some(tmdb) = ids.tmdb() { self.tmdb = Some(tmdb); }
-        let known: &[&str] = &["redseat", "trakt", "slug", "imdb", "tmdb"];
-        let mut other = self.otherids.take().unwrap_or_default();
-        for (k, v) in ids.iter() {
-            if !known.contains(&k.as_str()) { other.add(k, v); }
+impl PersonForUpdate {
+    pub fn merge_from(&mut self, patch: Self) {
+        overwrite_if_some(&mut self.name, patch.name);
+        overwrite_if_some(&mut self.socials, patch.socials);
+        append_vec(&mut self.add_socials, patch.add_socials);
+        append_vec(&mut self.remove_socials, patch.remove_socials);
+        self.kind.merge_from(patch.kind);
+        overwrite_if_some(&mut self.alt, patch.alt);
+        append_vec(&mut self.add_alts, patch.add_alts);
+        append_vec(&mut self.remove_alts, patch.remove_alts);
+        self.portrait.merge_from(patch.portrait);
+        self.params.merge_from(patch.params);
+        self.birthday.merge_from(patch.birthday);
+        overwrite_if_some(&mut self.generated, patch.generated);
+        self.imdb.merge_from(patch.imdb);
+        self.slug.merge_from(patch.slug);
+        self.tmdb.merge_from(patch.tmdb);
+        self.trakt.merge_from(patch.trakt);
+        self.death.merge_from(patch.death);
+        self.gender.merge_from(patch.gender);
+        self.country.merge_from(patch.country);
+        self.bio.merge_from(patch.bio);
+        overwrite_if_some(&mut self.otherids, patch.otherids);
+        append_vec(&mut self.add_otherids, patch.add_otherids);
+        append_vec(&mut self.remove_otherids, patch.remove_otherids);
+    }
+
+    pub fn apply_to(&self, person: &mut Person) -> ChangeSummary {
+        let mut changes = ChangeSummary::default();
+        changes.set("name", &mut person.name, self.name.clone());
+        changes.set_list(
+            "socials",
+            &mut person.socials,
+            self.socials.clone(),
+            self.add_socials.clone(),
+            self.remove_socials.clone(),
+        );
+        changes.set_patch("type", &mut person.kind, self.kind.clone());
+        changes.set_list(
+            "alt",
+            &mut person.alt,
+            self.alt.clone(),
+            self.add_alts.clone(),
+            self.remove_alts.clone(),
+        );
+        changes.set_patch("portrait", &mut person.portrait, self.portrait.clone());
+        changes.set_patch("params", &mut person.params, self.params.clone());
+        changes.set_patch("birthday", &mut person.birthday, self.birthday);
+        changes.set("generated", &mut person.generated, self.generated);
+        changes.set_patch("imdb", &mut person.imdb, self.imdb.clone());
+        changes.set_patch("slug", &mut person.slug, self.slug.clone());
+        changes.set_patch("tmdb", &mut person.tmdb, self.tmdb);
+        changes.set_patch("trakt", &mut person.trakt, self.trakt);
+        changes.set_patch("death", &mut person.death, self.death);
+        changes.set_patch("gender", &mut person.gender, self.gender.clone());
+        changes.set_patch("country", &mut person.country, self.country.clone());
+        changes.set_patch("bio", &mut person.bio, self.bio.clone());
+        changes.set_other_ids(
+            "otherids",
+            &mut person.otherids,
+            self.otherids.clone(),
+            self.add_otherids.clone(),
+            self.remove_otherids.clone(),
+        );
+        changes
+    }
+
+    /// Patch turning `old` into `new`. Values removed in `new` are cleared.
+    pub fn diff(old: &Person, new: &Person) -> Self {
+        let (add_socials, remove_socials) = diff_list(&old.socials, &new.socials);
+        let (add_alts, remove_alts) = diff_list(&old.alt, &new.alt);
+        let (add_otherids, remove_otherids) = diff_other_ids(&old.otherids, &new.otherids);
+        Self {
+            name: diff_value(&old.name, &new.name),
+            socials: None,
+            add_socials,
+            remove_socials,
+            kind: Patch::diff(&old.kind, &new.kind),
+            alt: None,
+            add_alts,
+            remove_alts,
+            portrait: Patch::diff(&old.portrait, &new.portrait),
+            params: Patch::diff(&old.params, &new.params),
+            birthday: Patch::diff(&old.birthday, &new.birthday),
+            generated: diff_value(&old.generated, &new.generated),
+            imdb: Patch::diff(&old.imdb, &new.imdb),
+            slug: Patch::diff(&old.slug, &new.slug),
+            tmdb: Patch::diff(&old.tmdb, &new.tmdb),
+            trakt: Patch::diff(&old.trakt, &new.trakt),
+            death: Patch::diff(&old.death, &new.death),
+            gender: Patch::diff(&old.gender, &new.gender),
+            country: Patch::diff(&old.country, &new.country),
+            bio: Patch::diff(&old.bio, &new.bio),
+            otherids: None,
+            add_otherids,
+            remove_otherids,
         }
-        if !other.as_slice().is_empty() { self.otherids = Some(other); }
     }
 }
 
-impl From<Person> for RsIds {
-    fn from(value: Person) -> Self {
-        let mut ids = RsIds::default();
-        if let Some(v) = value.trakt { ids.set("trakt", v); }
-        if let Some(v) = value.slug { ids.set("slug", v); }
-        if let Some(v) = value.imdb { ids.set("imdb", v); }
-        if let Some(v) = value.tmdb { ids.set("tmdb", v); }
-        if let Some(other) = value.otherids {
-            for entry in other.into_vec() {
-                let _ = ids.try_add(entry);
-            }
-        }
-        if ids.try_add(value.id.clone()).is_err() {
-            ids.set("redseat", value.id);
-        }
-        ids
+#[cfg(test)]
+mod tests {
+    use super::{Person, PersonForUpdate};
+    use crate::domain::{
+        other_ids::OtherIds,
+        rs_ids::{ApplyRsIds, RsIds},
+    };
+    use crate::url::RsLink;
+
+    #[test]
+    fn person_rs_ids_roundtrip() {
+        let person = Person {
+            id: "person-1".to_string(),
+            name: "Person".to_string(),
+            imdb: Some("nm0000001".to_string()),
+            slug: Some("person-slug".to_string()),
+            tmdb: Some(51),
+            trakt: Some(52),
+            otherids: Some(OtherIds(vec!["tvmaze:53".to_string()])),
+            ..Default::default()
+        };
+        let ids = RsIds::from(person.clone());
+        assert_eq!(ids.redseat(), Some("person-1"));
+
+        let mut restored = Person::default();
+        restored.apply_rs_ids(&ids);
+        assert_eq!(restored.id, person.id);
+        assert_eq!(restored.imdb, person.imdb);
+        assert_eq!(restored.slug, person.slug);
+        assert_eq!(restored.tmdb, person.tmdb);
+        assert_eq!(restored.trakt, person.trakt);
+        assert_eq!(restored.otherids, person.otherids);
+    }
+
+    #[test]
+    fn person_diff_socials() {
+        let link = |platform: &str| RsLink {
+            platform: platform.to_string(),
+            id: "keanu".to_string(),
+            ..Default::default()
+        };
+        let old = Person {
+            name: "Keanu Reeves".to_string(),
+            socials: Some(vec![link("instagram")]),
+            ..Default::default()
+        };
+        let new = Person {
+            socials: Some(vec![link("x")]),
+            kind: Some("actor".to_string()),
+            generated: true,
+            ..old.clone()
+        };
+        let patch = PersonForUpdate::diff(&old, &new);
+        assert_eq!(patch.add_socials, Some(vec![link("x")]));
+        assert_eq!(patch.remove_socials, Some(vec![link("instagram")]));
+        assert_eq!(patch.generated, Some(true));
+
+        let mut updated = old.clone();
+        let changes = patch.apply_to(&mut updated);
+        assert_eq!(updated, new);
+        assert!(changes.contains("type"));
     }
 }
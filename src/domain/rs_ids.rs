@@ -12,6 +12,9 @@ pub enum RsIdsError {
     NotAMediaId(String),
     NoMediaIdRequired(Box<RsIds>),
     InvalidBase64Ids(String),
+    UnsupportedUrlIdVersion(u8),
+    UrlIdTooLong(usize),
+    MergeConflict(Vec<RsIdsConflict>),
 }
 
 // region:    --- Error Boilerplate
@@ -26,19 +29,108 @@ impl std::error::Error for RsIdsError {}
 
 // endregion: --- Error Boilerplate
 
+/// Derive `From<T> for RsIds` and `ApplyRsIds` from `#[rs_id(..)]` field attributes.
+///
+/// Exported with the default `derive` feature; the crate own types always derive it.
+#[cfg(feature = "derive")]
+pub use rs_plugin_common_interfaces_derive::ApplyRsIds;
+#[cfg(not(feature = "derive"))]
+pub(crate) use rs_plugin_common_interfaces_derive::ApplyRsIds;
+
 pub trait ApplyRsIds {
     fn apply_rs_ids(&mut self, ids: &RsIds);
 }
 
+/// Which side of a merge a conflicting value was taken from.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RsIdsMergeSide {
+    /// The instance `merge_with` was called on.
+    Current,
+    /// The instance passed as argument.
+    Other,
+}
+
+/// How `merge_with` resolves keys present on both sides with different values.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum RsIdsMergeStrategy {
+    /// Keep the current value (same behavior as `merge`).
+    #[default]
+    PreferSelf,
+    /// Replace the current value with the other one.
+    PreferOther,
+    /// Leave `self` untouched and return `RsIdsError::MergeConflict`.
+    FailOnConflict,
+}
+
+/// A key present on both sides of a merge with different values.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RsIdsConflict {
+    pub key: String,
+    pub current: String,
+    pub other: String,
+    /// Side whose value ended up in the merged result.
+    pub kept: RsIdsMergeSide,
+}
+
+impl RsIdsConflict {
+    /// The value that ended up in the merged result.
+    pub fn kept_value(&self) -> &str {
+        match self.kept {
+            RsIdsMergeSide::Current => &self.current,
+            RsIdsMergeSide::Other => &self.other,
+        }
+    }
+}
+
 /// Keys that store numeric (u64) values and should be serialized as numbers in JSON.
 const NUMERIC_KEYS: &[&str] = &["trakt", "tmdb", "tvdb", "tvrage", "anilist", "mal"];
 
 /// Priority order for selecting the "best" external ID.
 const EXTERNAL_PRIORITY: &[&str] = &[
     "trakt", "imdb", "tmdb", "tvdb", "isbn13", "oleid", "olwid", "gbvid", "anilist", "mangadex",
-    "mal", "asin",
+    "mal", "asin", "isrc", "mbrec", "mbrel", "mbrg", "mbart",
+];
+
+/// Version byte written first in compact URL ids.
+const COMPACT_URL_ID_VERSION: u8 = 1;
+
+/// Maximum length (including the `ids:` prefix) of a compact URL id we produce or accept.
+/// Legacy JSON ids from `to_url_id` are not bounded.
+pub const MAX_URL_ID_LENGTH: usize = 2048;
+
+/// Single byte codes for well known keys in compact URL ids.
+/// Append only: codes are part of the wire format and must never be reused.
+const COMPACT_KEY_CODES: &[(u8, &str)] = &[
+    (1, "redseat"),
+    (2, "imdb"),
+    (3, "trakt"),
+    (4, "tmdb"),
+    (5, "tvdb"),
+    (6, "tvrage"),
+    (7, "slug"),
+    (8, "isbn13"),
+    (9, "oleid"),
+    (10, "olwid"),
+    (11, "gbvid"),
+    (12, "anilist"),
+    (13, "mangadex"),
+    (14, "mal"),
+    (15, "asin"),
+    (16, "isrc"),
+    (17, "mbrec"),
+    (18, "mbrel"),
+    (19, "mbrg"),
+    (20, "mbart"),
 ];
 
+/// Key code used for keys missing from `COMPACT_KEY_CODES`; the key follows as a string.
+const COMPACT_CUSTOM_KEY: u8 = 0;
+/// Flag set on the key code when the value is stored as a varint.
+const COMPACT_NUMERIC_FLAG: u8 = 0x80;
+
 /// Alias mappings: (alias → canonical key).
 /// Used to normalize long-form and camelCase names to short canonical names.
 const KEY_ALIASES: &[(&str, &str)] = &[
@@ -49,6 +141,10 @@ const KEY_ALIASES: &[(&str, &str)] = &[
     ("anilist_manga_id", "anilist"),
     ("mangadex_manga_uuid", "mangadex"),
     ("myanimelist_manga_id", "mal"),
+    ("musicbrainz_recording_id", "mbrec"),
+    ("musicbrainz_release_id", "mbrel"),
+    ("musicbrainz_release_group_id", "mbrg"),
+    ("musicbrainz_artist_id", "mbart"),
     // camelCase (from old JSON format)
     ("openlibraryeditionid", "oleid"),
     ("openlibraryworkid", "olwid"),
@@ -56,8 +152,102 @@ const KEY_ALIASES: &[(&str, &str)] = &[
     ("anilistmangaid", "anilist"),
     ("mangadexmangauuid", "mangadex"),
     ("myanimelistmangaid", "mal"),
+    ("musicbrainzrecordingid", "mbrec"),
+    ("musicbrainzreleaseid", "mbrel"),
+    ("musicbrainzreleasegroupid", "mbrg"),
+    ("musicbrainzartistid", "mbart"),
 ];
 
+/// A single ID value with its structured details.
+///
+/// The wire format stays the pipe-encoded string used by `RsIds` (`"123|volume:1|chapter:2.5"`):
+/// it is parsed by `FromStr`/`From<&str>` and produced by `Display`. Detail keys are
+/// case-insensitive and stored lowercase; details are written back in key order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct RsIdEntry {
+    pub value: String,
+    pub details: BTreeMap<String, String>,
+}
+
+impl RsIdEntry {
+    pub fn new(value: impl ToString) -> Self {
+        Self {
+            value: value.to_string(),
+            details: BTreeMap::new(),
+        }
+    }
+
+    /// Builder style variant of `set_detail`.
+    pub fn with_detail(mut self, key: &str, value: impl ToString) -> Self {
+        self.set_detail(key, value);
+        self
+    }
+
+    pub fn detail(&self, key: &str) -> Option<&str> {
+        self.details
+            .get(&key.to_ascii_lowercase())
+            .map(|v| v.as_str())
+    }
+
+    pub fn detail_f64(&self, key: &str) -> Option<f64> {
+        self.detail(key).and_then(|v| v.parse().ok())
+    }
+
+    pub fn set_detail(&mut self, key: &str, value: impl ToString) {
+        let key = key.to_ascii_lowercase();
+        if key.is_empty() {
+            return;
+        }
+        self.details.insert(key, value.to_string());
+    }
+
+    pub fn remove_detail(&mut self, key: &str) -> Option<String> {
+        self.details.remove(&key.to_ascii_lowercase())
+    }
+}
+
+impl From<&str> for RsIdEntry {
+    fn from(raw: &str) -> Self {
+        let (base, details) = RsIds::split_details(raw);
+        let mut entry = RsIdEntry::new(base);
+        for (k, v) in details {
+            entry.set_detail(k, v);
+        }
+        entry
+    }
+}
+
+impl From<String> for RsIdEntry {
+    fn from(raw: String) -> Self {
+        RsIdEntry::from(raw.as_str())
+    }
+}
+
+impl std::str::FromStr for RsIdEntry {
+    type Err = std::convert::Infallible;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        Ok(RsIdEntry::from(raw))
+    }
+}
+
+impl std::fmt::Display for RsIdEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.value)?;
+        for (k, v) in &self.details {
+            write!(f, "|{}:{}", k, v)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<RsIdEntry> for String {
+    fn from(value: RsIdEntry) -> Self {
+        value.to_string()
+    }
+}
+
 /// A collection of media IDs stored as a flat key-value map.
 ///
 /// All values are stored as strings internally. Typed accessors (`trakt()`, `tmdb()`, etc.)
@@ -65,7 +255,8 @@ const KEY_ALIASES: &[(&str, &str)] = &[
 /// canonical short names (e.g., `openlibrary_edition_id` → `oleid`).
 ///
 /// Values may contain pipe-separated details for extra metadata:
-/// `"123|volume:1|chapter:2.5"`. Use `split_details()` to parse these.
+/// `"123|volume:1|chapter:2.5"`. Use `entry()` to read them as an `RsIdEntry`, or the
+/// per-key `detail()`/`set_detail()` helpers.
 #[derive(Debug, Clone, Default, Eq, PartialEq, Ord, PartialOrd)]
 pub struct RsIds(pub BTreeMap<String, String>);
 
@@ -188,6 +379,11 @@ impl RsIds {
     str_accessor!(openlibrary_work_id, "olwid");
     str_accessor!(google_books_volume_id, "gbvid");
     str_accessor!(mangadex_manga_uuid, "mangadex");
+    str_accessor!(isrc, "isrc");
+    str_accessor!(musicbrainz_recording_id, "mbrec");
+    str_accessor!(musicbrainz_release_id, "mbrel");
+    str_accessor!(musicbrainz_release_group_id, "mbrg");
+    str_accessor!(musicbrainz_artist_id, "mbart");
     u64_accessor!(trakt, "trakt");
     u64_accessor!(tmdb, "tmdb");
     u64_accessor!(tvdb, "tvdb");
@@ -253,9 +449,53 @@ impl RsIds {
         (base, details)
     }
 
+    /// Get the value for a key as a structured `RsIdEntry`.
+    pub fn entry(&self, key: &str) -> Option<RsIdEntry> {
+        self.get(key).map(RsIdEntry::from)
+    }
+
+    /// Store a structured entry, encoded with pipe-separated details.
+    pub fn set_entry(&mut self, key: &str, entry: RsIdEntry) {
+        self.set(key, entry);
+    }
+
+    /// Get a detail of the entry stored under `key` (e.g. the volume of the `anilist` id).
+    pub fn detail(&self, key: &str, detail_key: &str) -> Option<&str> {
+        let (_, details) = Self::split_details(self.get(key)?);
+        details
+            .into_iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(detail_key))
+            .map(|(_, v)| v)
+    }
+
+    /// Like `detail`, but parses the result as f64.
+    pub fn detail_f64(&self, key: &str, detail_key: &str) -> Option<f64> {
+        self.detail(key, detail_key).and_then(|v| v.parse().ok())
+    }
+
+    /// Set a detail on the entry stored under `key`.
+    /// Returns `false` (and does nothing) when there is no entry for `key`.
+    pub fn set_detail(&mut self, key: &str, detail_key: &str, value: impl ToString) -> bool {
+        let Some(mut entry) = self.entry(key) else {
+            return false;
+        };
+        entry.set_detail(detail_key, value);
+        self.set_entry(key, entry);
+        true
+    }
+
+    /// Remove a detail from the entry stored under `key` and return its value.
+    pub fn remove_detail(&mut self, key: &str, detail_key: &str) -> Option<String> {
+        let mut entry = self.entry(key)?;
+        let removed = entry.remove_detail(detail_key)?;
+        self.set_entry(key, entry);
+        Some(removed)
+    }
+
     /// Search all values for a pipe-separated detail with the given key.
     /// Also checks top-level entries as a fallback (for backward compatibility).
-    /// Returns the first match found.
+    /// Returns the first match found, whatever the provider: prefer `detail()` when the
+    /// key is known.
     pub fn find_detail<'a>(&'a self, detail_key: &str) -> Option<&'a str> {
         // First check top-level entries (backward compat: old JSON had volume/chapter as fields)
         if let Some(v) = self.get(detail_key) {
@@ -357,21 +597,109 @@ impl RsIds {
 
     /// Serialize to `ids:<base64url(json)>` format for use in URL path parameters.
     /// Encodes all IDs as a single opaque string that can be passed in place of `source:value`.
+    /// Prefer `to_compact_url_id` for shorter links once every consumer can decode it.
     pub fn to_url_id(&self) -> String {
         let json = serde_json::to_string(self).unwrap_or_default();
         let encoded = URL_SAFE_NO_PAD.encode(json.as_bytes());
         format!("ids:{}", encoded)
     }
 
-    /// Decode from a base64url-encoded JSON string (without the `ids:` prefix).
+    /// Serialize to a short, versioned `ids:<base64url(binary)>` token.
+    ///
+    /// Well known keys are stored as a single byte and canonical numeric values as varints,
+    /// which keeps deep links much shorter than `to_url_id`. Decoded by `from_base64url`
+    /// (and `TryFrom<String>`) like the JSON form.
+    pub fn to_compact_url_id(&self) -> Result<String, RsIdsError> {
+        Self::encode_compact(self.0.iter())
+    }
+
+    /// Like `to_compact_url_id` but only encodes the given keys (missing keys are skipped).
+    pub fn to_compact_url_id_with_keys(&self, keys: &[&str]) -> Result<String, RsIdsError> {
+        let mut selected: Vec<String> = keys.iter().map(|k| Self::canonicalize_key(k)).collect();
+        selected.sort();
+        selected.dedup();
+        Self::encode_compact(
+            selected
+                .iter()
+                .filter_map(|k| self.0.get(k).map(|v| (k, v))),
+        )
+    }
+
+    fn encode_compact<'a>(
+        entries: impl Iterator<Item = (&'a String, &'a String)>,
+    ) -> Result<String, RsIdsError> {
+        let mut bytes = vec![COMPACT_URL_ID_VERSION];
+        for (key, value) in entries {
+            let code = COMPACT_KEY_CODES
+                .iter()
+                .find(|(_, k)| *k == key)
+                .map(|(code, _)| *code)
+                .unwrap_or(COMPACT_CUSTOM_KEY);
+            let numeric = value
+                .parse::<u64>()
+                .ok()
+                .filter(|n| n.to_string() == *value);
+            let flag = if numeric.is_some() { COMPACT_NUMERIC_FLAG } else { 0 };
+            bytes.push(code | flag);
+            if code == COMPACT_CUSTOM_KEY {
+                write_compact_str(&mut bytes, key);
+            }
+            match numeric {
+                Some(n) => write_varint(&mut bytes, n),
+                None => write_compact_str(&mut bytes, value),
+            }
+        }
+        let url_id = format!("ids:{}", URL_SAFE_NO_PAD.encode(bytes));
+        if url_id.len() > MAX_URL_ID_LENGTH {
+            return Err(RsIdsError::UrlIdTooLong(url_id.len()));
+        }
+        Ok(url_id)
+    }
+
+    /// Decode an URL id payload (without the `ids:` prefix).
+    /// Accepts both the compact binary form and the legacy base64url-encoded JSON.
     pub fn from_base64url(encoded: &str) -> Result<Self, RsIdsError> {
-        let bytes = URL_SAFE_NO_PAD
-            .decode(encoded)
-            .map_err(|_| RsIdsError::InvalidBase64Ids(encoded.to_string()))?;
-        let json_str = std::str::from_utf8(&bytes)
-            .map_err(|_| RsIdsError::InvalidBase64Ids(encoded.to_string()))?;
-        serde_json::from_str(json_str)
-            .map_err(|_| RsIdsError::InvalidBase64Ids(encoded.to_string()))
+        let invalid = || RsIdsError::InvalidBase64Ids(encoded.to_string());
+        let bytes = URL_SAFE_NO_PAD.decode(encoded).map_err(|_| invalid())?;
+        match bytes.first() {
+            // Legacy form: JSON object
+            Some(b'{') => {
+                let json_str = std::str::from_utf8(&bytes).map_err(|_| invalid())?;
+                serde_json::from_str(json_str).map_err(|_| invalid())
+            }
+            Some(&COMPACT_URL_ID_VERSION) => {
+                let len = encoded.len() + "ids:".len();
+                if len > MAX_URL_ID_LENGTH {
+                    return Err(RsIdsError::UrlIdTooLong(len));
+                }
+                Self::decode_compact(&bytes[1..]).ok_or_else(invalid)
+            }
+            Some(version) => Err(RsIdsError::UnsupportedUrlIdVersion(*version)),
+            None => Err(invalid()),
+        }
+    }
+
+    fn decode_compact(mut bytes: &[u8]) -> Option<Self> {
+        let mut ids = RsIds::default();
+        while let Some((&flagged, rest)) = bytes.split_first() {
+            bytes = rest;
+            let code = flagged & !COMPACT_NUMERIC_FLAG;
+            let key = if code == COMPACT_CUSTOM_KEY {
+                read_compact_str(&mut bytes)?
+            } else {
+                COMPACT_KEY_CODES
+                    .iter()
+                    .find(|(c, _)| *c == code)
+                    .map(|(_, k)| k.to_string())?
+            };
+            let value = if flagged & COMPACT_NUMERIC_FLAG != 0 {
+                read_varint(&mut bytes)?.to_string()
+            } else {
+                read_compact_str(&mut bytes)?
+            };
+            ids.set(&key, value);
+        }
+        Some(ids)
     }
 
     /// Return the first available TV/movie ID (imdb → trakt → tmdb → tvdb) or error.
@@ -429,12 +757,123 @@ impl RsIds {
         }
     }
 
+    /// List keys present in both instances with different values.
+    /// `kept` is set to `Current`, as `merge` would do.
+    pub fn conflicts(&self, other: &RsIds) -> Vec<RsIdsConflict> {
+        other
+            .0
+            .iter()
+            .filter_map(|(k, v)| {
+                let current = self.0.get(k)?;
+                if current == v {
+                    return None;
+                }
+                Some(RsIdsConflict {
+                    key: k.clone(),
+                    current: current.clone(),
+                    other: v.clone(),
+                    kept: RsIdsMergeSide::Current,
+                })
+            })
+            .collect()
+    }
+
+    /// Merge entries from `other` into `self` and report every key where both sides disagreed.
+    ///
+    /// Missing keys are always added. Conflicting keys are resolved with `strategy`; with
+    /// `FailOnConflict` nothing is merged and the conflicts are returned as an error.
+    pub fn merge_with(
+        &mut self,
+        other: &RsIds,
+        strategy: RsIdsMergeStrategy,
+    ) -> Result<Vec<RsIdsConflict>, RsIdsError> {
+        let mut conflicts = self.conflicts(other);
+        match strategy {
+            RsIdsMergeStrategy::FailOnConflict if !conflicts.is_empty() => {
+                return Err(RsIdsError::MergeConflict(conflicts));
+            }
+            RsIdsMergeStrategy::PreferOther => {
+                for conflict in conflicts.iter_mut() {
+                    self.0.insert(conflict.key.clone(), conflict.other.clone());
+                    conflict.kept = RsIdsMergeSide::Other;
+                }
+            }
+            _ => {}
+        }
+        self.merge(other);
+        Ok(conflicts)
+    }
+
+    /// Score how likely two instances describe the same entity, from `0.0` to `1.0`.
+    ///
+    /// Only keys present on both sides are considered: the score is the share of them
+    /// holding the same value. Instances without any common key score `0.0`.
+    pub fn similarity(&self, other: &RsIds) -> f64 {
+        let mut shared = 0usize;
+        let mut matching = 0usize;
+        for (k, v) in &self.0 {
+            if let Some(other_value) = other.0.get(k) {
+                shared += 1;
+                if v == other_value {
+                    matching += 1;
+                }
+            }
+        }
+        if shared == 0 {
+            0.0
+        } else {
+            matching as f64 / shared as f64
+        }
+    }
+
     /// Apply these IDs to a target that implements `ApplyRsIds`.
     pub fn apply_to<T: ApplyRsIds>(&self, target: &mut T) {
         target.apply_rs_ids(self);
     }
 }
 
+// -- Compact URL id helpers --
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        let chunk = (byte & 0x7f) as u64;
+        if shift == 63 && chunk > 1 {
+            return None;
+        }
+        value |= chunk << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn write_compact_str(bytes: &mut Vec<u8>, value: &str) {
+    write_varint(bytes, value.len() as u64);
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+fn read_compact_str(bytes: &mut &[u8]) -> Option<String> {
+    let len = usize::try_from(read_varint(bytes)?).ok()?;
+    if len > bytes.len() {
+        return None;
+    }
+    let (value, rest) = bytes.split_at(len);
+    *bytes = rest;
+    String::from_utf8(value.to_vec()).ok()
+}
+
 // -- Serde --
 
 impl Serialize for RsIds {
@@ -888,6 +1327,54 @@ mod tests {
         assert_eq!(ids.find_detail("volume"), Some("1"));
     }
 
+    #[test]
+    fn test_rs_id_entry_parse_and_format() {
+        let entry: RsIdEntry = "123|Volume:1|chapter:2.5".parse().unwrap();
+        assert_eq!(entry.value, "123");
+        assert_eq!(entry.detail("volume"), Some("1"));
+        assert_eq!(entry.detail_f64("CHAPTER"), Some(2.5));
+        assert_eq!(entry.to_string(), "123|chapter:2.5|volume:1");
+
+        let entry = RsIdEntry::new("abc").with_detail("extra", 42);
+        assert_eq!(entry.to_string(), "abc|extra:42");
+        assert_eq!(RsIdEntry::from("plain").to_string(), "plain");
+    }
+
+    #[test]
+    fn test_rs_id_entry_serde_uses_pipe_string() {
+        let entry = RsIdEntry::new("123").with_detail("volume", 1);
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(json, "\"123|volume:1\"");
+        let parsed: RsIdEntry = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, entry);
+    }
+
+    #[test]
+    fn test_per_entry_details() {
+        let mut ids = RsIds::default();
+        ids.set("anilist", "123|volume:1");
+        ids.set("mangadex", "uuid-1|volume:7|chapter:3");
+
+        assert_eq!(ids.detail("anilist", "volume"), Some("1"));
+        assert_eq!(ids.detail_f64("mangadex", "volume"), Some(7.0));
+        assert_eq!(ids.detail("anilist", "chapter"), None);
+        assert_eq!(ids.detail("mal", "volume"), None);
+
+        assert!(ids.set_detail("anilist", "chapter", 2.5));
+        assert_eq!(ids.get("anilist"), Some("123|chapter:2.5|volume:1"));
+        assert_eq!(ids.anilist_manga_id(), Some(123));
+        assert!(!ids.set_detail("mal", "chapter", 1));
+        assert!(!ids.has("mal"));
+
+        assert_eq!(ids.remove_detail("mangadex", "volume"), Some("7".to_string()));
+        assert_eq!(ids.get("mangadex"), Some("uuid-1|chapter:3"));
+        assert_eq!(ids.remove_detail("mangadex", "volume"), None);
+
+        let entry = ids.entry("anilist").unwrap();
+        assert_eq!(entry.value, "123");
+        assert_eq!(entry.detail_f64("chapter"), Some(2.5));
+    }
+
     #[test]
     fn test_find_detail_top_level_fallback() {
         let mut ids = RsIds::default();
@@ -986,6 +1473,107 @@ mod tests {
         assert_eq!(b.imdb(), Some("tt123"));
     }
 
+    #[test]
+    fn test_conflicts_reports_differing_keys_only() {
+        let mut a = RsIds::default();
+        a.set("imdb", "tt111");
+        a.set("tmdb", "42");
+
+        let mut b = RsIds::default();
+        b.set("imdb", "tt111");
+        b.set("tmdb", "43");
+        b.set("trakt", "999");
+
+        let conflicts = a.conflicts(&b);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].key, "tmdb");
+        assert_eq!(conflicts[0].current, "42");
+        assert_eq!(conflicts[0].other, "43");
+        assert_eq!(conflicts[0].kept, RsIdsMergeSide::Current);
+    }
+
+    #[test]
+    fn test_merge_with_prefer_self() -> Result<(), RsIdsError> {
+        let mut a = RsIds::default();
+        a.set("tmdb", "42");
+        let mut b = RsIds::default();
+        b.set("tmdb", "43");
+        b.set("trakt", "999");
+
+        let conflicts = a.merge_with(&b, RsIdsMergeStrategy::PreferSelf)?;
+        assert_eq!(a.tmdb(), Some(42));
+        assert_eq!(a.trakt(), Some(999));
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].kept_value(), "42");
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_with_prefer_other() -> Result<(), RsIdsError> {
+        let mut a = RsIds::default();
+        a.set("tmdb", "42");
+        a.set("imdb", "tt111");
+        let mut b = RsIds::default();
+        b.set("tmdb", "43");
+
+        let conflicts = a.merge_with(&b, RsIdsMergeStrategy::PreferOther)?;
+        assert_eq!(a.tmdb(), Some(43));
+        assert_eq!(a.imdb(), Some("tt111"));
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].kept, RsIdsMergeSide::Other);
+        assert_eq!(conflicts[0].kept_value(), "43");
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_with_fail_on_conflict_leaves_self_untouched() {
+        let mut a = RsIds::default();
+        a.set("tmdb", "42");
+        let mut b = RsIds::default();
+        b.set("tmdb", "43");
+        b.set("trakt", "999");
+
+        let result = a.merge_with(&b, RsIdsMergeStrategy::FailOnConflict);
+        match result {
+            Err(RsIdsError::MergeConflict(conflicts)) => {
+                assert_eq!(conflicts.len(), 1);
+                assert_eq!(conflicts[0].key, "tmdb");
+            }
+            _ => panic!("expected a merge conflict"),
+        }
+        assert_eq!(a.len(), 1);
+        assert_eq!(a.tmdb(), Some(42));
+
+        let mut c = RsIds::default();
+        c.set("trakt", "999");
+        let conflicts = a
+            .merge_with(&c, RsIdsMergeStrategy::FailOnConflict)
+            .unwrap();
+        assert!(conflicts.is_empty());
+        assert_eq!(a.trakt(), Some(999));
+    }
+
+    #[test]
+    fn test_similarity() {
+        let mut a = RsIds::default();
+        a.set("imdb", "tt111");
+        a.set("tmdb", "42");
+        a.set("redseat", "local");
+
+        let mut b = RsIds::default();
+        b.set("imdb", "tt111");
+        b.set("tmdb", "42");
+        assert_eq!(a.similarity(&b), 1.0);
+
+        b.set("tmdb", "43");
+        assert_eq!(a.similarity(&b), 0.5);
+
+        let mut c = RsIds::default();
+        c.set("trakt", "1");
+        assert_eq!(a.similarity(&c), 0.0);
+        assert_eq!(RsIds::default().similarity(&a), 0.0);
+    }
+
     #[cfg(feature = "rusqlite")]
     #[test]
     fn test_rusqlite_roundtrip_rsids() -> rusqlite::Result<()> {
@@ -1034,6 +1622,85 @@ mod tests {
         assert_eq!(ids.tmdb(), Some(12345));
     }
 
+    #[test]
+    fn test_compact_url_id_roundtrip() -> Result<(), RsIdsError> {
+        let mut ids = RsIds::default();
+        ids.set("trakt", "905982");
+        ids.set("imdb", "tt1234567");
+        ids.set("redseat", "local-1");
+        ids.set("anilist", "123|volume:1");
+        ids.set("custom", "007");
+        ids.set("anidb", "42");
+
+        let url_id = ids.to_compact_url_id()?;
+        assert!(url_id.starts_with("ids:"));
+        assert!(RsIds::is_id(&url_id));
+        assert!(url_id.len() < ids.to_url_id().len());
+
+        let decoded: RsIds = url_id.try_into()?;
+        assert_eq!(decoded, ids);
+        // Non canonical numbers are kept as strings
+        assert_eq!(decoded.get("custom"), Some("007"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_url_id_with_keys_subset() -> Result<(), RsIdsError> {
+        let mut ids = RsIds::default();
+        ids.set("trakt", "905982");
+        ids.set("imdb", "tt1234567");
+        ids.set("tmdb", "42");
+
+        let url_id = ids.to_compact_url_id_with_keys(&["TMDB", "imdb", "tvdb"])?;
+        let decoded: RsIds = url_id.try_into()?;
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded.tmdb(), Some(42));
+        assert_eq!(decoded.imdb(), Some("tt1234567"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_url_id_version_and_limits() {
+        // Unknown future version
+        let encoded = URL_SAFE_NO_PAD.encode([2u8, 3, 1]);
+        match RsIds::from_base64url(&encoded) {
+            Err(RsIdsError::UnsupportedUrlIdVersion(2)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        // Truncated payload: string length larger than remaining bytes
+        let encoded = URL_SAFE_NO_PAD.encode([1u8, 2, 10, b't']);
+        assert!(RsIds::from_base64url(&encoded).is_err());
+
+        let mut ids = RsIds::default();
+        ids.set("custom", "x".repeat(MAX_URL_ID_LENGTH));
+        match ids.to_compact_url_id() {
+            Err(RsIdsError::UrlIdTooLong(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        let too_long = [vec![COMPACT_URL_ID_VERSION], vec![0; MAX_URL_ID_LENGTH]].concat();
+        match RsIds::from_base64url(&URL_SAFE_NO_PAD.encode(too_long)) {
+            Err(RsIdsError::UrlIdTooLong(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        // Legacy JSON ids are not bounded
+        let legacy = ids.to_url_id();
+        assert!(legacy.len() > MAX_URL_ID_LENGTH);
+        assert_eq!(RsIds::try_from(legacy).unwrap(), ids);
+    }
+
+    #[test]
+    fn test_varint_roundtrip() {
+        for value in [0u64, 1, 127, 128, 300, 905982, u64::MAX] {
+            let mut bytes = vec![];
+            write_varint(&mut bytes, value);
+            let mut slice = bytes.as_slice();
+            assert_eq!(read_varint(&mut slice), Some(value));
+            assert!(slice.is_empty());
+        }
+    }
+
     #[test]
     fn test_from_base64url_invalid() {
         let result: Result<RsIds, _> = "ids:not-valid-base64!!!".to_string().try_into();
@@ -3,6 +3,7 @@ use rusqlite::{
     ToSql,
 };
 
+use super::credit::CreditRole;
 use super::media::{FileType, RsGpsPosition};
 use super::movie::MovieStatus;
 use super::serie::{SerieStatus, SerieType};
@@ -37,21 +38,22 @@ impl ToSql for SerieType {
 
 impl FromSql for RsGpsPosition {
     fn column_result(value: ValueRef) -> FromSqlResult<Self> {
+        // stored rows are read back as is, positions are validated on write
         String::column_result(value).and_then(|as_string| {
-            let mut splitted = as_string.split(",");
-            let lat = splitted
-                .next()
-                .and_then(|f| f.parse::<f64>().ok())
-                .ok_or(FromSqlError::InvalidType)?;
-            let long = splitted
-                .next()
-                .and_then(|f| f.parse::<f64>().ok())
-                .ok_or(FromSqlError::InvalidType)?;
-            Ok(RsGpsPosition { lat, long })
+            RsGpsPosition::parse_unchecked(&as_string)
+                .map_err(|e| FromSqlError::Other(Box::new(e)))
         })
     }
 }
 
+impl ToSql for RsGpsPosition {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        self.validate()
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
 impl FromSql for FileType {
     fn column_result(value: ValueRef) -> FromSqlResult<Self> {
         String::column_result(value).and_then(|as_string| {
@@ -81,3 +83,18 @@ impl ToSql for MovieStatus {
         Ok(ToSqlOutput::from(self.to_string()))
     }
 }
+
+// Credit
+impl FromSql for CreditRole {
+    fn column_result(value: ValueRef) -> FromSqlResult<Self> {
+        String::column_result(value).and_then(|as_string| {
+            CreditRole::try_from(&*as_string).map_err(|_| FromSqlError::InvalidType)
+        })
+    }
+}
+
+impl ToSql for CreditRole {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
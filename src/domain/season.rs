@@ -0,0 +1,135 @@
+use crate::domain::{other_ids::OtherIds, rs_ids::ApplyRsIds, tools::rating_serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, ApplyRsIds)]
+#[serde(rename_all = "camelCase")]
+#[rs_ids(redseat_fn = "id")]
+pub struct Season {
+    pub serie: String,
+    pub number: u32,
+
+    pub name: Option<String>,
+    pub overview: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub airdate: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub episodes: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("imdb")]
+    pub imdb: Option<String>,
+    #[rs_id("slug")]
+    pub slug: Option<String>,
+    #[rs_id("tmdb")]
+    pub tmdb: Option<u64>,
+    #[rs_id("trakt")]
+    pub trakt: Option<u64>,
+    #[rs_id("tvdb")]
+    pub tvdb: Option<u64>,
+    #[rs_id(otherids)]
+    pub otherids: Option<OtherIds>,
+
+    #[serde(serialize_with = "rating_serializer")]
+    pub trakt_rating: Option<f32>,
+    pub trakt_votes: Option<u64>,
+
+    #[serde(default)]
+    pub modified: u64,
+    #[serde(default)]
+    pub added: u64,
+
+    #[serde(default)]
+    pub posterv: u64,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serie_name: Option<String>,
+}
+
+impl Season {
+    pub fn id(&self) -> String {
+        format!("{}x{}", self.serie, self.number)
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+pub mod season_rusqlite {
+    use rusqlite::{
+        types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+        ToSql,
+    };
+
+    use super::Season;
+
+    impl FromSql for Season {
+        fn column_result(value: ValueRef) -> FromSqlResult<Self> {
+            String::column_result(value).and_then(|as_string| {
+                serde_json::from_str(&as_string).map_err(|_| FromSqlError::InvalidType)
+            })
+        }
+    }
+    impl ToSql for Season {
+        fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+            let serialized = serde_json::to_string(self).map_err(|_| FromSqlError::InvalidType)?;
+            Ok(ToSqlOutput::from(serialized))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Season;
+    use crate::domain::{
+        other_ids::OtherIds,
+        rs_ids::{ApplyRsIds, RsIds},
+    };
+
+    #[test]
+    fn season_rs_ids_roundtrip() {
+        let season = Season {
+            serie: "serie-1".to_string(),
+            number: 2,
+            tmdb: Some(3624),
+            tvdb: Some(364731),
+            otherids: Some(OtherIds(vec!["tvmaze:1234".to_string()])),
+            ..Default::default()
+        };
+        let ids = RsIds::from(season.clone());
+        assert_eq!(ids.redseat(), Some("serie-1x2"));
+        assert_eq!(ids.tvdb(), Some(364731));
+
+        let mut restored = Season::default();
+        restored.apply_rs_ids(&ids);
+        assert_eq!(restored.tmdb, season.tmdb);
+        assert_eq!(restored.tvdb, season.tvdb);
+        assert_eq!(restored.otherids, season.otherids);
+    }
+
+    #[cfg(feature = "rusqlite")]
+    #[test]
+    fn test_rusqlite_roundtrip_season() -> rusqlite::Result<()> {
+        use rusqlite::Connection;
+
+        let conn = Connection::open_in_memory()?;
+        conn.execute("CREATE TABLE test_seasons (season TEXT NOT NULL)", [])?;
+
+        let season = Season {
+            serie: "serie-1".to_string(),
+            number: 1,
+            name: Some("Season 1".to_string()),
+            episodes: Some(10),
+            ..Default::default()
+        };
+        conn.execute("INSERT INTO test_seasons (season) VALUES (?1)", [&season])?;
+
+        let loaded: Season =
+            conn.query_row("SELECT season FROM test_seasons LIMIT 1", [], |row| {
+                row.get(0)
+            })?;
+        assert_eq!(loaded, season);
+        Ok(())
+    }
+}
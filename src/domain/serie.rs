@@ -4,8 +4,11 @@ use strum_macros::{Display, EnumString};
 
 use crate::domain::{
     other_ids::OtherIds,
-    rs_ids::{ApplyRsIds, RsIds},
+    rs_ids::ApplyRsIds,
     tools::rating_serializer,
+    update::{
+        append_vec, diff_list, diff_other_ids, diff_value, overwrite_if_some, ChangeSummary, Patch,
+    },
 };
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Display, EnumString)]
@@ -104,10 +107,11 @@ pub enum SerieStatus {
     Unknown,
 }
 
-#[derive(Debug, Serialize, PartialEq, Deserialize, Clone, Default)]
+#[derive(Debug, Serialize, PartialEq, Deserialize, Clone, Default, ApplyRsIds)]
 #[serde(rename_all = "camelCase")]
 pub struct Serie {
     #[serde(default)]
+    #[rs_id(redseat)]
     pub id: String,
 
     pub name: String,
@@ -120,19 +124,29 @@ pub struct Serie {
     pub status: Option<SerieStatus>,
     pub params: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("imdb")]
     pub imdb: Option<String>,
+    #[rs_id("slug")]
     pub slug: Option<String>,
+    #[rs_id("tmdb")]
     pub tmdb: Option<u64>,
+    #[rs_id("trakt")]
     pub trakt: Option<u64>,
+    #[rs_id("tvdb")]
     pub tvdb: Option<u64>,
+    #[rs_id(otherids)]
     pub otherids: Option<OtherIds>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("olwid")]
     pub openlibrary_work_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("anilist")]
     pub anilist_manga_id: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("mangadex")]
     pub mangadex_manga_uuid: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("mal")]
     pub myanimelist_manga_id: Option<u64>,
 
     #[serde(serialize_with = "rating_serializer")]
@@ -161,68 +175,165 @@ pub struct Serie {
     pub cardv: u64,
 }
 
-impl From<Serie> for RsIds {
-    fn from(value: Serie) -> Self {
-        let mut ids = RsIds::default();
-        if let Some(v) = value.trakt { ids.set("trakt", v); }
-        if let Some(v) = value.slug { ids.set("slug", v); }
-        if let Some(v) = value.tvdb { ids.set("tvdb", v); }
-        if let Some(v) = value.imdb { ids.set("imdb", v); }
-        if let Some(v) = value.tmdb { ids.set("tmdb", v); }
-        if let Some(v) = value.anilist_manga_id { ids.set("anilist", v); }
-        if let Some(v) = value.mangadex_manga_uuid { ids.set("mangadex", v); }
-        if let Some(v) = value.myanimelist_manga_id { ids.set("mal", v); }
-        if let Some(v) = value.openlibrary_work_id { ids.set("olwid", v); }
-        if let Some(other) = value.otherids {
-            for entry in other.into_vec() {
-                let _ = ids.try_add(entry);
-            }
-        }
-        if ids.try_add(value.id.clone()).is_err() {
-            ids.set("redseat", value.id);
-        }
-        ids
-    }
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SerieForUpdate {
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub kind: Patch<SerieType>,
+    pub alt: Option<Vec<String>>,
+    pub add_alts: Option<Vec<String>>,
+    pub remove_alts: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub status: Patch<SerieStatus>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub params: Patch<Value>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub imdb: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub slug: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub tmdb: Patch<u64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub trakt: Patch<u64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub tvdb: Patch<u64>,
+    pub otherids: Option<OtherIds>,
+    pub add_otherids: Option<Vec<String>>,
+    pub remove_otherids: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub openlibrary_work_id: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub anilist_manga_id: Patch<u64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub mangadex_manga_uuid: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub myanimelist_manga_id: Patch<u64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub imdb_rating: Patch<f32>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub imdb_votes: Patch<u64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub trakt_rating: Patch<f32>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub trakt_votes: Patch<u64>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub trailer: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub year: Patch<u16>,
 }
 
-impl ApplyRsIds for Serie {
-    fn apply_rs_ids(&mut self, ids: &RsIds) {
-        if let Some(redseat) = ids.redseat() {
-            self.id = redseat.to_string();
-        }
-        if let Some(trakt) = ids.trakt() {
-            self.trakt = Some(trakt);
-        }
-        if let Some(slug) = ids.slug() {
-            self.slug = Some(slug.to_string());
-        }
-        if let Some(tvdb) = ids.tvdb() {
-            self.tvdb = Some(tvdb);
-        }
-        if let Some(imdb) = ids.imdb() {
-            self.imdb = Some(imdb.to_string());
-        }
-        if let Some(tmdb) = ids.tmdb() {
-            self.tmdb = Some(tmdb);
-        }
-        if let Some(olwid) = ids.openlibrary_work_id() {
-            self.openlibrary_work_id = Some(olwid.to_string());
-        }
-        if let Some(anilist) = ids.anilist_manga_id() {
-            self.anilist_manga_id = Some(anilist);
-        }
-        if let Some(mangadex) = ids.mangadex_manga_uuid() {
-            self.mangadex_manga_uuid = Some(mangadex.to_string());
-        }
-        if let Some(mal) = ids.myanimelist_manga_id() {
-            self.myanimelist_manga_id = Some(mal);
-        }
-        let known: &[&str] = &["redseat", "trakt", "slug", "tvdb", "imdb", "tmdb", "olwid", "anilist", "mangadex", "mal"];
-        let mut other = self.otherids.take().unwrap_or_default();
-        for (k, v) in ids.iter() {
-            if !known.contains(&k.as_str()) { other.add(k, v); }
+impl SerieForUpdate {
+    pub fn merge_from(&mut self, patch: Self) {
+        overwrite_if_some(&mut self.name, patch.name);
+        self.kind.merge_from(patch.kind);
+        overwrite_if_some(&mut self.alt, patch.alt);
+        append_vec(&mut self.add_alts, patch.add_alts);
+        append_vec(&mut self.remove_alts, patch.remove_alts);
+        self.status.merge_from(patch.status);
+        self.params.merge_from(patch.params);
+        self.imdb.merge_from(patch.imdb);
+        self.slug.merge_from(patch.slug);
+        self.tmdb.merge_from(patch.tmdb);
+        self.trakt.merge_from(patch.trakt);
+        self.tvdb.merge_from(patch.tvdb);
+        overwrite_if_some(&mut self.otherids, patch.otherids);
+        append_vec(&mut self.add_otherids, patch.add_otherids);
+        append_vec(&mut self.remove_otherids, patch.remove_otherids);
+        self.openlibrary_work_id.merge_from(patch.openlibrary_work_id);
+        self.anilist_manga_id.merge_from(patch.anilist_manga_id);
+        self.mangadex_manga_uuid.merge_from(patch.mangadex_manga_uuid);
+        self.myanimelist_manga_id.merge_from(patch.myanimelist_manga_id);
+        self.imdb_rating.merge_from(patch.imdb_rating);
+        self.imdb_votes.merge_from(patch.imdb_votes);
+        self.trakt_rating.merge_from(patch.trakt_rating);
+        self.trakt_votes.merge_from(patch.trakt_votes);
+        self.trailer.merge_from(patch.trailer);
+        self.year.merge_from(patch.year);
+    }
+
+    pub fn apply_to(&self, serie: &mut Serie) -> ChangeSummary {
+        let mut changes = ChangeSummary::default();
+        changes.set("name", &mut serie.name, self.name.clone());
+        changes.set_patch("type", &mut serie.kind, self.kind.clone());
+        changes.set_list(
+            "alt",
+            &mut serie.alt,
+            self.alt.clone(),
+            self.add_alts.clone(),
+            self.remove_alts.clone(),
+        );
+        changes.set_patch("status", &mut serie.status, self.status.clone());
+        changes.set_patch("params", &mut serie.params, self.params.clone());
+        changes.set_patch("imdb", &mut serie.imdb, self.imdb.clone());
+        changes.set_patch("slug", &mut serie.slug, self.slug.clone());
+        changes.set_patch("tmdb", &mut serie.tmdb, self.tmdb);
+        changes.set_patch("trakt", &mut serie.trakt, self.trakt);
+        changes.set_patch("tvdb", &mut serie.tvdb, self.tvdb);
+        changes.set_other_ids(
+            "otherids",
+            &mut serie.otherids,
+            self.otherids.clone(),
+            self.add_otherids.clone(),
+            self.remove_otherids.clone(),
+        );
+        changes.set_patch(
+            "openlibraryWorkId",
+            &mut serie.openlibrary_work_id,
+            self.openlibrary_work_id.clone(),
+        );
+        changes.set_patch("anilistMangaId", &mut serie.anilist_manga_id, self.anilist_manga_id);
+        changes.set_patch(
+            "mangadexMangaUuid",
+            &mut serie.mangadex_manga_uuid,
+            self.mangadex_manga_uuid.clone(),
+        );
+        changes.set_patch(
+            "myanimelistMangaId",
+            &mut serie.myanimelist_manga_id,
+            self.myanimelist_manga_id,
+        );
+        changes.set_patch("imdbRating", &mut serie.imdb_rating, self.imdb_rating);
+        changes.set_patch("imdbVotes", &mut serie.imdb_votes, self.imdb_votes);
+        changes.set_patch("traktRating", &mut serie.trakt_rating, self.trakt_rating);
+        changes.set_patch("traktVotes", &mut serie.trakt_votes, self.trakt_votes);
+        changes.set_patch("trailer", &mut serie.trailer, self.trailer.clone());
+        changes.set_patch("year", &mut serie.year, self.year);
+        changes
+    }
+
+    /// Patch turning `old` into `new`. Values removed in `new` are cleared.
+    pub fn diff(old: &Serie, new: &Serie) -> Self {
+        let (add_alts, remove_alts) = diff_list(&old.alt, &new.alt);
+        let (add_otherids, remove_otherids) = diff_other_ids(&old.otherids, &new.otherids);
+        Self {
+            name: diff_value(&old.name, &new.name),
+            kind: Patch::diff(&old.kind, &new.kind),
+            alt: None,
+            add_alts,
+            remove_alts,
+            status: Patch::diff(&old.status, &new.status),
+            params: Patch::diff(&old.params, &new.params),
+            imdb: Patch::diff(&old.imdb, &new.imdb),
+            slug: Patch::diff(&old.slug, &new.slug),
+            tmdb: Patch::diff(&old.tmdb, &new.tmdb),
+            trakt: Patch::diff(&old.trakt, &new.trakt),
+            tvdb: Patch::diff(&old.tvdb, &new.tvdb),
+            otherids: None,
+            add_otherids,
+            remove_otherids,
+            openlibrary_work_id: Patch::diff(&old.openlibrary_work_id, &new.openlibrary_work_id),
+            anilist_manga_id: Patch::diff(&old.anilist_manga_id, &new.anilist_manga_id),
+            mangadex_manga_uuid: Patch::diff(&old.mangadex_manga_uuid, &new.mangadex_manga_uuid),
+            myanimelist_manga_id: Patch::diff(&old.myanimelist_manga_id, &new.myanimelist_manga_id),
+            imdb_rating: Patch::diff(&old.imdb_rating, &new.imdb_rating),
+            imdb_votes: Patch::diff(&old.imdb_votes, &new.imdb_votes),
+            trakt_rating: Patch::diff(&old.trakt_rating, &new.trakt_rating),
+            trakt_votes: Patch::diff(&old.trakt_votes, &new.trakt_votes),
+            trailer: Patch::diff(&old.trailer, &new.trailer),
+            year: Patch::diff(&old.year, &new.year),
         }
-        if !other.as_slice().is_empty() { self.otherids = Some(other); }
     }
 }
 
@@ -230,7 +341,7 @@ impl ApplyRsIds for Serie {
 mod tests {
     use serde_json::json;
 
-    use super::{Serie, SerieType};
+    use super::{Serie, SerieForUpdate, SerieType};
     use crate::domain::{
         other_ids::OtherIds,
         rs_ids::{ApplyRsIds, RsIds},
@@ -326,4 +437,63 @@ mod tests {
         assert_eq!(serie.myanimelist_manga_id, Some(401));
         assert_eq!(serie.openlibrary_work_id.as_deref(), Some("olw-123"));
     }
+
+    #[test]
+    fn serie_rs_ids_roundtrip() {
+        let serie = Serie {
+            id: "serie-1".to_string(),
+            name: "Serie".to_string(),
+            imdb: Some("tt0000002".to_string()),
+            slug: Some("serie-slug".to_string()),
+            tmdb: Some(21),
+            trakt: Some(22),
+            tvdb: Some(23),
+            openlibrary_work_id: Some("OL1W".to_string()),
+            anilist_manga_id: Some(24),
+            mangadex_manga_uuid: Some("uuid-25".to_string()),
+            myanimelist_manga_id: Some(26),
+            otherids: Some(OtherIds(vec!["anidb:27".to_string()])),
+            ..Default::default()
+        };
+        let ids = RsIds::from(serie.clone());
+        assert_eq!(ids.redseat(), Some("serie-1"));
+
+        let mut restored = Serie::default();
+        restored.apply_rs_ids(&ids);
+        assert_eq!(restored.id, serie.id);
+        assert_eq!(restored.imdb, serie.imdb);
+        assert_eq!(restored.slug, serie.slug);
+        assert_eq!(restored.tmdb, serie.tmdb);
+        assert_eq!(restored.trakt, serie.trakt);
+        assert_eq!(restored.tvdb, serie.tvdb);
+        assert_eq!(restored.openlibrary_work_id, serie.openlibrary_work_id);
+        assert_eq!(restored.anilist_manga_id, serie.anilist_manga_id);
+        assert_eq!(restored.mangadex_manga_uuid, serie.mangadex_manga_uuid);
+        assert_eq!(restored.myanimelist_manga_id, serie.myanimelist_manga_id);
+        assert_eq!(restored.otherids, serie.otherids);
+    }
+
+    #[test]
+    fn serie_diff_alt_lists() {
+        let old = Serie {
+            name: "Serie".to_string(),
+            alt: Some(vec!["A".to_string(), "B".to_string()]),
+            ..Default::default()
+        };
+        let new = Serie {
+            alt: Some(vec!["B".to_string(), "C".to_string()]),
+            tvdb: Some(42),
+            kind: Some(SerieType::Anime),
+            ..old.clone()
+        };
+        let patch = SerieForUpdate::diff(&old, &new);
+        assert_eq!(patch.add_alts, Some(vec!["C".to_string()]));
+        assert_eq!(patch.remove_alts, Some(vec!["A".to_string()]));
+        assert_eq!(patch.name, None);
+
+        let mut updated = old.clone();
+        let changes = patch.apply_to(&mut updated);
+        assert_eq!(updated, new);
+        assert_eq!(changes.fields, vec!["type", "alt", "tvdb"]);
+    }
 }
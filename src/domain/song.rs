@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{external_images::ExternalImage, other_ids::OtherIds, rs_ids::ApplyRsIds};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, ApplyRsIds)]
+#[serde(rename_all = "camelCase")]
+pub struct Song {
+    #[serde(default)]
+    #[rs_id(redseat)]
+    pub id: String,
+    pub name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album_ref: Option<String>,
+    /// Album title, for results without a local album
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artists_ref: Option<Vec<String>>,
+    /// Artist names as credited on the track
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artists: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disc: Option<u32>,
+    /// Duration in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub airdate: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genres: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("isrc")]
+    pub isrc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("mbrec")]
+    pub musicbrainz_recording_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id("slug")]
+    pub slug: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[rs_id(otherids)]
+    pub otherids: Option<OtherIds>,
+
+    /// Album artwork
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub images: Option<Vec<ExternalImage>>,
+
+    #[serde(default)]
+    pub modified: u64,
+    #[serde(default)]
+    pub added: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Song;
+    use crate::domain::{
+        other_ids::OtherIds,
+        rs_ids::{ApplyRsIds, RsIds},
+    };
+
+    #[test]
+    fn song_rs_ids_roundtrip() {
+        let song = Song {
+            id: "song-1".to_string(),
+            name: "Song".to_string(),
+            track: Some(3),
+            disc: Some(1),
+            isrc: Some("USRC17607839".to_string()),
+            musicbrainz_recording_id: Some("b1a9c0e9-d987-4042-ae91-78d6a3267d69".to_string()),
+            otherids: Some(OtherIds(vec!["spotify:4uLU6hMCjMI75M1A2tKUQC".to_string()])),
+            ..Default::default()
+        };
+        let ids = RsIds::from(song.clone());
+        assert_eq!(ids.redseat(), Some("song-1"));
+        assert_eq!(ids.isrc(), Some("USRC17607839"));
+        assert_eq!(
+            ids.musicbrainz_recording_id(),
+            Some("b1a9c0e9-d987-4042-ae91-78d6a3267d69")
+        );
+
+        let mut restored = Song::default();
+        restored.apply_rs_ids(&ids);
+        assert_eq!(restored.id, song.id);
+        assert_eq!(restored.isrc, song.isrc);
+        assert_eq!(
+            restored.musicbrainz_recording_id,
+            song.musicbrainz_recording_id
+        );
+        assert_eq!(restored.otherids, song.otherids);
+    }
+}
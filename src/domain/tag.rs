@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::domain::other_ids::OtherIds;
+use crate::domain::{other_ids::OtherIds, update::{ChangeSummary, Patch}};
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
@@ -25,13 +25,15 @@ pub struct Tag {
     pub otherids: Option<OtherIds>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct TagForUpdate {
     pub name: Option<String>,
-    pub parent: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub parent: Patch<String>,
     #[serde(rename = "type")]
-    pub kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub kind: Patch<String>,
 
     pub alt: Option<Vec<String>>,
     pub add_alts: Option<Vec<String>>,
@@ -42,8 +44,10 @@ pub struct TagForUpdate {
     pub add_otherids: Option<Vec<String>>,
     pub remove_otherids: Option<Vec<String>>,
 
-    pub thumb: Option<String>,
-    pub params: Option<Value>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub thumb: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub params: Patch<Value>,
     pub generated: Option<bool>,
 
     pub migrate_to: Option<String>,
@@ -57,3 +61,77 @@ impl Tag {
         format!("{}{}/", self.path, self.name)
     }
 }
+
+impl TagForUpdate {
+    /// Apply the patch to `tag`. `migrate_to` is not applied here: it moves medias and
+    /// children to another tag (see `TagTree::migrate`).
+    pub fn apply_to(&self, tag: &mut Tag) -> ChangeSummary {
+        let mut changes = ChangeSummary::default();
+        changes.set("name", &mut tag.name, self.name.clone());
+        changes.set_patch("parent", &mut tag.parent, self.parent.clone());
+        changes.set_patch("type", &mut tag.kind, self.kind.clone());
+        changes.set_list(
+            "alt",
+            &mut tag.alt,
+            self.alt.clone(),
+            self.add_alts.clone(),
+            self.remove_alts.clone(),
+        );
+        changes.set_other_ids(
+            "otherids",
+            &mut tag.otherids,
+            self.otherids.clone(),
+            self.add_otherids.clone(),
+            self.remove_otherids.clone(),
+        );
+        changes.set_patch("thumb", &mut tag.thumb, self.thumb.clone());
+        changes.set_patch("params", &mut tag.params, self.params.clone());
+        changes.set("generated", &mut tag.generated, self.generated);
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_apply_to_lists() {
+        let mut tag = Tag {
+            id: "t1".to_string(),
+            name: "Paris".to_string(),
+            parent: None,
+            kind: None,
+            alt: Some(vec!["Paname".to_string()]),
+            thumb: None,
+            params: None,
+            modified: 0,
+            added: 0,
+            generated: false,
+            path: "/".to_string(),
+            otherids: None,
+        };
+        let update = TagForUpdate {
+            name: Some("Paris".to_string()),
+            add_alts: Some(vec!["Lutece".to_string(), "Paname".to_string()]),
+            remove_alts: Some(vec!["Paname".to_string()]),
+            add_otherids: Some(vec!["wikidata:Q90".to_string()]),
+            ..Default::default()
+        };
+        let changes = update.apply_to(&mut tag);
+        assert_eq!(changes.fields, vec!["alt", "otherids"]);
+        assert_eq!(tag.alt, Some(vec!["Lutece".to_string()]));
+        assert_eq!(
+            tag.otherids,
+            Some(OtherIds(vec!["wikidata:Q90".to_string()]))
+        );
+
+        let changes = TagForUpdate {
+            remove_alts: Some(vec!["Lutece".to_string()]),
+            ..Default::default()
+        }
+        .apply_to(&mut tag);
+        assert!(changes.contains("alt"));
+        assert_eq!(tag.alt, None);
+    }
+}
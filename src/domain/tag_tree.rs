@@ -0,0 +1,563 @@
+//! Tag hierarchy built from flat `Tag` rows.
+//!
+//! A root tag has `parent: None` and path `/`; a child has its parent `childs_path()` as path
+//! (`/places/france/` for `paris`). Slash paths (`places/france/paris`) match names ignoring
+//! case.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{
+    tag::{Tag, TagForUpdate},
+    update::Patch,
+};
+
+pub const ROOT_PATH: &str = "/";
+
+#[derive(Debug, Serialize, strum_macros::AsRefStr)]
+pub enum TagTreeError {
+    UnknownTag(String),
+    /// Moving or migrating the tag (first) under its own descendant (second)
+    Cycle(String, String),
+}
+
+// region:    --- Error Boilerplate
+
+impl core::fmt::Display for TagTreeError {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for TagTreeError {}
+
+// endregion: --- Error Boilerplate
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum TagTreeIssue {
+    DuplicateId {
+        tag: String,
+    },
+    MissingParent {
+        tag: String,
+        parent: String,
+    },
+    /// Tags whose parent chain loops
+    Cycle {
+        tags: Vec<String>,
+    },
+    PathMismatch {
+        tag: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TagPathChange {
+    pub id: String,
+    pub path: String,
+}
+
+/// Updates to store after a move or a migration.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TagTreeChanges {
+    pub updates: Vec<(String, TagForUpdate)>,
+    /// New materialized paths of the moved tags and their descendants
+    pub paths: Vec<TagPathChange>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TagPathResolution {
+    /// Tag at the full path, when it exists
+    pub tag: Option<String>,
+    /// Deepest existing tag of the path
+    pub parent: Option<String>,
+    /// Tags to create, each a child of the previous one. Only the first has `parent` set;
+    /// set the next ones to the id of the previously created tag.
+    pub missing: Vec<TagForUpdate>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TagTree {
+    tags: HashMap<String, Tag>,
+    children: HashMap<Option<String>, Vec<String>>,
+    duplicates: Vec<String>,
+}
+
+impl TagTree {
+    pub fn new(tags: Vec<Tag>) -> Self {
+        let mut tree = Self::default();
+        for tag in tags {
+            if tree.tags.contains_key(&tag.id) {
+                tree.duplicates.push(tag.id);
+                continue;
+            }
+            tree.children
+                .entry(tag.parent.clone())
+                .or_default()
+                .push(tag.id.clone());
+            tree.tags.insert(tag.id.clone(), tag);
+        }
+        for ids in tree.children.values_mut() {
+            ids.sort();
+        }
+        tree
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Tag> {
+        self.tags.get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Tags without parent.
+    pub fn roots(&self) -> Vec<&Tag> {
+        self.child_tags(None)
+    }
+
+    pub fn children(&self, id: &str) -> Vec<&Tag> {
+        self.child_tags(Some(id.to_string()))
+    }
+
+    fn child_tags(&self, parent: Option<String>) -> Vec<&Tag> {
+        self.children
+            .get(&parent)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.tags.get(id))
+            .collect()
+    }
+
+    /// Every tag under `id`, depth first.
+    pub fn descendants(&self, id: &str) -> Vec<&Tag> {
+        let mut result = vec![];
+        let mut seen = HashSet::from([id.to_string()]);
+        let mut stack: Vec<&Tag> = self.children(id).into_iter().rev().collect();
+        while let Some(tag) = stack.pop() {
+            if !seen.insert(tag.id.clone()) {
+                continue;
+            }
+            result.push(tag);
+            stack.extend(self.children(&tag.id).into_iter().rev());
+        }
+        result
+    }
+
+    /// Parents of `id`, closest first. Stops at a missing parent or a cycle.
+    pub fn ancestors(&self, id: &str) -> Vec<&Tag> {
+        let mut result = vec![];
+        let mut seen = HashSet::from([id.to_string()]);
+        let mut current = self.tags.get(id).and_then(|t| t.parent.as_ref());
+        while let Some(parent) = current.and_then(|p| self.tags.get(p)) {
+            if !seen.insert(parent.id.clone()) {
+                break;
+            }
+            result.push(parent);
+            current = parent.parent.as_ref();
+        }
+        result
+    }
+
+    /// Path the tag should have according to its parents.
+    /// `None` for unknown tags, missing parents and cycles.
+    pub fn expected_path(&self, id: &str) -> Option<String> {
+        let tag = self.tags.get(id)?;
+        let mut names = vec![];
+        let mut seen = HashSet::from([id]);
+        let mut current = tag.parent.as_deref();
+        while let Some(parent_id) = current {
+            let parent = self.tags.get(parent_id)?;
+            if !seen.insert(parent_id) {
+                return None;
+            }
+            names.push(parent.name.as_str());
+            current = parent.parent.as_deref();
+        }
+        names.reverse();
+        Some(Self::path_of(&names))
+    }
+
+    fn path_of(names: &[&str]) -> String {
+        names
+            .iter()
+            .fold(ROOT_PATH.to_string(), |path, name| format!("{path}{name}/"))
+    }
+
+    /// Duplicate ids, missing parents, cycles and stored paths that do not match the parents.
+    pub fn validate(&self) -> Vec<TagTreeIssue> {
+        let mut issues: Vec<TagTreeIssue> = self
+            .duplicates
+            .iter()
+            .map(|tag| TagTreeIssue::DuplicateId { tag: tag.clone() })
+            .collect();
+        let mut ids: Vec<&String> = self.tags.keys().collect();
+        ids.sort();
+        let mut in_cycle: HashSet<&str> = HashSet::new();
+        for id in ids {
+            let tag = &self.tags[id];
+            if let Some(parent) = &tag.parent {
+                if !self.tags.contains_key(parent) {
+                    issues.push(TagTreeIssue::MissingParent {
+                        tag: id.clone(),
+                        parent: parent.clone(),
+                    });
+                    continue;
+                }
+            }
+            if !in_cycle.contains(id.as_str()) {
+                if let Some(cycle) = self.cycle_from(id) {
+                    in_cycle.extend(cycle.iter().map(|t| self.tags[t].id.as_str()));
+                    issues.push(TagTreeIssue::Cycle { tags: cycle });
+                    continue;
+                }
+            }
+            if let Some(expected) = self.expected_path(id) {
+                if expected != tag.path {
+                    issues.push(TagTreeIssue::PathMismatch {
+                        tag: id.clone(),
+                        expected,
+                        actual: tag.path.clone(),
+                    });
+                }
+            }
+        }
+        issues
+    }
+
+    /// Tags of the loop reached from `id`, sorted, when its parent chain loops back to `id`.
+    fn cycle_from(&self, id: &str) -> Option<Vec<String>> {
+        let mut chain = vec![id.to_string()];
+        let mut current = self.tags.get(id)?.parent.clone();
+        while let Some(parent) = current {
+            if parent == id {
+                chain.sort();
+                return Some(chain);
+            }
+            if chain.contains(&parent) {
+                return None;
+            }
+            current = self.tags.get(&parent)?.parent.clone();
+            chain.push(parent);
+        }
+        None
+    }
+
+    fn find_child(&self, parent: Option<&str>, name: &str) -> Option<&Tag> {
+        self.child_tags(parent.map(str::to_string))
+            .into_iter()
+            .find(|t| t.name.to_lowercase() == name.to_lowercase())
+    }
+
+    /// Tag at slash path `places/france/paris`.
+    pub fn find_by_path(&self, path: &str) -> Option<&Tag> {
+        let resolution = self.resolve_path(path);
+        resolution.tag.and_then(|id| self.tags.get(&id))
+    }
+
+    /// Walk slash path `places/france/paris`, returning the existing tag or the tags to create.
+    pub fn resolve_path(&self, path: &str) -> TagPathResolution {
+        let names: Vec<&str> = path.split('/').filter(|n| !n.trim().is_empty()).collect();
+        let mut parent: Option<&Tag> = None;
+        let mut position = 0;
+        while let Some(name) = names.get(position) {
+            match self.find_child(parent.map(|p| p.id.as_str()), name.trim()) {
+                Some(tag) => parent = Some(tag),
+                None => break,
+            }
+            position += 1;
+        }
+        let parent_id = parent.map(|p| p.id.clone());
+        if position == names.len() {
+            return TagPathResolution {
+                tag: parent_id.clone(),
+                parent: parent_id,
+                missing: vec![],
+            };
+        }
+        let missing = names[position..]
+            .iter()
+            .enumerate()
+            .map(|(index, name)| TagForUpdate {
+                name: Some(name.trim().to_string()),
+                parent: if index == 0 { parent_id.clone().into() } else { Patch::Unchanged },
+                ..Default::default()
+            })
+            .collect();
+        TagPathResolution {
+            tag: None,
+            parent: parent_id,
+            missing,
+        }
+    }
+
+    fn is_same_or_descendant(&self, id: &str, candidate: &str) -> bool {
+        id == candidate || self.ancestors(candidate).iter().any(|t| t.id == id)
+    }
+
+    /// New paths of `ids` and of all their descendants once `ids` are under `parent`.
+    fn repath(&self, ids: &[&str], parent: Option<&str>) -> Vec<TagPathChange> {
+        let base = match parent {
+            Some(parent) => match (self.expected_path(parent), self.tags.get(parent)) {
+                (Some(path), Some(tag)) => format!("{path}{}/", tag.name),
+                _ => self
+                    .tags
+                    .get(parent)
+                    .map(Tag::childs_path)
+                    .unwrap_or_default(),
+            },
+            None => ROOT_PATH.to_string(),
+        };
+        let mut changes = vec![];
+        for id in ids {
+            let Some(tag) = self.tags.get(*id) else {
+                continue;
+            };
+            changes.push(TagPathChange {
+                id: tag.id.clone(),
+                path: base.clone(),
+            });
+            let mut stack: Vec<(&Tag, String)> = vec![(tag, format!("{base}{}/", tag.name))];
+            while let Some((current, childs_path)) = stack.pop() {
+                for child in self.children(&current.id) {
+                    if changes.iter().any(|c| c.id == child.id) {
+                        continue;
+                    }
+                    changes.push(TagPathChange {
+                        id: child.id.clone(),
+                        path: childs_path.clone(),
+                    });
+                    stack.push((child, format!("{childs_path}{}/", child.name)));
+                }
+            }
+        }
+        changes
+    }
+
+    /// Updates to move `id` under `new_parent` (`None` for root).
+    pub fn move_to(
+        &self,
+        id: &str,
+        new_parent: Option<&str>,
+    ) -> Result<TagTreeChanges, TagTreeError> {
+        if !self.tags.contains_key(id) {
+            return Err(TagTreeError::UnknownTag(id.to_string()));
+        }
+        if let Some(parent) = new_parent {
+            if !self.tags.contains_key(parent) {
+                return Err(TagTreeError::UnknownTag(parent.to_string()));
+            }
+            if self.is_same_or_descendant(id, parent) {
+                return Err(TagTreeError::Cycle(id.to_string(), parent.to_string()));
+            }
+        }
+        Ok(TagTreeChanges {
+            updates: vec![(
+                id.to_string(),
+                TagForUpdate {
+                    parent: match new_parent {
+                        Some(parent) => Patch::Set(parent.to_string()),
+                        None => Patch::Clear,
+                    },
+                    ..Default::default()
+                },
+            )],
+            paths: self.repath(&[id], new_parent),
+        })
+    }
+
+    /// Updates to apply `migrate_to`: children of `id` move under `target`, then `id` can be
+    /// removed once its medias are reassigned.
+    pub fn migrate(&self, id: &str, target: &str) -> Result<TagTreeChanges, TagTreeError> {
+        for tag in [id, target] {
+            if !self.tags.contains_key(tag) {
+                return Err(TagTreeError::UnknownTag(tag.to_string()));
+            }
+        }
+        if self.is_same_or_descendant(id, target) {
+            return Err(TagTreeError::Cycle(id.to_string(), target.to_string()));
+        }
+        let children: Vec<&str> = self.children(id).iter().map(|t| t.id.as_str()).collect();
+        Ok(TagTreeChanges {
+            updates: children
+                .iter()
+                .map(|child| {
+                    (
+                        child.to_string(),
+                        TagForUpdate {
+                            parent: Patch::Set(target.to_string()),
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect(),
+            paths: self.repath(&children, Some(target)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(id: &str, name: &str, parent: Option<&str>, path: &str) -> Tag {
+        Tag {
+            id: id.to_string(),
+            name: name.to_string(),
+            parent: parent.map(str::to_string),
+            kind: None,
+            alt: None,
+            thumb: None,
+            params: None,
+            modified: 0,
+            added: 0,
+            generated: false,
+            path: path.to_string(),
+            otherids: None,
+        }
+    }
+
+    fn tree() -> TagTree {
+        TagTree::new(vec![
+            tag("places", "Places", None, "/"),
+            tag("france", "France", Some("places"), "/Places/"),
+            tag("paris", "Paris", Some("france"), "/Places/France/"),
+            tag("people", "People", None, "/"),
+        ])
+    }
+
+    #[test]
+    fn navigation_and_paths() {
+        let tree = tree();
+        assert_eq!(tree.roots().len(), 2);
+        assert_eq!(
+            tree.descendants("places")
+                .iter()
+                .map(|t| t.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["france", "paris"]
+        );
+        assert_eq!(tree.ancestors("paris").len(), 2);
+        assert_eq!(
+            tree.expected_path("paris").as_deref(),
+            Some("/Places/France/")
+        );
+        assert!(tree.validate().is_empty());
+        assert_eq!(
+            tree.find_by_path("places/france/PARIS")
+                .map(|t| t.id.as_str()),
+            Some("paris")
+        );
+    }
+
+    #[test]
+    fn resolve_missing_path() {
+        let resolution = tree().resolve_path("/places/france/lyon/croix-rousse");
+        assert_eq!(resolution.tag, None);
+        assert_eq!(resolution.parent.as_deref(), Some("france"));
+        assert_eq!(resolution.missing.len(), 2);
+        assert_eq!(resolution.missing[0].name.as_deref(), Some("lyon"));
+        assert_eq!(resolution.missing[0].parent, Patch::Set("france".to_string()));
+        assert_eq!(resolution.missing[1].parent, Patch::Unchanged);
+    }
+
+    #[test]
+    fn validate_reports_issues() {
+        let tree = TagTree::new(vec![
+            tag("a", "A", Some("b"), "/B/"),
+            tag("b", "B", Some("a"), "/A/"),
+            tag("c", "C", Some("missing"), "/"),
+            tag("d", "D", None, "/Wrong/"),
+            tag("d", "D", None, "/"),
+        ]);
+        let issues = tree.validate();
+        assert!(issues.contains(&TagTreeIssue::DuplicateId {
+            tag: "d".to_string()
+        }));
+        assert!(issues.contains(&TagTreeIssue::Cycle {
+            tags: vec!["a".to_string(), "b".to_string()]
+        }));
+        assert!(issues.contains(&TagTreeIssue::MissingParent {
+            tag: "c".to_string(),
+            parent: "missing".to_string()
+        }));
+        assert!(issues.contains(&TagTreeIssue::PathMismatch {
+            tag: "d".to_string(),
+            expected: "/".to_string(),
+            actual: "/Wrong/".to_string()
+        }));
+        assert_eq!(issues.len(), 4);
+    }
+
+    #[test]
+    fn move_and_migrate() {
+        let tree = tree();
+        let changes = tree.move_to("france", Some("people")).unwrap();
+        assert_eq!(changes.updates[0].1.parent, Patch::Set("people".to_string()));
+        assert_eq!(
+            changes.paths,
+            vec![
+                TagPathChange {
+                    id: "france".to_string(),
+                    path: "/People/".to_string()
+                },
+                TagPathChange {
+                    id: "paris".to_string(),
+                    path: "/People/France/".to_string()
+                },
+            ]
+        );
+        assert!(matches!(
+            tree.move_to("places", Some("paris")),
+            Err(TagTreeError::Cycle(_, _))
+        ));
+        let changes = tree.move_to("france", None).unwrap();
+        assert_eq!(changes.updates[0].1.parent, Patch::Clear);
+
+        let changes = tree.migrate("places", "people").unwrap();
+        assert_eq!(changes.updates.len(), 1);
+        assert_eq!(changes.updates[0].0, "france");
+        assert_eq!(changes.paths[1].path, "/People/France/");
+        assert!(tree.migrate("places", "france").is_err());
+    }
+
+    #[test]
+    fn move_to_root() {
+        let tree = tree();
+        let changes = tree.move_to("france", None).unwrap();
+        let mut france = tree.get("france").unwrap().clone();
+        changes.updates[0].1.apply_to(&mut france);
+        assert_eq!(france.parent, None);
+        assert_eq!(
+            changes.paths,
+            vec![
+                TagPathChange {
+                    id: "france".to_string(),
+                    path: "/".to_string()
+                },
+                TagPathChange {
+                    id: "paris".to_string(),
+                    path: "/France/".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn find_ignores_unicode_case() {
+        let tree = TagTree::new(vec![tag("ete", "Été", None, "/")]);
+        assert_eq!(tree.find_by_path("été").map(|t| t.id.as_str()), Some("ete"));
+        assert_eq!(tree.find_by_path("ÉTÉ").map(|t| t.id.as_str()), Some("ete"));
+    }
+}
@@ -0,0 +1,510 @@
+//! Shared semantics of `*ForUpdate` patches.
+//!
+//! - `None` leaves a field untouched, `Some(value)` sets it.
+//! - Lists: the full list (`alt`) replaces first, then `add_*` appends missing values, then
+//!   `remove_*` removes values. An emptied list becomes `None`.
+//! - Other ids: `add_otherids` entries are `key:value` and replace the value of an existing key;
+//!   `remove_otherids` entries are either `key:value` (exact entry) or `key` (any value).
+//!
+//! - Clearable fields use `Patch`: a missing key leaves the value, `null` clears it. Payloads
+//!   serialized before `Patch`, with every unset field as `null`, are read with
+//!   [`LegacyUpdate`].
+//!
+//! `apply_to` returns the serialized names of the fields whose value actually changed.
+
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::domain::other_ids::OtherIds;
+
+/// Three-state patch field: leave the value, clear it or set it.
+///
+/// Deserializes a `null` as `Clear` and a value as `Set`; a missing key falls back to
+/// `Unchanged` with `#[serde(default)]`. Fields must skip `Unchanged` when serializing:
+/// `#[serde(default, skip_serializing_if = "Patch::is_unchanged")]`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Patch<T> {
+    #[default]
+    Unchanged,
+    Clear,
+    Set(T),
+}
+
+impl<T> Patch<T> {
+    pub fn is_unchanged(&self) -> bool {
+        matches!(self, Patch::Unchanged)
+    }
+
+    /// Value to store: `None` when unchanged, `Some(None)` when cleared.
+    pub fn into_update(self) -> Option<Option<T>> {
+        match self {
+            Patch::Unchanged => None,
+            Patch::Clear => Some(None),
+            Patch::Set(value) => Some(Some(value)),
+        }
+    }
+
+    pub fn as_ref(&self) -> Patch<&T> {
+        match self {
+            Patch::Unchanged => Patch::Unchanged,
+            Patch::Clear => Patch::Clear,
+            Patch::Set(value) => Patch::Set(value),
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Patch<U> {
+        match self {
+            Patch::Unchanged => Patch::Unchanged,
+            Patch::Clear => Patch::Clear,
+            Patch::Set(value) => Patch::Set(f(value)),
+        }
+    }
+
+    /// Take `other` unless it is `Unchanged`.
+    pub fn merge_from(&mut self, other: Patch<T>) {
+        if !other.is_unchanged() {
+            *self = other;
+        }
+    }
+
+    /// Patch turning `old` into `new`.
+    pub fn diff(old: &Option<T>, new: &Option<T>) -> Self
+    where
+        T: PartialEq + Clone,
+    {
+        match (old, new) {
+            (old, new) if old == new => Patch::Unchanged,
+            (_, None) => Patch::Clear,
+            (_, Some(value)) => Patch::Set(value.clone()),
+        }
+    }
+}
+
+/// `None` stays unchanged, as `Option` patch fields did.
+impl<T> From<Option<T>> for Patch<T> {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => Patch::Set(value),
+            None => Patch::Unchanged,
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for Patch<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Patch::Set(value) => serializer.serialize_some(value),
+            Patch::Unchanged | Patch::Clear => serializer.serialize_none(),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Patch<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match Option::<T>::deserialize(deserializer)? {
+            Some(value) => Patch::Set(value),
+            None => Patch::Clear,
+        })
+    }
+}
+
+/// A `*ForUpdate` sent by clients predating `Patch`, which serialize every unset field as
+/// `null`: top level `null`s are dropped before deserializing, so they leave fields unchanged
+/// and nothing is cleared.
+///
+/// Use it in place of the update type (`LegacyUpdate<MediaForUpdate>`) or on a field with
+/// `#[serde(deserialize_with = "LegacyUpdate::deserialize_inner")]`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LegacyUpdate<T>(pub T);
+
+impl<T: DeserializeOwned> LegacyUpdate<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+
+    pub fn deserialize_inner<'de, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        Self::deserialize(deserializer).map(Self::into_inner)
+    }
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for LegacyUpdate<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut fields = serde_json::Map::deserialize(deserializer)?;
+        fields.retain(|_, value| !value.is_null());
+        serde_json::from_value(serde_json::Value::Object(fields))
+            .map(LegacyUpdate)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeSummary {
+    pub fields: Vec<String>,
+}
+
+impl ChangeSummary {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn contains(&self, field: &str) -> bool {
+        self.fields.iter().any(|f| f == field)
+    }
+
+    pub(crate) fn record(&mut self, field: &str) {
+        if !self.contains(field) {
+            self.fields.push(field.to_string());
+        }
+    }
+
+    /// Set `dst` to `value` when given, recording `field` if it differs.
+    pub(crate) fn set<T: PartialEq>(&mut self, field: &str, dst: &mut T, value: Option<T>) {
+        if let Some(value) = value {
+            if *dst != value {
+                *dst = value;
+                self.record(field);
+            }
+        }
+    }
+
+    pub(crate) fn set_option<T: PartialEq>(
+        &mut self,
+        field: &str,
+        dst: &mut Option<T>,
+        value: Option<T>,
+    ) {
+        self.set(field, dst, value.map(Some));
+    }
+
+    pub(crate) fn set_patch<T: PartialEq>(
+        &mut self,
+        field: &str,
+        dst: &mut Option<T>,
+        patch: Patch<T>,
+    ) {
+        self.set(field, dst, patch.into_update());
+    }
+
+    pub(crate) fn set_list<T: PartialEq + Clone>(
+        &mut self,
+        field: &str,
+        dst: &mut Option<Vec<T>>,
+        replace: Option<Vec<T>>,
+        add: Option<Vec<T>>,
+        remove: Option<Vec<T>>,
+    ) {
+        if replace.is_none() && add.is_none() && remove.is_none() {
+            return;
+        }
+        let mut list = replace.or_else(|| dst.clone()).unwrap_or_default();
+        for value in add.into_iter().flatten() {
+            if !list.contains(&value) {
+                list.push(value);
+            }
+        }
+        if let Some(remove) = remove {
+            list.retain(|value| !remove.contains(value));
+        }
+        let list = if list.is_empty() { None } else { Some(list) };
+        self.set(field, dst, Some(list));
+    }
+
+    pub(crate) fn set_other_ids(
+        &mut self,
+        field: &str,
+        dst: &mut Option<OtherIds>,
+        replace: Option<OtherIds>,
+        add: Option<Vec<String>>,
+        remove: Option<Vec<String>>,
+    ) {
+        if replace.is_none() && add.is_none() && remove.is_none() {
+            return;
+        }
+        let mut ids = replace.or_else(|| dst.clone()).unwrap_or_default();
+        for entry in add.into_iter().flatten() {
+            if let Some((key, value)) = entry.split_once(':') {
+                ids.add(key, value);
+            }
+        }
+        if let Some(remove) = remove {
+            ids.0.retain(|entry| {
+                let Some((k, v)) = entry.split_once(':') else {
+                    return true;
+                };
+                !remove.iter().any(|r| match r.split_once(':') {
+                    Some((key, value)) => k.eq_ignore_ascii_case(key) && v == value,
+                    None => k.eq_ignore_ascii_case(r),
+                })
+            });
+        }
+        let ids = if ids.as_slice().is_empty() {
+            None
+        } else {
+            Some(ids)
+        };
+        self.set(field, dst, Some(ids));
+    }
+}
+
+/// Overwrite `dst` only if `src` is `Some(..)`.
+pub(crate) fn overwrite_if_some<T>(dst: &mut Option<T>, src: Option<T>) {
+    if src.is_some() {
+        *dst = src;
+    }
+}
+
+/// Append vectors when both sides are `Some(vec)`; if `dst` is `None`, it becomes `src`.
+pub(crate) fn append_vec<T>(dst: &mut Option<Vec<T>>, src: Option<Vec<T>>) {
+    match (dst.as_mut(), src) {
+        (Some(d), Some(mut s)) => d.append(&mut s),
+        (None, Some(s)) => *dst = Some(s),
+        _ => {}
+    }
+}
+
+/// `Some(new)` when it differs from `old`.
+pub(crate) fn diff_value<T: PartialEq + Clone>(old: &T, new: &T) -> Option<T> {
+    if old != new {
+        Some(new.clone())
+    } else {
+        None
+    }
+}
+
+/// `(add, remove)` lists turning `old` into `new`.
+pub(crate) fn diff_list<T: PartialEq + Clone>(
+    old: &Option<Vec<T>>,
+    new: &Option<Vec<T>>,
+) -> (Option<Vec<T>>, Option<Vec<T>>) {
+    let old = old.as_deref().unwrap_or_default();
+    let new = new.as_deref().unwrap_or_default();
+    let add: Vec<T> = new.iter().filter(|v| !old.contains(v)).cloned().collect();
+    let remove: Vec<T> = old.iter().filter(|v| !new.contains(v)).cloned().collect();
+    (
+        (!add.is_empty()).then_some(add),
+        (!remove.is_empty()).then_some(remove),
+    )
+}
+
+/// `(add_otherids, remove_otherids)` turning `old` into `new`. Changed values are only added:
+/// adding replaces the value of an existing key.
+pub(crate) fn diff_other_ids(
+    old: &Option<OtherIds>,
+    new: &Option<OtherIds>,
+) -> (Option<Vec<String>>, Option<Vec<String>>) {
+    let old = old.as_ref().map(|ids| ids.as_slice()).unwrap_or_default();
+    let new_ids = new.clone().unwrap_or_default();
+    let add: Vec<String> = new_ids
+        .as_slice()
+        .iter()
+        .filter(|entry| !old.contains(entry))
+        .cloned()
+        .collect();
+    let remove: Vec<String> = old
+        .iter()
+        .filter(|entry| {
+            entry
+                .split_once(':')
+                .is_some_and(|(key, _)| !new_ids.has_key(key))
+        })
+        .cloned()
+        .collect();
+    (
+        (!add.is_empty()).then_some(add),
+        (!remove.is_empty()).then_some(remove),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_records_only_real_changes() {
+        let mut changes = ChangeSummary::default();
+        let mut name = "a".to_string();
+        changes.set("name", &mut name, Some("a".to_string()));
+        changes.set("name", &mut name, None);
+        assert!(changes.is_empty());
+        changes.set("name", &mut name, Some("b".to_string()));
+        assert_eq!(changes.fields, vec!["name"]);
+    }
+
+    #[test]
+    fn other_ids_add_and_remove() {
+        let mut changes = ChangeSummary::default();
+        let mut ids = Some(OtherIds(vec![
+            "tvmaze:1".to_string(),
+            "anidb:2".to_string(),
+        ]));
+        changes.set_other_ids(
+            "otherids",
+            &mut ids,
+            None,
+            Some(vec!["tvmaze:3".to_string(), "kitsu:4".to_string()]),
+            Some(vec!["anidb".to_string(), "kitsu:5".to_string()]),
+        );
+        assert_eq!(
+            ids,
+            Some(OtherIds(vec![
+                "tvmaze:3".to_string(),
+                "kitsu:4".to_string()
+            ]))
+        );
+        assert!(changes.contains("otherids"));
+    }
+
+    #[test]
+    fn untouched_lists_stay_as_is() {
+        let mut changes = ChangeSummary::default();
+        let mut alt: Option<Vec<String>> = Some(vec![]);
+        changes.set_list("alt", &mut alt, None, None, None);
+        let mut ids = Some(OtherIds(vec![]));
+        changes.set_other_ids("otherids", &mut ids, None, None, None);
+        assert!(changes.is_empty());
+        assert_eq!((alt, ids), (Some(vec![]), Some(OtherIds(vec![]))));
+    }
+
+    #[test]
+    fn patch_distinguishes_missing_and_null() {
+        use crate::domain::{
+            media::{Media, MediaForUpdate},
+            tag::TagForUpdate,
+        };
+        let update: TagForUpdate =
+            serde_json::from_value(serde_json::json!({ "name": "Paris", "parent": null }))
+                .unwrap();
+        assert_eq!(update.parent, Patch::Clear);
+        assert_eq!(update.thumb, Patch::Unchanged);
+        let update: TagForUpdate =
+            serde_json::from_value(serde_json::json!({ "parent": "france" })).unwrap();
+        assert_eq!(update.parent, Patch::Set("france".to_string()));
+        let value = serde_json::to_value(&update).unwrap();
+        assert_eq!(value["parent"], "france");
+        assert!(value.get("thumb").is_none());
+        let clear = TagForUpdate {
+            parent: Patch::Clear,
+            ..Default::default()
+        };
+        let value = serde_json::to_value(&clear).unwrap();
+        assert_eq!(value.get("parent"), Some(&serde_json::Value::Null));
+
+        let mut media = Media {
+            description: Some("Old".to_string()),
+            lat: Some(48.85),
+            long: Some(2.35),
+            ..Default::default()
+        };
+        let update: MediaForUpdate =
+            serde_json::from_value(serde_json::json!({ "description": null, "gps": null }))
+                .unwrap();
+        let changes = update.apply_to(&mut media);
+        assert_eq!(changes.fields, vec!["description", "lat", "long"]);
+        assert_eq!((media.description, media.lat, media.long), (None, None, None));
+        assert_eq!(Patch::diff(&Some(1), &None), Patch::Clear);
+        assert_eq!(Patch::<u32>::diff(&None, &None), Patch::Unchanged);
+    }
+
+    /// Payload of a rename as serialized before `Patch`: every unset field is `null`.
+    fn legacy_rename(fields: &str) -> serde_json::Value {
+        let mut payload: serde_json::Map<String, serde_json::Value> = fields
+            .split_whitespace()
+            .map(|field| (field.to_string(), serde_json::Value::Null))
+            .collect();
+        payload.insert("name".to_string(), "Renamed".into());
+        payload.into()
+    }
+
+    #[test]
+    fn legacy_null_payloads_only_rename() {
+        use crate::domain::{
+            book::{Book, BookForUpdate},
+            media::{Media, MediaForUpdate},
+            tag::{Tag, TagForUpdate},
+        };
+        let mut payload = legacy_rename(
+            "description mimetype kind size md5 modified created width height orientation \
+             colorSpace icc mp vcodecs acodecs fps bitrate focal iso model sspeed fNumber pages \
+             duration progress addTags removeTags tagsLookup addSeries removeSeries seriesLookup \
+             season episode addPeople removePeople peopleLookup long lat gps origin originUrl \
+             movie book lang rating thumbsize iv uploader uploadkey uploadId originalHash \
+             originalId",
+        );
+        payload["ignoreOriginDuplicate"] = false.into();
+        // read as a current payload, the nulls clear
+        let current: MediaForUpdate = serde_json::from_value(payload.clone()).unwrap();
+        assert_eq!(current.description, Patch::Clear);
+        let LegacyUpdate(update) =
+            serde_json::from_value::<LegacyUpdate<MediaForUpdate>>(payload).unwrap();
+        let mut media = Media {
+            name: "Old".to_string(),
+            description: Some("Description".to_string()),
+            lat: Some(48.85),
+            long: Some(2.35),
+            lang: Some("fr".to_string()),
+            rating: Some(4.0),
+            ..Default::default()
+        };
+        let expected = Media {
+            name: "Renamed".to_string(),
+            ..media.clone()
+        };
+        assert_eq!(update.apply_to(&mut media).fields, vec!["name"]);
+        assert_eq!(media, expected);
+
+        let payload = legacy_rename(
+            "kind serieRef volume chapter year airdate overview pages params lang original isbn13 \
+             openlibraryEditionId openlibraryWorkId googleBooksVolumeId asin otherids addTags \
+             removeTags tagsLookup addPeople removePeople peopleLookup",
+        );
+        let LegacyUpdate(update) =
+            serde_json::from_value::<LegacyUpdate<BookForUpdate>>(payload).unwrap();
+        let mut book = Book {
+            name: "Old".to_string(),
+            year: Some(1965),
+            overview: Some("Overview".to_string()),
+            isbn13: Some("9780441013593".to_string()),
+            ..Default::default()
+        };
+        let expected = Book {
+            name: "Renamed".to_string(),
+            ..book.clone()
+        };
+        assert_eq!(update.apply_to(&mut book).fields, vec!["name"]);
+        assert_eq!(book, expected);
+
+        #[derive(Deserialize)]
+        struct TagRequest {
+            #[serde(deserialize_with = "LegacyUpdate::deserialize_inner")]
+            update: TagForUpdate,
+        }
+        let request: TagRequest = serde_json::from_value(serde_json::json!({
+            "update": legacy_rename(
+                "parent kind alt addAlts removeAlts otherids addOtherids removeOtherids thumb \
+                 params generated migrateTo",
+            )
+        }))
+        .unwrap();
+        let update = request.update;
+        let mut tag = Tag {
+            id: "paris".to_string(),
+            name: "Old".to_string(),
+            parent: Some("france".to_string()),
+            kind: None,
+            alt: None,
+            thumb: Some("thumb".to_string()),
+            params: None,
+            modified: 0,
+            added: 0,
+            generated: false,
+            path: "/France/".to_string(),
+            otherids: None,
+        };
+        let expected = Tag {
+            name: "Renamed".to_string(),
+            ..tag.clone()
+        };
+        assert_eq!(update.apply_to(&mut tag).fields, vec!["name"]);
+        assert_eq!(tag, expected);
+    }
+}
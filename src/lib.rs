@@ -1,3 +1,6 @@
+// Lets derive macros refer to `::rs_plugin_common_interfaces` from inside this crate too.
+extern crate self as rs_plugin_common_interfaces;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use strum_macros::EnumString;
@@ -8,9 +11,9 @@ pub use domain::{
     other_ids::OtherIds,
 };
 pub use lookup::{
-    RsLookupBook, RsLookupEpisode, RsLookupMatchType, RsLookupMedia, RsLookupMovie,
-    RsLookupPerson, RsLookupQuery, RsLookupSerie, RsLookupSerieSeason, RsLookupSong,
-    RsLookupSourceResult, RsLookupWrapper,
+    RsLookupBook, RsLookupCalendar, RsLookupEpisode, RsLookupEpisodeList, RsLookupFilters,
+    RsLookupMatchType, RsLookupMedia, RsLookupMovie, RsLookupPerson, RsLookupQuery, RsLookupSerie,
+    RsLookupSerieSeason, RsLookupSong, RsLookupSourceResult, RsLookupWrapper,
 };
 pub use request::{
     RsCookie, RsCookies, RsRequest, RsRequestFiles, RsRequestPluginRequest, RsRequestStatus,
@@ -22,7 +25,10 @@ pub use video::{RsAudio, RsResolution, RsVideoCodec, RsVideoFormat};
 #[cfg(feature = "rusqlite")]
 pub mod rusqlite;
 
+#[cfg(feature = "crypt")]
+pub mod crypt;
 pub mod lookup;
+pub mod metadata;
 pub mod provider;
 pub mod request;
 pub mod url;
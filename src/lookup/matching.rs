@@ -0,0 +1,383 @@
+//! Ranking of metadata lookup results against the query that produced them.
+//!
+//! Each result gets a score between `0.0` and `1.0` from:
+//! - id overlap with the query (`RsIds::has_common_id`): an exact id match always scores `1.0`
+//! - title similarity on normalized titles, including alternate and original titles
+//! - year proximity when both the query filters and the result carry a year
+
+use std::cmp::Ordering;
+
+use crate::domain::rs_ids::RsIds;
+
+use super::{
+    RsLookupFilters, RsLookupMatchType, RsLookupMetadataResult, RsLookupMetadataResults,
+    RsLookupQuery,
+};
+
+/// Weight of the title in the final score when a year can be compared.
+const TITLE_WEIGHT: f32 = 0.8;
+/// Year distance at which the year score drops to zero.
+const MAX_YEAR_DISTANCE: f32 = 5.0;
+
+/// Lowercase, strip accents and punctuation, and collapse whitespace.
+pub fn normalize_title(title: &str) -> String {
+    let mut normalized = String::with_capacity(title.len());
+    for c in title.chars().flat_map(char::to_lowercase) {
+        match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => normalized.push('a'),
+            'ç' => normalized.push('c'),
+            'è' | 'é' | 'ê' | 'ë' => normalized.push('e'),
+            'ì' | 'í' | 'î' | 'ï' => normalized.push('i'),
+            'ñ' => normalized.push('n'),
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => normalized.push('o'),
+            'ù' | 'ú' | 'û' | 'ü' => normalized.push('u'),
+            'ý' | 'ÿ' => normalized.push('y'),
+            'æ' => normalized.push_str("ae"),
+            'œ' => normalized.push_str("oe"),
+            'ß' => normalized.push_str("ss"),
+            '&' => normalized.push_str(" and "),
+            // Apostrophes join words: "Ocean's" -> "oceans"
+            '\'' | '’' => {}
+            c if c.is_alphanumeric() => normalized.push(c),
+            _ => normalized.push(' '),
+        }
+    }
+    normalized.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Similarity of two titles from `0.0` to `1.0`.
+///
+/// Best of the edit distance ratio (typos, small variations) and the shared word ratio
+/// (reordered words) on normalized titles.
+pub fn title_similarity(a: &str, b: &str) -> f32 {
+    let (a, b) = (normalize_title(a), normalize_title(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+    let (chars_a, chars_b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let max_len = chars_a.len().max(chars_b.len());
+    let edit = 1.0 - levenshtein(&chars_a, &chars_b) as f32 / max_len as f32;
+
+    let mut words_a: Vec<&str> = a.split(' ').collect();
+    let mut words_b: Vec<&str> = b.split(' ').collect();
+    words_a.sort_unstable();
+    words_a.dedup();
+    words_b.sort_unstable();
+    words_b.dedup();
+    let common = words_a.iter().filter(|w| words_b.contains(w)).count();
+    let union = words_a.len() + words_b.len() - common;
+    let words = common as f32 / union as f32;
+
+    edit.max(words)
+}
+
+/// Year score from `0.0` to `1.0`, or `None` when the year cannot be compared.
+fn year_score(filters: &RsLookupFilters, year: Option<u16>) -> Option<f32> {
+    let year = year?;
+    if filters.year.is_none() && filters.year_min.is_none() && filters.year_max.is_none() {
+        return None;
+    }
+    if filters.accepts_year(year) {
+        return Some(1.0);
+    }
+    let distance = [filters.year, filters.year_min, filters.year_max]
+        .into_iter()
+        .flatten()
+        .map(|y| (y as i32 - year as i32).unsigned_abs())
+        .min()
+        .unwrap_or(0);
+    Some((1.0 - distance as f32 / MAX_YEAR_DISTANCE).max(0.0))
+}
+
+fn query_title(query: &RsLookupQuery) -> Option<&str> {
+    match query {
+        RsLookupQuery::Book(q) => q.name.as_deref(),
+        RsLookupQuery::Media(q) => q.search.as_deref(),
+        RsLookupQuery::Episode(q) => q.name.as_deref(),
+        RsLookupQuery::EpisodeList(_) | RsLookupQuery::Calendar(_) => None,
+        RsLookupQuery::Movie(q) => q.name.as_deref(),
+        RsLookupQuery::Person(q) => q.name.as_deref(),
+        RsLookupQuery::Serie(q) => q.name.as_deref(),
+        RsLookupQuery::SerieSeason(q) => q.name.as_deref(),
+        RsLookupQuery::Song(q) => q.title.as_deref(),
+    }
+}
+
+fn query_ids(query: &RsLookupQuery) -> Option<&RsIds> {
+    match query {
+        RsLookupQuery::Book(q) => q.ids.as_ref(),
+        RsLookupQuery::Media(q) => q.ids.as_ref(),
+        RsLookupQuery::Episode(q) => q.ids.as_ref(),
+        RsLookupQuery::EpisodeList(_) | RsLookupQuery::Calendar(_) => None,
+        RsLookupQuery::Movie(q) => q.ids.as_ref(),
+        RsLookupQuery::Person(q) => q.ids.as_ref(),
+        RsLookupQuery::Serie(q) => q.ids.as_ref(),
+        RsLookupQuery::SerieSeason(q) => q.ids.as_ref(),
+        RsLookupQuery::Song(q) => q.ids.as_ref(),
+    }
+}
+
+/// Every title a result can be known by: main name, original title and alternate names.
+fn result_titles(result: &RsLookupMetadataResult) -> Vec<&str> {
+    let mut titles: Vec<&str> = vec![];
+    match result {
+        RsLookupMetadataResult::Book(b) => {
+            titles.push(&b.name);
+            titles.extend(b.original.as_deref());
+        }
+        RsLookupMetadataResult::Media(m) => titles.push(&m.name),
+        RsLookupMetadataResult::Episode(e) => {
+            titles.extend(e.name.as_deref());
+            titles.extend(e.alt.iter().flatten().map(String::as_str));
+        }
+        RsLookupMetadataResult::Movie(m) => {
+            titles.push(&m.name);
+            titles.extend(m.original.as_deref());
+        }
+        RsLookupMetadataResult::Person(p) => {
+            titles.push(&p.name);
+            titles.extend(p.alt.iter().flatten().map(String::as_str));
+        }
+        RsLookupMetadataResult::Serie(s) => {
+            titles.push(&s.name);
+            titles.extend(s.alt.iter().flatten().map(String::as_str));
+        }
+        RsLookupMetadataResult::Season(s) => titles.extend(s.name.as_deref()),
+        RsLookupMetadataResult::Song(s) => titles.push(&s.name),
+        RsLookupMetadataResult::None => {}
+    }
+    titles
+}
+
+fn result_year(result: &RsLookupMetadataResult) -> Option<u16> {
+    match result {
+        RsLookupMetadataResult::Book(b) => b.year,
+        RsLookupMetadataResult::Movie(m) => m.year,
+        RsLookupMetadataResult::Serie(s) => s.year,
+        RsLookupMetadataResult::Song(s) => s.year,
+        _ => None,
+    }
+}
+
+/// Score `result` against `query` and classify the match.
+///
+/// Returns `None` for empty results or when nothing at all matches.
+pub fn match_result(
+    query: &RsLookupQuery,
+    result: &RsLookupMetadataResult,
+) -> Option<(RsLookupMatchType, f32)> {
+    if let (Some(query_ids), Some(result_ids)) = (query_ids(query), result.extract_ids()) {
+        if query_ids.has_common_id(&result_ids) {
+            return Some((RsLookupMatchType::ExactId, 1.0));
+        }
+    }
+
+    let title = query_title(query)?;
+    let title_score = result_titles(result)
+        .into_iter()
+        .map(|candidate| title_similarity(title, candidate))
+        .fold(0.0f32, f32::max);
+    if title_score <= 0.0 {
+        return None;
+    }
+
+    let year_match = year_score(query.filters(), result_year(result));
+    let score = match year_match {
+        Some(year_score) => title_score * TITLE_WEIGHT + year_score * (1.0 - TITLE_WEIGHT),
+        None => title_score,
+    };
+    // A known but different year (more than one year apart) is not an exact match
+    if title_score >= 1.0 && year_match.is_none_or(|y| y >= 0.8) {
+        Some((RsLookupMatchType::ExactText, score))
+    } else {
+        Some((RsLookupMatchType::Fuzzy { score }, score))
+    }
+}
+
+fn match_tier(match_type: Option<&RsLookupMatchType>) -> u8 {
+    match match_type {
+        Some(RsLookupMatchType::ExactId) => 2,
+        Some(RsLookupMatchType::ExactText) => 1,
+        _ => 0,
+    }
+}
+
+impl RsLookupMetadataResults {
+    /// Rank results against `query`, best first.
+    ///
+    /// Results without a `match_type` get the computed one; match types set by the plugin
+    /// are kept. Ordering is by match type (exact id, exact text, fuzzy) then by score.
+    pub fn sort_by_match(&mut self, query: &RsLookupQuery) {
+        let mut scored: Vec<(u8, f32, _)> = self
+            .results
+            .drain(..)
+            .map(|mut wrapper| {
+                let computed = match_result(query, &wrapper.metadata);
+                let score = match (&wrapper.match_type, &computed) {
+                    (Some(RsLookupMatchType::ExactId), _) => 1.0,
+                    (Some(RsLookupMatchType::Fuzzy { score }), _) => *score,
+                    (_, Some((_, score))) => *score,
+                    _ => 0.0,
+                };
+                if wrapper.match_type.is_none() {
+                    wrapper.match_type = computed.map(|(match_type, _)| match_type);
+                }
+                (match_tier(wrapper.match_type.as_ref()), score, wrapper)
+            })
+            .collect();
+        scored.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then(b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal))
+        });
+        self.results = scored.into_iter().map(|(_, _, wrapper)| wrapper).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{movie::Movie, serie::Serie},
+        lookup::{RsLookupMetadataResultWrapper, RsLookupMovie, RsLookupSerie},
+    };
+
+    fn movie(name: &str, year: Option<u16>) -> RsLookupMetadataResultWrapper {
+        RsLookupMetadataResultWrapper {
+            metadata: RsLookupMetadataResult::Movie(Movie {
+                id: format!("{}-{:?}", name, year),
+                name: name.to_string(),
+                year,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn normalize_title_strips_accents_and_punctuation() {
+        assert_eq!(
+            normalize_title("  Amélie: Le Fabuleux   Destin!  "),
+            "amelie le fabuleux destin"
+        );
+        assert_eq!(normalize_title("Ocean's Eleven"), "oceans eleven");
+        assert_eq!(normalize_title("Fast & Furious"), "fast and furious");
+    }
+
+    #[test]
+    fn title_similarity_scores() {
+        assert_eq!(title_similarity("The Matrix", "the matrix"), 1.0);
+        assert!(title_similarity("The Matrix", "The Matrx") > 0.8);
+        assert_eq!(title_similarity("Wars Star", "Star Wars"), 1.0);
+        assert!(title_similarity("The Matrix", "Frozen") < 0.3);
+        assert_eq!(title_similarity("", "Frozen"), 0.0);
+    }
+
+    #[test]
+    fn match_result_uses_alternate_titles_and_year() {
+        let query = RsLookupQuery::Serie(RsLookupSerie {
+            name: Some("Shingeki no Kyojin".to_string()),
+            filters: RsLookupFilters {
+                year: Some(2013),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let result = RsLookupMetadataResult::Serie(Serie {
+            name: "Attack on Titan".to_string(),
+            alt: Some(vec!["Shingeki no Kyojin".to_string()]),
+            year: Some(2013),
+            ..Default::default()
+        });
+        assert_eq!(
+            match_result(&query, &result),
+            Some((RsLookupMatchType::ExactText, 1.0))
+        );
+
+        let remake = RsLookupMetadataResult::Serie(Serie {
+            name: "Shingeki no Kyojin".to_string(),
+            year: Some(2020),
+            ..Default::default()
+        });
+        let (match_type, score) = match_result(&query, &remake).unwrap();
+        assert_eq!(match_type, RsLookupMatchType::Fuzzy { score });
+        assert!((score - TITLE_WEIGHT).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn match_result_exact_id() {
+        let mut ids = RsIds::default();
+        ids.set("tmdb", 603u64);
+        let query = RsLookupQuery::Movie(RsLookupMovie {
+            name: Some("Something else".to_string()),
+            ids: Some(ids),
+            ..Default::default()
+        });
+        let result = RsLookupMetadataResult::Movie(Movie {
+            name: "The Matrix".to_string(),
+            tmdb: Some(603),
+            ..Default::default()
+        });
+        assert_eq!(
+            match_result(&query, &result),
+            Some((RsLookupMatchType::ExactId, 1.0))
+        );
+        assert_eq!(match_result(&query, &RsLookupMetadataResult::None), None);
+    }
+
+    #[test]
+    fn sort_by_match_ranks_results() {
+        let query = RsLookupQuery::Movie(RsLookupMovie {
+            name: Some("The Matrix".to_string()),
+            filters: RsLookupFilters {
+                year: Some(1999),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let mut results = RsLookupMetadataResults {
+            results: vec![
+                movie("The Matrix Reloaded", Some(2003)),
+                movie("Frozen", Some(2013)),
+                movie("The Matrix", Some(2021)),
+                movie("The Matrix", Some(1999)),
+            ],
+            next_page_key: None,
+        };
+        results.sort_by_match(&query);
+
+        let years: Vec<Option<u16>> = results
+            .results
+            .iter()
+            .map(|r| match &r.metadata {
+                RsLookupMetadataResult::Movie(m) => m.year,
+                _ => None,
+            })
+            .collect();
+        assert_eq!(years, vec![Some(1999), Some(2021), Some(2003), Some(2013)]);
+        assert_eq!(
+            results.results[0].match_type,
+            Some(RsLookupMatchType::ExactText)
+        );
+        assert!(matches!(
+            results.results[1].match_type,
+            Some(RsLookupMatchType::Fuzzy { .. })
+        ));
+    }
+}
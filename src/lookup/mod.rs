@@ -6,7 +6,9 @@ use crate::domain::episode::Episode;
 use crate::domain::media::Media;
 use crate::domain::movie::Movie;
 use crate::domain::person::Person;
-use crate::domain::serie::Serie;
+use crate::domain::season::Season;
+use crate::domain::serie::{Serie, SerieType};
+use crate::domain::song::Song;
 use crate::{CustomParamTypes, PluginCredential};
 use crate::request::RsGroupDownload;
 use crate::domain::rs_ids::{ApplyRsIds, RsIds};
@@ -14,6 +16,8 @@ use crate::request::RsRequest;
 use serde::{Deserialize, Serialize};
 use strum_macros::EnumString;
 
+pub mod matching;
+
 #[derive(
     Debug, Serialize, Deserialize, Clone, PartialEq, strum_macros::Display, EnumString, Default,
 )]
@@ -27,12 +31,60 @@ pub enum RsLookupSourceResult {
     NotApplicable,
 }
 
+/// Optional search constraints shared by all lookup queries.
+/// Plugins apply the filters their source supports and ignore the others.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RsLookupFilters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<u16>,
+    /// Inclusive lower bound of a year range
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year_min: Option<u16>,
+    /// Inclusive upper bound of a year range
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year_max: Option<u16>,
+    /// Language of the results (ISO 639-1, e.g. `fr`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    /// Country of origin (ISO 3166-1 alpha-2, e.g. `FR`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    /// Only return series of these types
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub serie_types: Option<Vec<SerieType>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<u32>,
+}
+
+impl RsLookupFilters {
+    pub fn is_empty(&self) -> bool {
+        self == &RsLookupFilters::default()
+    }
+
+    /// Check a result year against `year` and the `year_min`/`year_max` range.
+    pub fn accepts_year(&self, year: u16) -> bool {
+        self.year.is_none_or(|y| y == year)
+            && self.year_min.is_none_or(|min| year >= min)
+            && self.year_max.is_none_or(|max| year <= max)
+    }
+
+    pub fn accepts_serie_type(&self, kind: &SerieType) -> bool {
+        self.serie_types
+            .as_ref()
+            .is_none_or(|types| types.contains(kind))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct RsLookupPerson {
     pub name: Option<String>,
     pub ids: Option<RsIds>,
 
+    #[serde(flatten)]
+    pub filters: RsLookupFilters,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub page_key: Option<String>
 
@@ -45,6 +97,9 @@ pub struct RsLookupSerie {
     pub name: Option<String>,
     pub ids: Option<RsIds>,
 
+    #[serde(flatten)]
+    pub filters: RsLookupFilters,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub page_key: Option<String>
 }
@@ -55,6 +110,9 @@ pub struct RsLookupSerieSeason {
     pub name: Option<String>,
     pub ids: Option<RsIds>,
 
+    #[serde(flatten)]
+    pub filters: RsLookupFilters,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub page_key: Option<String>
 }
@@ -65,6 +123,9 @@ pub struct RsLookupEpisode {
     pub name: Option<String>,
     pub ids: Option<RsIds>,
 
+    #[serde(flatten)]
+    pub filters: RsLookupFilters,
+
     pub season: u32,
     pub number: Option<u32>,
 
@@ -72,12 +133,65 @@ pub struct RsLookupEpisode {
     pub page_key: Option<String>
 }
 
+/// Every episode of a serie, optionally restricted to one season.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RsLookupEpisodeList {
+    /// Serie name
+    pub name: Option<String>,
+    /// Serie ids
+    pub ids: Option<RsIds>,
+
+    #[serde(flatten)]
+    pub filters: RsLookupFilters,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub season: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_key: Option<String>
+}
+
+/// Episodes airing between `from` (inclusive) and `to` (exclusive), timestamps in milliseconds.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RsLookupCalendar {
+    pub from: i64,
+    pub to: i64,
+    /// Restrict to these series; all series known to the plugin when `None`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series: Option<Vec<RsIds>>,
+
+    #[serde(flatten)]
+    pub filters: RsLookupFilters,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_key: Option<String>
+}
+
+impl RsLookupCalendar {
+    pub fn accepts_airdate(&self, airdate: i64) -> bool {
+        airdate >= self.from && airdate < self.to
+    }
+
+    /// Keep only episode results airing inside the window.
+    pub fn retain_results(&self, results: &mut RsLookupMetadataResults) {
+        results.results.retain(|r| match &r.metadata {
+            RsLookupMetadataResult::Episode(e) => e.airdate.is_some_and(|a| self.accepts_airdate(a)),
+            _ => false,
+        });
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct RsLookupBook {
     pub name: Option<String>,
     pub ids: Option<RsIds>,
 
+    #[serde(flatten)]
+    pub filters: RsLookupFilters,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub page_key: Option<String>
 }
@@ -89,6 +203,9 @@ pub struct RsLookupSong {
     pub album: Option<String>,
     pub ids: Option<RsIds>,
 
+    #[serde(flatten)]
+    pub filters: RsLookupFilters,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub page_key: Option<String>
 }
@@ -99,6 +216,9 @@ pub struct RsLookupMedia {
     pub search: Option<String>,
     pub ids: Option<RsIds>,
 
+    #[serde(flatten)]
+    pub filters: RsLookupFilters,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub page_key: Option<String>
 }
@@ -109,6 +229,9 @@ pub struct RsLookupMovie {
     pub name: Option<String>,
     pub ids: Option<RsIds>,
 
+    #[serde(flatten)]
+    pub filters: RsLookupFilters,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub page_key: Option<String>
 }
@@ -120,6 +243,8 @@ pub enum RsLookupQuery {
     Book(RsLookupBook),
     Media(RsLookupMedia),
     Episode(RsLookupEpisode),
+    EpisodeList(RsLookupEpisodeList),
+    Calendar(RsLookupCalendar),
     Movie(RsLookupMovie),
     Person(RsLookupPerson),
     Serie(RsLookupSerie),
@@ -127,6 +252,57 @@ pub enum RsLookupQuery {
     Song(RsLookupSong),
 }
 
+macro_rules! lookup_query_variants {
+    ($($variant:ident($query:ty)),* $(,)?) => {
+        impl RsLookupQuery {
+            pub fn filters(&self) -> &RsLookupFilters {
+                match self {
+                    $(Self::$variant(q) => &q.filters,)*
+                }
+            }
+
+            pub fn filters_mut(&mut self) -> &mut RsLookupFilters {
+                match self {
+                    $(Self::$variant(q) => &mut q.filters,)*
+                }
+            }
+
+            pub fn page_key(&self) -> Option<&str> {
+                match self {
+                    $(Self::$variant(q) => q.page_key.as_deref(),)*
+                }
+            }
+
+            pub fn set_page_key(&mut self, page_key: Option<String>) {
+                match self {
+                    $(Self::$variant(q) => q.page_key = page_key,)*
+                }
+            }
+        }
+
+        $(
+            impl From<$query> for RsLookupQuery {
+                fn from(value: $query) -> Self {
+                    Self::$variant(value)
+                }
+            }
+        )*
+    };
+}
+
+lookup_query_variants!(
+    Book(RsLookupBook),
+    Media(RsLookupMedia),
+    Episode(RsLookupEpisode),
+    EpisodeList(RsLookupEpisodeList),
+    Calendar(RsLookupCalendar),
+    Movie(RsLookupMovie),
+    Person(RsLookupPerson),
+    Serie(RsLookupSerie),
+    SerieSeason(RsLookupSerieSeason),
+    Song(RsLookupSong),
+);
+
 #[derive(
     Debug, Serialize, Deserialize, Clone, PartialEq, strum_macros::Display, EnumString,
 )]
@@ -135,6 +311,8 @@ pub enum RsLookupQuery {
 pub enum RsLookupMatchType {
     ExactId,
     ExactText,
+    /// Approximate match, `score` from `0.0` to `1.0` (see `matching`)
+    Fuzzy { score: f32 },
 }
 
 #[derive(
@@ -148,7 +326,9 @@ pub enum RsLookupMetadataResult {
     Episode(Episode),
     Movie(Movie),
     Person(Person),
+    Season(Season),
     Serie(Serie),
+    Song(Song),
     #[default]
     None,
 }
@@ -162,6 +342,8 @@ impl RsLookupMetadataResult {
             Self::Book(b) => Some(RsIds::from(b.clone())),
             Self::Person(p) => Some(RsIds::from(p.clone())),
             Self::Episode(e) => Some(RsIds::from(e.clone())),
+            Self::Season(s) => Some(RsIds::from(s.clone())),
+            Self::Song(s) => Some(RsIds::from(s.clone())),
             Self::Media(_) | Self::None => None,
         }
     }
@@ -174,6 +356,8 @@ impl RsLookupMetadataResult {
             Self::Book(b) => b.apply_rs_ids(ids),
             Self::Person(p) => p.apply_rs_ids(ids),
             Self::Episode(e) => e.apply_rs_ids(ids),
+            Self::Season(s) => s.apply_rs_ids(ids),
+            Self::Song(s) => s.apply_rs_ids(ids),
             Self::Media(_) | Self::None => {}
         }
     }
@@ -209,3 +393,257 @@ pub struct RsLookupWrapper {
     pub credential: Option<PluginCredential>,
     pub params: Option<HashMap<String, CustomParamTypes>>,
 }
+
+impl RsLookupWrapper {
+    /// Start building a wrapper around `query`.
+    ///
+    /// ```
+    /// use rs_plugin_common_interfaces::{lookup::RsLookupMovie, RsLookupWrapper};
+    /// let wrapper = RsLookupWrapper::builder(RsLookupMovie {
+    ///     name: Some("Parasite".to_string()),
+    ///     ..Default::default()
+    /// })
+    /// .year(2019)
+    /// .lang("fr")
+    /// .build();
+    /// assert_eq!(wrapper.query.filters().year, Some(2019));
+    /// ```
+    pub fn builder(query: impl Into<RsLookupQuery>) -> RsLookupWrapperBuilder {
+        RsLookupWrapperBuilder {
+            query: query.into(),
+            credential: None,
+            params: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RsLookupWrapperBuilder {
+    query: RsLookupQuery,
+    credential: Option<PluginCredential>,
+    params: Option<HashMap<String, CustomParamTypes>>,
+}
+
+impl RsLookupWrapperBuilder {
+    pub fn credential(mut self, credential: PluginCredential) -> Self {
+        self.credential = Some(credential);
+        self
+    }
+
+    pub fn param(mut self, name: impl Into<String>, value: CustomParamTypes) -> Self {
+        self.params
+            .get_or_insert_with(HashMap::new)
+            .insert(name.into(), value);
+        self
+    }
+
+    pub fn params(mut self, params: HashMap<String, CustomParamTypes>) -> Self {
+        self.params = Some(params);
+        self
+    }
+
+    pub fn filters(mut self, filters: RsLookupFilters) -> Self {
+        *self.query.filters_mut() = filters;
+        self
+    }
+
+    pub fn year(mut self, year: u16) -> Self {
+        self.query.filters_mut().year = Some(year);
+        self
+    }
+
+    pub fn year_range(mut self, min: Option<u16>, max: Option<u16>) -> Self {
+        let filters = self.query.filters_mut();
+        filters.year_min = min;
+        filters.year_max = max;
+        self
+    }
+
+    pub fn lang(mut self, lang: impl Into<String>) -> Self {
+        self.query.filters_mut().lang = Some(lang.into());
+        self
+    }
+
+    pub fn country(mut self, country: impl Into<String>) -> Self {
+        self.query.filters_mut().country = Some(country.into());
+        self
+    }
+
+    /// Restrict results to a serie type; can be called several times to allow more types.
+    pub fn serie_type(mut self, kind: SerieType) -> Self {
+        let types = self
+            .query
+            .filters_mut()
+            .serie_types
+            .get_or_insert_with(Vec::new);
+        if !types.contains(&kind) {
+            types.push(kind);
+        }
+        self
+    }
+
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.query.filters_mut().page_size = Some(page_size);
+        self
+    }
+
+    pub fn page_key(mut self, page_key: impl Into<String>) -> Self {
+        self.query.set_page_key(Some(page_key.into()));
+        self
+    }
+
+    pub fn build(self) -> RsLookupWrapper {
+        RsLookupWrapper {
+            query: self.query,
+            credential: self.credential,
+            params: self.params,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn lookup_filters_are_flattened_and_optional() {
+        let parsed: RsLookupMovie = serde_json::from_value(json!({
+            "name": "Parasite",
+            "ids": null
+        }))
+        .unwrap();
+        assert!(parsed.filters.is_empty());
+
+        let query = RsLookupSerie {
+            name: Some("One Piece".to_string()),
+            filters: RsLookupFilters {
+                year_min: Some(1999),
+                serie_types: Some(vec![SerieType::Anime]),
+                page_size: Some(20),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let value = serde_json::to_value(&query).unwrap();
+        assert_eq!(value.get("yearMin"), Some(&json!(1999)));
+        assert_eq!(value.get("serieTypes"), Some(&json!(["anime"])));
+        assert_eq!(value.get("pageSize"), Some(&json!(20)));
+        assert_eq!(value.get("year"), None);
+
+        let parsed: RsLookupSerie = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, query);
+    }
+
+    #[test]
+    fn lookup_filters_accept() {
+        let filters = RsLookupFilters {
+            year_min: Some(2000),
+            year_max: Some(2010),
+            serie_types: Some(vec![SerieType::Anime, SerieType::Ova]),
+            ..Default::default()
+        };
+        assert!(filters.accepts_year(2000));
+        assert!(filters.accepts_year(2010));
+        assert!(!filters.accepts_year(2011));
+        assert!(filters.accepts_serie_type(&SerieType::Ova));
+        assert!(!filters.accepts_serie_type(&SerieType::Tv));
+        assert!(RsLookupFilters::default().accepts_serie_type(&SerieType::Tv));
+    }
+
+    #[test]
+    fn lookup_wrapper_builder() {
+        let wrapper = RsLookupWrapper::builder(RsLookupSerie {
+            name: Some("Frieren".to_string()),
+            ..Default::default()
+        })
+        .credential(PluginCredential {
+            login: Some("user".to_string()),
+            ..Default::default()
+        })
+        .param("region", CustomParamTypes::Text(Some("EU".to_string())))
+        .year_range(Some(2020), None)
+        .lang("fr")
+        .country("JP")
+        .serie_type(SerieType::Anime)
+        .serie_type(SerieType::Anime)
+        .page_size(10)
+        .page_key("page-2")
+        .build();
+
+        let filters = wrapper.query.filters();
+        assert_eq!(filters.year_min, Some(2020));
+        assert_eq!(filters.year_max, None);
+        assert_eq!(filters.lang.as_deref(), Some("fr"));
+        assert_eq!(filters.country.as_deref(), Some("JP"));
+        assert_eq!(filters.serie_types, Some(vec![SerieType::Anime]));
+        assert_eq!(filters.page_size, Some(10));
+        assert_eq!(wrapper.query.page_key(), Some("page-2"));
+        assert_eq!(
+            wrapper.credential.and_then(|c| c.login),
+            Some("user".to_string())
+        );
+        assert_eq!(wrapper.params.map(|p| p.len()), Some(1));
+    }
+
+    #[test]
+    fn calendar_keeps_episodes_inside_window() {
+        let episode = |airdate: Option<i64>| RsLookupMetadataResultWrapper {
+            metadata: RsLookupMetadataResult::Episode(Episode {
+                serie: "serie-1".to_string(),
+                airdate,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let calendar = RsLookupCalendar {
+            from: 1_000,
+            to: 2_000,
+            ..Default::default()
+        };
+        let mut results = RsLookupMetadataResults {
+            results: vec![
+                episode(Some(999)),
+                episode(Some(1_000)),
+                episode(Some(1_999)),
+                episode(Some(2_000)),
+                episode(None),
+            ],
+            next_page_key: None,
+        };
+        calendar.retain_results(&mut results);
+        assert_eq!(results.results.len(), 2);
+
+        let query = RsLookupQuery::from(calendar);
+        let value = serde_json::to_value(&query).unwrap();
+        assert_eq!(value, json!({ "calendar": { "from": 1000, "to": 2000 } }));
+    }
+
+    #[test]
+    fn episode_list_roundtrip() {
+        let mut ids = RsIds::default();
+        ids.set("tvdb", 81189u64);
+        let query = RsLookupQuery::from(RsLookupEpisodeList {
+            name: Some("Breaking Bad".to_string()),
+            ids: Some(ids),
+            filters: RsLookupFilters {
+                lang: Some("fr".to_string()),
+                ..Default::default()
+            },
+            season: Some(2),
+            page_key: None,
+        });
+        let value = serde_json::to_value(&query).unwrap();
+        assert_eq!(
+            value,
+            json!({ "episodeList": {
+                "name": "Breaking Bad",
+                "ids": { "tvdb": 81189 },
+                "lang": "fr",
+                "season": 2
+            } })
+        );
+        assert_eq!(serde_json::from_value::<RsLookupQuery>(value).unwrap(), query);
+    }
+}
@@ -0,0 +1,807 @@
+//! Header probing of MP4/MOV/M4V (ISO base media) and Matroska/WebM (EBML) video files.
+//!
+//! Only the headers are read: `moov` for ISO files, everything before the first `Cluster` for
+//! Matroska. A prefix of the file is enough for Matroska and fast-start MP4 files; the whole
+//! file size can then be given to still get the bitrate. Codec names follow ffprobe (`h264`,
+//! `hevc`, `aac`, `eac3`, `subrip`...).
+
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+
+use crate::{
+    domain::media::MediaForUpdate,
+    metadata::{civil_to_millis, MetadataError},
+    video::RsVideoFormat,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Display)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum ProbeTrackKind {
+    Video,
+    Audio,
+    Subtitle,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeTrack {
+    pub kind: ProbeTrackKind,
+    pub codec: String,
+    /// ISO 639-2 or BCP 47 code, `None` when undetermined
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channels: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fps: Option<f64>,
+    pub default: bool,
+    pub forced: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoProbe {
+    pub format: RsVideoFormat,
+    /// Seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+    /// Bits per second over the whole file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bitrate: Option<u64>,
+    /// Milliseconds since epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<i64>,
+    pub tracks: Vec<ProbeTrack>,
+}
+
+impl VideoProbe {
+    pub fn tracks_of(&self, kind: ProbeTrackKind) -> impl Iterator<Item = &ProbeTrack> {
+        self.tracks.iter().filter(move |track| track.kind == kind)
+    }
+
+    /// Default video track, or the first one.
+    pub fn main_video(&self) -> Option<&ProbeTrack> {
+        self.tracks_of(ProbeTrackKind::Video)
+            .find(|track| track.default)
+            .or_else(|| self.tracks_of(ProbeTrackKind::Video).next())
+    }
+
+    pub fn vcodecs(&self) -> Vec<String> {
+        codecs(self.tracks_of(ProbeTrackKind::Video))
+    }
+
+    pub fn acodecs(&self) -> Vec<String> {
+        codecs(self.tracks_of(ProbeTrackKind::Audio))
+    }
+
+    /// Channel count of each audio track, `Media.achan`.
+    pub fn achan(&self) -> Vec<usize> {
+        self.tracks_of(ProbeTrackKind::Audio)
+            .map(|track| track.channels.unwrap_or(0) as usize)
+            .collect()
+    }
+
+    /// Distinct audio languages, in track order.
+    pub fn audio_languages(&self) -> Vec<String> {
+        languages(self.tracks_of(ProbeTrackKind::Audio))
+    }
+
+    pub fn subtitle_languages(&self) -> Vec<String> {
+        languages(self.tracks_of(ProbeTrackKind::Subtitle))
+    }
+
+    /// Patch with the technical fields. `lang` is the language of the default audio track.
+    pub fn to_update(&self) -> MediaForUpdate {
+        let video = self.main_video();
+        let audio = self
+            .tracks_of(ProbeTrackKind::Audio)
+            .find(|track| track.default)
+            .or_else(|| self.tracks_of(ProbeTrackKind::Audio).next());
+        let non_empty = |list: Vec<String>| (!list.is_empty()).then_some(list);
+        MediaForUpdate {
+            duration: self.duration.map(|duration| duration.round() as u64),
+            width: video.and_then(|video| video.width),
+            height: video.and_then(|video| video.height),
+            fps: video.and_then(|video| video.fps),
+            bitrate: self.bitrate,
+            created: self.created,
+            vcodecs: non_empty(self.vcodecs()),
+            acodecs: non_empty(self.acodecs()),
+            achan: Some(self.achan()).filter(|achan| !achan.is_empty()),
+            lang: audio.and_then(|audio| audio.language.clone()).into(),
+            ..Default::default()
+        }
+    }
+}
+
+fn codecs<'a>(tracks: impl Iterator<Item = &'a ProbeTrack>) -> Vec<String> {
+    tracks.map(|track| track.codec.clone()).collect()
+}
+
+fn languages<'a>(tracks: impl Iterator<Item = &'a ProbeTrack>) -> Vec<String> {
+    let mut languages: Vec<String> = vec![];
+    for language in tracks.filter_map(|track| track.language.as_ref()) {
+        if !languages.contains(language) {
+            languages.push(language.clone());
+        }
+    }
+    languages
+}
+
+/// Probe a video file of type `mime`.
+///
+/// `bytes` is the file or its beginning; `file_size` is the full size when `bytes` is only a
+/// prefix. `application/octet-stream` is sniffed from the magic bytes.
+pub fn probe_video(
+    bytes: &[u8],
+    mime: &str,
+    file_size: Option<u64>,
+) -> Result<VideoProbe, MetadataError> {
+    let format = match RsVideoFormat::from_mime(mime) {
+        Some(RsVideoFormat::Other) if bytes.starts_with(&EBML_MAGIC) => RsVideoFormat::Mkv,
+        Some(RsVideoFormat::Other) if bytes.get(4..8) == Some(b"ftyp") => RsVideoFormat::Mp4,
+        Some(format) => format,
+        None => return Err(MetadataError::UnsupportedFormat(mime.to_string())),
+    };
+    let mut probe = match format {
+        RsVideoFormat::Mp4 | RsVideoFormat::M4v | RsVideoFormat::Mov => probe_mp4(bytes)?,
+        RsVideoFormat::Mkv | RsVideoFormat::WebM => probe_matroska(bytes)?,
+        _ => return Err(MetadataError::UnsupportedFormat(mime.to_string())),
+    };
+    probe.format = format;
+    let size = file_size.unwrap_or(bytes.len() as u64);
+    probe.bitrate = probe
+        .duration
+        .filter(|duration| *duration > 0.0)
+        .map(|duration| (size as f64 * 8.0 / duration).round() as u64);
+    Ok(probe)
+}
+
+fn normalize_language(code: &str) -> Option<String> {
+    let code = code.trim_matches(char::from(0)).trim();
+    (!code.is_empty() && code != "und").then(|| code.to_string())
+}
+
+fn round_fps(fps: f64) -> Option<f64> {
+    (fps.is_finite() && fps > 0.0).then(|| (fps * 1000.0).round() / 1000.0)
+}
+
+fn be_uint(data: &[u8]) -> u64 {
+    data.iter()
+        .fold(0, |value, byte| (value << 8) | *byte as u64)
+}
+
+fn read_uint(data: &[u8], offset: usize, len: usize) -> Option<u64> {
+    data.get(offset..offset + len).map(be_uint)
+}
+
+// region:    --- ISO base media
+
+/// Seconds between 1904-01-01 and 1970-01-01
+const MP4_EPOCH_OFFSET: u64 = 2_082_844_800;
+
+/// Child boxes of `data` as `(type, content)`; a truncated last box is cut to the data.
+fn mp4_boxes(data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut boxes = vec![];
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let mut size = be_uint(&data[pos..pos + 4]);
+        let kind: [u8; 4] = data[pos + 4..pos + 8].try_into().unwrap_or_default();
+        let mut header = 8;
+        if size == 1 {
+            match read_uint(data, pos + 8, 8) {
+                Some(large) => size = large,
+                None => break,
+            }
+            header = 16;
+        } else if size == 0 {
+            size = (data.len() - pos) as u64;
+        }
+        if size < header as u64 {
+            break;
+        }
+        let end = pos.saturating_add(size as usize).min(data.len());
+        boxes.push((kind, &data[pos + header..end]));
+        pos = end;
+    }
+    boxes
+}
+
+fn mp4_child<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    mp4_boxes(data)
+        .into_iter()
+        .find(|(k, _)| k == kind)
+        .map(|(_, content)| content)
+}
+
+fn mp4_path<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    path.iter()
+        .try_fold(data, |data, kind| mp4_child(data, kind))
+}
+
+/// `(timescale, duration, creation)` of a `mvhd` or `mdhd` box.
+fn mp4_header(data: &[u8]) -> Option<(u64, u64, u64)> {
+    match data.first()? {
+        1 => Some((
+            read_uint(data, 20, 4)?,
+            read_uint(data, 24, 8)?,
+            read_uint(data, 4, 8)?,
+        )),
+        _ => Some((
+            read_uint(data, 12, 4)?,
+            read_uint(data, 16, 4)?,
+            read_uint(data, 4, 4)?,
+        )),
+    }
+}
+
+fn mp4_codec(format: &[u8; 4]) -> String {
+    match format {
+        b"avc1" | b"avc3" => "h264".to_string(),
+        b"hvc1" | b"hev1" => "hevc".to_string(),
+        b"av01" => "av1".to_string(),
+        b"vp09" => "vp9".to_string(),
+        b"mp4v" => "mpeg4".to_string(),
+        b"mp4a" => "aac".to_string(),
+        b"ac-3" => "ac3".to_string(),
+        b"ec-3" => "eac3".to_string(),
+        b"Opus" => "opus".to_string(),
+        b"fLaC" => "flac".to_string(),
+        b".mp3" => "mp3".to_string(),
+        b"tx3g" => "mov_text".to_string(),
+        b"wvtt" => "webvtt".to_string(),
+        b"c608" => "eia_608".to_string(),
+        other => String::from_utf8_lossy(other).trim().to_lowercase(),
+    }
+}
+
+fn probe_mp4(bytes: &[u8]) -> Result<VideoProbe, MetadataError> {
+    let moov = mp4_child(bytes, b"moov").ok_or(MetadataError::NotFound)?;
+    let mut probe = VideoProbe::default();
+    if let Some((timescale, duration, creation)) = mp4_child(moov, b"mvhd").and_then(mp4_header) {
+        if timescale > 0 {
+            probe.duration = Some(duration as f64 / timescale as f64);
+        }
+        probe.created = creation
+            .checked_sub(MP4_EPOCH_OFFSET)
+            .filter(|seconds| *seconds > 0)
+            .and_then(|seconds| seconds.checked_mul(1000))
+            .and_then(|millis| i64::try_from(millis).ok());
+    }
+    probe.tracks = mp4_boxes(moov)
+        .into_iter()
+        .filter(|(kind, _)| kind == b"trak")
+        .filter_map(|(_, trak)| mp4_track(trak))
+        .collect();
+    Ok(probe)
+}
+
+fn mp4_track(trak: &[u8]) -> Option<ProbeTrack> {
+    let mdia = mp4_child(trak, b"mdia")?;
+    let kind = match mp4_child(mdia, b"hdlr")?.get(8..12)? {
+        b"vide" => ProbeTrackKind::Video,
+        b"soun" => ProbeTrackKind::Audio,
+        b"sbtl" | b"subt" | b"text" | b"clcp" => ProbeTrackKind::Subtitle,
+        _ => return None,
+    };
+    let mdhd = mp4_child(mdia, b"mdhd")?;
+    let (timescale, _, _) = mp4_header(mdhd)?;
+    let language_offset = if mdhd.first() == Some(&1) { 32 } else { 20 };
+    let language = read_uint(mdhd, language_offset, 2).and_then(|packed| {
+        let code: String = [10, 5, 0]
+            .iter()
+            .map(|shift| char::from((((packed >> shift) & 0x1f) as u8) + 0x60))
+            .collect();
+        normalize_language(&code).filter(|code| code.chars().all(|c| c.is_ascii_lowercase()))
+    });
+
+    let stbl = mp4_path(mdia, &[b"minf", b"stbl"])?;
+    let stsd = mp4_child(stbl, b"stsd")?;
+    let (format, entry) = mp4_boxes(stsd.get(8..)?).into_iter().next()?;
+    let mut track = ProbeTrack {
+        kind,
+        codec: mp4_codec(&format),
+        language,
+        name: None,
+        channels: None,
+        width: None,
+        height: None,
+        fps: None,
+        default: true,
+        forced: false,
+    };
+    match kind {
+        ProbeTrackKind::Video => {
+            track.width = read_uint(entry, 24, 2).map(|w| w as u32);
+            track.height = read_uint(entry, 26, 2).map(|h| h as u32);
+            track.fps = mp4_child(stbl, b"stts").and_then(|stts| {
+                // the entry count is not trusted beyond the entries present
+                let count = (read_uint(stts, 4, 4)? as usize)
+                    .min(stts.len().saturating_sub(8) / 8);
+                let (samples, duration) = (0..count)
+                    .filter_map(|index| {
+                        let offset = 8 + index * 8;
+                        Some((read_uint(stts, offset, 4)?, read_uint(stts, offset + 4, 4)?))
+                    })
+                    .fold((0u128, 0u128), |(samples, duration), (count, delta)| {
+                        (samples + count as u128, duration + count as u128 * delta as u128)
+                    });
+                round_fps(samples as f64 * timescale as f64 / duration as f64)
+            });
+        }
+        ProbeTrackKind::Audio => track.channels = read_uint(entry, 16, 2).map(|c| c as u32),
+        ProbeTrackKind::Subtitle => {}
+    }
+    if let Some(tkhd) = mp4_child(trak, b"tkhd") {
+        // flags: 0x1 enabled
+        track.default = read_uint(tkhd, 1, 3).is_some_and(|flags| flags & 1 == 1);
+    }
+    Some(track)
+}
+
+// endregion: --- ISO base media
+
+// region:    --- Matroska
+
+const EBML_MAGIC: [u8; 4] = [0x1a, 0x45, 0xdf, 0xa3];
+
+const MKV_SEGMENT: u64 = 0x18538067;
+const MKV_INFO: u64 = 0x1549a966;
+const MKV_TIMESTAMP_SCALE: u64 = 0x2ad7b1;
+const MKV_DURATION: u64 = 0x4489;
+const MKV_DATE_UTC: u64 = 0x4461;
+const MKV_TRACKS: u64 = 0x1654ae6b;
+const MKV_TRACK_ENTRY: u64 = 0xae;
+const MKV_TRACK_TYPE: u64 = 0x83;
+const MKV_CODEC_ID: u64 = 0x86;
+const MKV_LANGUAGE: u64 = 0x22b59c;
+const MKV_LANGUAGE_BCP47: u64 = 0x22b59d;
+const MKV_NAME: u64 = 0x536e;
+const MKV_FLAG_DEFAULT: u64 = 0x88;
+const MKV_FLAG_FORCED: u64 = 0x55aa;
+const MKV_DEFAULT_DURATION: u64 = 0x23e383;
+const MKV_VIDEO: u64 = 0xe0;
+const MKV_PIXEL_WIDTH: u64 = 0xb0;
+const MKV_PIXEL_HEIGHT: u64 = 0xba;
+const MKV_AUDIO: u64 = 0xe1;
+const MKV_CHANNELS: u64 = 0x9f;
+const MKV_CLUSTER: u64 = 0x1f43b675;
+
+/// Variable size integer at `pos`: `(value, length)`. The length marker is kept for ids.
+fn ebml_vint(data: &[u8], pos: usize, keep_marker: bool) -> Option<(u64, usize)> {
+    let first = *data.get(pos)?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 {
+        return None;
+    }
+    let value = read_uint(data, pos, len)?;
+    if keep_marker {
+        Some((value, len))
+    } else {
+        Some((value & !(1 << (7 * len)), len))
+    }
+}
+
+/// Child elements as `(id, content)` until the first `Cluster`. Unknown sizes extend to the
+/// end of the parent, truncated elements are cut to the data.
+fn ebml_elements(data: &[u8]) -> Vec<(u64, &[u8])> {
+    let mut elements = vec![];
+    let mut pos = 0;
+    while let Some((id, id_len)) = ebml_vint(data, pos, true) {
+        if id == MKV_CLUSTER {
+            break;
+        }
+        let Some((size, size_len)) = ebml_vint(data, pos + id_len, false) else {
+            break;
+        };
+        let start = pos + id_len + size_len;
+        let unknown = size == (1 << (7 * size_len)) - 1;
+        let end = if unknown {
+            data.len()
+        } else {
+            start.saturating_add(size as usize).min(data.len())
+        };
+        if start > end {
+            break;
+        }
+        elements.push((id, &data[start..end]));
+        pos = end;
+    }
+    elements
+}
+
+fn ebml_child(data: &[u8], id: u64) -> Option<&[u8]> {
+    ebml_elements(data)
+        .into_iter()
+        .find(|(element, _)| *element == id)
+        .map(|(_, content)| content)
+}
+
+fn ebml_uint(data: &[u8], id: u64) -> Option<u64> {
+    ebml_child(data, id)
+        .filter(|content| content.len() <= 8)
+        .map(be_uint)
+}
+
+fn ebml_string(data: &[u8], id: u64) -> Option<String> {
+    ebml_child(data, id)
+        .map(|content| {
+            String::from_utf8_lossy(content)
+                .trim_matches(char::from(0))
+                .to_string()
+        })
+        .filter(|text| !text.is_empty())
+}
+
+fn ebml_float(data: &[u8], id: u64) -> Option<f64> {
+    let content = ebml_child(data, id)?;
+    match content.len() {
+        4 => Some(f32::from_bits(be_uint(content) as u32) as f64),
+        8 => Some(f64::from_bits(be_uint(content))),
+        _ => None,
+    }
+}
+
+fn mkv_codec(codec: &str) -> String {
+    let known = [
+        ("V_MPEG4/ISO/AVC", "h264"),
+        ("V_MPEGH/ISO/HEVC", "hevc"),
+        ("V_AV1", "av1"),
+        ("V_VP9", "vp9"),
+        ("V_VP8", "vp8"),
+        ("V_MPEG4/ISO/ASP", "mpeg4"),
+        ("V_MPEG2", "mpeg2video"),
+        ("A_AAC", "aac"),
+        ("A_AC3", "ac3"),
+        ("A_EAC3", "eac3"),
+        ("A_DTS", "dts"),
+        ("A_TRUEHD", "truehd"),
+        ("A_OPUS", "opus"),
+        ("A_VORBIS", "vorbis"),
+        ("A_FLAC", "flac"),
+        ("A_MPEG/L3", "mp3"),
+        ("S_TEXT/UTF8", "subrip"),
+        ("S_TEXT/ASS", "ass"),
+        ("S_ASS", "ass"),
+        ("S_TEXT/SSA", "ssa"),
+        ("S_SSA", "ssa"),
+        ("S_TEXT/WEBVTT", "webvtt"),
+        ("S_HDMV/PGS", "hdmv_pgs_subtitle"),
+        ("S_VOBSUB", "dvd_subtitle"),
+    ];
+    known
+        .iter()
+        .find(|(id, _)| codec.starts_with(id))
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| codec.to_lowercase())
+}
+
+fn probe_matroska(bytes: &[u8]) -> Result<VideoProbe, MetadataError> {
+    if !bytes.starts_with(&EBML_MAGIC) {
+        return Err(MetadataError::Invalid("Missing EBML header".to_string()));
+    }
+    let segment = ebml_child(bytes, MKV_SEGMENT).ok_or(MetadataError::NotFound)?;
+    let mut probe = VideoProbe::default();
+    if let Some(info) = ebml_child(segment, MKV_INFO) {
+        let scale = ebml_uint(info, MKV_TIMESTAMP_SCALE).unwrap_or(1_000_000) as f64;
+        probe.duration = ebml_float(info, MKV_DURATION).map(|d| d * scale / 1e9);
+        // nanoseconds since 2001-01-01
+        probe.created = ebml_child(info, MKV_DATE_UTC)
+            .filter(|content| content.len() == 8)
+            .map(|content| {
+                civil_to_millis(2001, 1, 1, 0, 0, 0) + be_uint(content) as i64 / 1_000_000
+            });
+    }
+    let tracks = ebml_child(segment, MKV_TRACKS).ok_or(MetadataError::NotFound)?;
+    probe.tracks = ebml_elements(tracks)
+        .into_iter()
+        .filter(|(id, _)| *id == MKV_TRACK_ENTRY)
+        .filter_map(|(_, entry)| mkv_track(entry))
+        .collect();
+    Ok(probe)
+}
+
+fn mkv_track(entry: &[u8]) -> Option<ProbeTrack> {
+    let kind = match ebml_uint(entry, MKV_TRACK_TYPE)? {
+        1 => ProbeTrackKind::Video,
+        2 => ProbeTrackKind::Audio,
+        17 => ProbeTrackKind::Subtitle,
+        _ => return None,
+    };
+    let video = ebml_child(entry, MKV_VIDEO);
+    let audio = ebml_child(entry, MKV_AUDIO);
+    Some(ProbeTrack {
+        kind,
+        codec: mkv_codec(&ebml_string(entry, MKV_CODEC_ID).unwrap_or_default()),
+        // Matroska default language is English
+        language: ebml_string(entry, MKV_LANGUAGE_BCP47)
+            .or_else(|| ebml_string(entry, MKV_LANGUAGE))
+            .map_or(Some("eng".to_string()), |code| normalize_language(&code)),
+        name: ebml_string(entry, MKV_NAME),
+        channels: audio
+            .and_then(|audio| ebml_uint(audio, MKV_CHANNELS))
+            .map(|c| c as u32),
+        width: video
+            .and_then(|video| ebml_uint(video, MKV_PIXEL_WIDTH))
+            .map(|w| w as u32),
+        height: video
+            .and_then(|video| ebml_uint(video, MKV_PIXEL_HEIGHT))
+            .map(|h| h as u32),
+        fps: ebml_uint(entry, MKV_DEFAULT_DURATION)
+            .filter(|_| kind == ProbeTrackKind::Video)
+            .and_then(|duration| round_fps(1e9 / duration as f64)),
+        default: ebml_uint(entry, MKV_FLAG_DEFAULT) != Some(0),
+        forced: ebml_uint(entry, MKV_FLAG_FORCED) == Some(1),
+    })
+}
+
+// endregion: --- Matroska
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::update::Patch;
+
+    fn mp4_box(kind: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        [
+            &((content.len() + 8) as u32).to_be_bytes()[..],
+            kind,
+            content,
+        ]
+        .concat()
+    }
+
+    fn mp4_trak(
+        handler: &[u8; 4],
+        language: &str,
+        entry: Vec<u8>,
+        stts: Option<Vec<u8>>,
+    ) -> Vec<u8> {
+        let packed = language
+            .bytes()
+            .fold(0u16, |packed, c| (packed << 5) | (c - 0x60) as u16);
+        let mut mdhd = vec![0; 20];
+        mdhd[12..16].copy_from_slice(&24000u32.to_be_bytes());
+        mdhd.extend(packed.to_be_bytes());
+        mdhd.extend([0, 0]);
+        let hdlr = [&[0u8; 8][..], handler, &[0u8; 12]].concat();
+        let stsd = [&[0u8, 0, 0, 0, 0, 0, 0, 1][..], &entry].concat();
+        let stbl = [
+            mp4_box(b"stsd", &stsd),
+            stts.map(|s| mp4_box(b"stts", &s)).unwrap_or_default(),
+        ]
+        .concat();
+        let minf = mp4_box(b"stbl", &stbl);
+        let mdia = [
+            mp4_box(b"mdhd", &mdhd),
+            mp4_box(b"hdlr", &hdlr),
+            mp4_box(b"minf", &minf),
+        ]
+        .concat();
+        let tkhd = [0u8, 0, 0, 1];
+        mp4_box(
+            b"trak",
+            &[mp4_box(b"tkhd", &tkhd), mp4_box(b"mdia", &mdia)].concat(),
+        )
+    }
+
+    fn mp4() -> Vec<u8> {
+        let mut mvhd = vec![0; 20];
+        // 2024-02-29T12:30:15Z
+        mvhd[4..8].copy_from_slice(&((1709209815 + MP4_EPOCH_OFFSET) as u32).to_be_bytes());
+        mvhd[12..16].copy_from_slice(&1000u32.to_be_bytes());
+        mvhd[16..20].copy_from_slice(&120_000u32.to_be_bytes());
+
+        let mut video = vec![0; 78];
+        video[24..26].copy_from_slice(&1920u16.to_be_bytes());
+        video[26..28].copy_from_slice(&1080u16.to_be_bytes());
+        // 2880 frames of 1001 at 24000 -> 23.976 fps
+        let stts = [
+            &1u32.to_be_bytes()[..],
+            &1u32.to_be_bytes(),
+            &2880u32.to_be_bytes(),
+            &1001u32.to_be_bytes(),
+        ]
+        .concat();
+        let mut audio = vec![0; 28];
+        audio[16..18].copy_from_slice(&6u16.to_be_bytes());
+
+        let moov = [
+            mp4_box(b"mvhd", &mvhd),
+            mp4_trak(b"vide", "und", mp4_box(b"hvc1", &video), Some(stts)),
+            mp4_trak(b"soun", "fra", mp4_box(b"ec-3", &audio), None),
+            mp4_trak(b"sbtl", "eng", mp4_box(b"tx3g", &[0; 8]), None),
+        ]
+        .concat();
+        [
+            mp4_box(b"ftyp", b"isom\0\0\0\0"),
+            mp4_box(b"moov", &moov),
+            mp4_box(b"mdat", &[0; 16]),
+        ]
+        .concat()
+    }
+
+    fn ebml(id: u64, content: &[u8]) -> Vec<u8> {
+        let id_bytes = id.to_be_bytes();
+        let id_bytes = &id_bytes[(id.leading_zeros() / 8) as usize..];
+        // 8 bytes size: length marker then 7 bytes of value
+        [
+            id_bytes,
+            &(content.len() as u64 | 1 << 56).to_be_bytes(),
+            content,
+        ]
+        .concat()
+    }
+
+    fn mkv() -> Vec<u8> {
+        let info = [
+            ebml(MKV_TIMESTAMP_SCALE, &1_000_000u32.to_be_bytes()),
+            ebml(MKV_DURATION, &5_400_000f64.to_be_bytes()),
+        ]
+        .concat();
+        let video = [
+            ebml(MKV_TRACK_TYPE, &[1]),
+            ebml(MKV_CODEC_ID, b"V_MPEG4/ISO/AVC"),
+            ebml(MKV_DEFAULT_DURATION, &40_000_000u32.to_be_bytes()),
+            ebml(
+                MKV_VIDEO,
+                &[
+                    ebml(MKV_PIXEL_WIDTH, &3840u16.to_be_bytes()),
+                    ebml(MKV_PIXEL_HEIGHT, &1600u16.to_be_bytes()),
+                ]
+                .concat(),
+            ),
+        ]
+        .concat();
+        let audio = |codec: &[u8], language: &[u8], channels: u8, default: u8| {
+            [
+                ebml(MKV_TRACK_TYPE, &[2]),
+                ebml(MKV_CODEC_ID, codec),
+                ebml(MKV_LANGUAGE, language),
+                ebml(MKV_FLAG_DEFAULT, &[default]),
+                ebml(MKV_AUDIO, &ebml(MKV_CHANNELS, &[channels])),
+            ]
+            .concat()
+        };
+        let subtitle = [
+            ebml(MKV_TRACK_TYPE, &[17]),
+            ebml(MKV_CODEC_ID, b"S_TEXT/UTF8"),
+            ebml(MKV_LANGUAGE, b"fre"),
+            ebml(MKV_NAME, b"Forced"),
+            ebml(MKV_FLAG_FORCED, &[1]),
+        ]
+        .concat();
+        let tracks = [
+            ebml(MKV_TRACK_ENTRY, &video),
+            ebml(MKV_TRACK_ENTRY, &audio(b"A_EAC3", b"eng", 6, 0)),
+            ebml(MKV_TRACK_ENTRY, &audio(b"A_AAC", b"fre", 2, 1)),
+            ebml(MKV_TRACK_ENTRY, &subtitle),
+        ]
+        .concat();
+        let segment = [
+            ebml(MKV_INFO, &info),
+            ebml(MKV_TRACKS, &tracks),
+            ebml(MKV_CLUSTER, &[0; 32]),
+        ]
+        .concat();
+        [
+            ebml(0x1a45dfa3, &ebml(0x4282, b"matroska")),
+            ebml(MKV_SEGMENT, &segment),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn probe_mp4_file() {
+        let bytes = mp4();
+        let probe = probe_video(&bytes, "video/mp4", Some(15_000_000)).unwrap();
+        assert_eq!(probe.format, RsVideoFormat::Mp4);
+        assert_eq!(probe.duration, Some(120.0));
+        assert_eq!(probe.bitrate, Some(1_000_000));
+        assert_eq!(probe.created, Some(1709209815000));
+        assert_eq!(probe.vcodecs(), vec!["hevc"]);
+        assert_eq!(probe.acodecs(), vec!["eac3"]);
+        assert_eq!(probe.achan(), vec![6]);
+        assert_eq!(probe.audio_languages(), vec!["fra"]);
+        assert_eq!(probe.subtitle_languages(), vec!["eng"]);
+        let video = probe.main_video().unwrap();
+        assert_eq!((video.width, video.height), (Some(1920), Some(1080)));
+        assert_eq!(video.fps, Some(23.976));
+        assert_eq!(video.language, None);
+
+        let update = probe.to_update();
+        assert_eq!(update.duration, Some(120));
+        assert_eq!(update.width, Some(1920));
+        assert_eq!(update.achan, Some(vec![6]));
+        assert_eq!(update.lang, Patch::Set("fra".to_string()));
+    }
+
+    #[test]
+    fn probe_matroska_header() {
+        let bytes = mkv();
+        let probe = probe_video(&bytes, "application/x-matroska", None).unwrap();
+        assert_eq!(probe.duration, Some(5400.0));
+        assert_eq!(probe.vcodecs(), vec!["h264"]);
+        assert_eq!(probe.acodecs(), vec!["eac3", "aac"]);
+        assert_eq!(probe.achan(), vec![6, 2]);
+        assert_eq!(probe.audio_languages(), vec!["eng", "fre"]);
+        let subtitle = probe.tracks_of(ProbeTrackKind::Subtitle).next().unwrap();
+        assert_eq!(subtitle.codec, "subrip");
+        assert!(subtitle.forced);
+        assert_eq!(subtitle.name.as_deref(), Some("Forced"));
+
+        let update = probe.to_update();
+        assert_eq!((update.width, update.height), (Some(3840), Some(1600)));
+        assert_eq!(update.fps, Some(25.0));
+        assert_eq!(update.lang, Patch::Set("fre".to_string()));
+
+        // a header prefix is enough, sniffed from octet-stream
+        let prefix = &bytes[..bytes.len() - 20];
+        let probe = probe_video(prefix, "application/octet-stream", None).unwrap();
+        assert_eq!(probe.format, RsVideoFormat::Mkv);
+        assert_eq!(probe.tracks.len(), 4);
+    }
+
+    #[test]
+    fn probe_crafted_mp4() {
+        // version 1 mvhd: creation time of u64::MAX
+        let mut mvhd = vec![0; 32];
+        mvhd[0] = 1;
+        mvhd[4..12].copy_from_slice(&u64::MAX.to_be_bytes());
+        mvhd[20..24].copy_from_slice(&1000u32.to_be_bytes());
+        // u32::MAX entries announced, two present with u32::MAX samples and deltas
+        let stts = [
+            &[0u8; 4][..],
+            &u32::MAX.to_be_bytes(),
+            &u32::MAX.to_be_bytes(),
+            &u32::MAX.to_be_bytes(),
+            &u32::MAX.to_be_bytes(),
+            &u32::MAX.to_be_bytes(),
+        ]
+        .concat();
+        let mut video = vec![0; 78];
+        video[24..26].copy_from_slice(&1920u16.to_be_bytes());
+        let moov = [
+            mp4_box(b"mvhd", &mvhd),
+            mp4_trak(b"vide", "und", mp4_box(b"avc1", &video), Some(stts)),
+        ]
+        .concat();
+        let bytes = [mp4_box(b"ftyp", b"isom\0\0\0\0"), mp4_box(b"moov", &moov)].concat();
+        let probe = probe_video(&bytes, "video/mp4", None).unwrap();
+        assert_eq!(probe.created, None);
+        assert_eq!(probe.duration, Some(0.0));
+        let video = probe.main_video().unwrap();
+        assert_eq!(video.width, Some(1920));
+        assert!(video.fps.is_some_and(|fps| fps < 1.0));
+
+        // a huge entry count with no entries
+        let stts = [&[0u8; 4][..], &u32::MAX.to_be_bytes()].concat();
+        let moov = mp4_trak(b"vide", "und", mp4_box(b"avc1", &[0; 78]), Some(stts));
+        let bytes = mp4_box(b"moov", &moov);
+        let probe = probe_video(&bytes, "video/mp4", None).unwrap();
+        assert_eq!(probe.main_video().unwrap().fps, None);
+    }
+
+    #[test]
+    fn probe_errors() {
+        assert!(matches!(
+            probe_video(&[], "video/x-msvideo", None),
+            Err(MetadataError::UnsupportedFormat(_))
+        ));
+        assert!(matches!(
+            probe_video(&[0; 16], "image/jpeg", None),
+            Err(MetadataError::UnsupportedFormat(_))
+        ));
+        assert!(matches!(
+            probe_video(&mp4_box(b"ftyp", b"isom"), "video/mp4", None),
+            Err(MetadataError::NotFound)
+        ));
+    }
+}
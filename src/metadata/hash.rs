@@ -0,0 +1,447 @@
+//! Perceptual hash and thumbhash of decoded RGBA pixels (`Media.phash` and `Media.thumbhash`).
+//!
+//! Decoding is left to the caller, these functions only take `width * height * 4` bytes.
+
+use std::f64::consts::PI;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{domain::media::Media, metadata::MetadataError};
+
+/// Side of the grayscale image the perceptual hash DCT runs on
+const PHASH_SAMPLE: usize = 32;
+/// Side of the low frequencies kept, 8 x 8 = 64 bits
+const PHASH_SIZE: usize = 8;
+/// Thumbhash encodes at most 100 x 100 pixels
+const THUMBHASH_MAX: usize = 100;
+
+/// Default maximum Hamming distance for two phashes to be near duplicates.
+pub const DUPLICATE_DISTANCE: u32 = 10;
+
+/// RGBA pixels in rows, alpha not premultiplied.
+#[derive(Debug, Clone, Copy)]
+pub struct RgbaImage<'a> {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: &'a [u8],
+}
+
+impl<'a> RgbaImage<'a> {
+    pub fn new(width: usize, height: usize, pixels: &'a [u8]) -> Result<Self, MetadataError> {
+        let len = width.checked_mul(height).and_then(|len| len.checked_mul(4));
+        if width == 0 || height == 0 || len != Some(pixels.len()) {
+            return Err(MetadataError::Invalid(format!(
+                "{} bytes for {}x{} RGBA pixels",
+                pixels.len(),
+                width,
+                height
+            )));
+        }
+        Ok(RgbaImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Box filter resize of each channel to `width` x `height`.
+    fn resize(&self, width: usize, height: usize) -> Vec<[f64; 4]> {
+        let mut resized = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let (y0, y1) = span(y, height, self.height);
+            for x in 0..width {
+                let (x0, x1) = span(x, width, self.width);
+                let mut sum = [0.0; 4];
+                for sy in y0..y1 {
+                    for sx in x0..x1 {
+                        let index = (sy * self.width + sx) * 4;
+                        for (channel, value) in sum.iter_mut().enumerate() {
+                            *value += self.pixels[index + channel] as f64;
+                        }
+                    }
+                }
+                let count = ((y1 - y0) * (x1 - x0)) as f64;
+                resized.push(sum.map(|value| value / count));
+            }
+        }
+        resized
+    }
+}
+
+/// Source range of the destination pixel `index`, never empty.
+fn span(index: usize, target: usize, source: usize) -> (usize, usize) {
+    let start = index * source / target;
+    let end = ((index + 1) * source).div_ceil(target);
+    (start, end.max(start + 1).min(source))
+}
+
+/// 64 bits DCT perceptual hash, as 16 hex characters.
+///
+/// The image is reduced to 32 x 32 grayscale, and each of the 8 x 8 lowest frequencies gives
+/// a bit: above the median or not. Resizes, recompressions and small color changes keep the
+/// hash within a few bits.
+pub fn perceptual_hash(image: &RgbaImage) -> String {
+    let gray: Vec<f64> = image
+        .resize(PHASH_SAMPLE, PHASH_SAMPLE)
+        .iter()
+        .map(|[r, g, b, _]| 0.299 * r + 0.587 * g + 0.114 * b)
+        .collect();
+
+    let cosines: Vec<f64> = (0..PHASH_SIZE * PHASH_SAMPLE)
+        .map(|i| {
+            let (u, x) = (i / PHASH_SAMPLE, i % PHASH_SAMPLE);
+            (PI / PHASH_SAMPLE as f64 * (x as f64 + 0.5) * u as f64).cos()
+        })
+        .collect();
+    let mut frequencies = Vec::with_capacity(PHASH_SIZE * PHASH_SIZE);
+    for v in 0..PHASH_SIZE {
+        for u in 0..PHASH_SIZE {
+            let mut sum = 0.0;
+            for y in 0..PHASH_SAMPLE {
+                let fy = cosines[v * PHASH_SAMPLE + y];
+                for x in 0..PHASH_SAMPLE {
+                    sum += gray[y * PHASH_SAMPLE + x] * cosines[u * PHASH_SAMPLE + x] * fy;
+                }
+            }
+            frequencies.push(sum);
+        }
+    }
+
+    let mut sorted = frequencies.clone();
+    sorted.sort_by(f64::total_cmp);
+    let median = (sorted[31] + sorted[32]) / 2.0;
+    let hash = frequencies
+        .iter()
+        .fold(0u64, |hash, value| hash << 1 | (*value > median) as u64);
+    format!("{:016x}", hash)
+}
+
+/// Number of different bits between two phashes.
+pub fn phash_distance(a: &str, b: &str) -> Result<u32, MetadataError> {
+    let parse = |hash: &str| {
+        u64::from_str_radix(hash, 16).map_err(|_| MetadataError::Invalid(hash.to_string()))
+    };
+    if a.len() != b.len() {
+        return Err(MetadataError::Invalid(format!("{} / {}", a, b)));
+    }
+    Ok((parse(a)? ^ parse(b)?).count_ones())
+}
+
+/// Groups of near duplicate medias ids, by `phash` distance up to `max_distance`.
+///
+/// Groups are transitive (a ~ b and b ~ c group a, b and c) and only have two medias or more,
+/// in the order of the medias. Medias without a valid phash are ignored.
+pub fn group_near_duplicates(medias: &[Media], max_distance: u32) -> Vec<Vec<String>> {
+    let hashes: Vec<(usize, u64)> = medias
+        .iter()
+        .enumerate()
+        .filter_map(|(index, media)| {
+            let hash = media.phash.as_deref()?;
+            (hash.len() == 16)
+                .then(|| u64::from_str_radix(hash, 16).ok())
+                .flatten()
+                .map(|hash| (index, hash))
+        })
+        .collect();
+
+    let mut parents: Vec<usize> = (0..medias.len()).collect();
+    fn root(parents: &mut [usize], mut index: usize) -> usize {
+        while parents[index] != index {
+            parents[index] = parents[parents[index]];
+            index = parents[index];
+        }
+        index
+    }
+    for (position, (a, hash_a)) in hashes.iter().enumerate() {
+        for (b, hash_b) in &hashes[position + 1..] {
+            if (hash_a ^ hash_b).count_ones() <= max_distance {
+                let (root_a, root_b) = (root(&mut parents, *a), root(&mut parents, *b));
+                parents[root_a.max(root_b)] = root_a.min(root_b);
+            }
+        }
+    }
+
+    let mut groups: Vec<(usize, Vec<String>)> = Vec::new();
+    for (index, _) in &hashes {
+        let group = root(&mut parents, *index);
+        match groups.iter_mut().find(|(root, _)| *root == group) {
+            Some((_, ids)) => ids.push(medias[*index].id.clone()),
+            None => groups.push((group, vec![medias[*index].id.clone()])),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(_, ids)| ids)
+        .filter(|ids| ids.len() > 1)
+        .collect()
+}
+
+/// [ThumbHash](https://evanw.github.io/thumbhash/) of the image, base64 encoded.
+///
+/// Images larger than 100 x 100 are reduced first.
+pub fn thumbhash(image: &RgbaImage) -> String {
+    let scale = (THUMBHASH_MAX as f64 / image.width.max(image.height) as f64).min(1.0);
+    let w = ((image.width as f64 * scale).round() as usize).max(1);
+    let h = ((image.height as f64 * scale).round() as usize).max(1);
+    let pixels = image.resize(w, h);
+    // JavaScript Math.round, to produce the same bytes as the reference implementation
+    let round = |value: f64| (value + 0.5).floor() as u32;
+
+    let (mut avg_r, mut avg_g, mut avg_b, mut avg_a) = (0.0, 0.0, 0.0, 0.0);
+    for [r, g, b, a] in &pixels {
+        let alpha = a / 255.0;
+        avg_r += alpha / 255.0 * r;
+        avg_g += alpha / 255.0 * g;
+        avg_b += alpha / 255.0 * b;
+        avg_a += alpha;
+    }
+    if avg_a > 0.0 {
+        avg_r /= avg_a;
+        avg_g /= avg_a;
+        avg_b /= avg_a;
+    }
+
+    let has_alpha = avg_a < (w * h) as f64;
+    let l_limit = if has_alpha { 5.0 } else { 7.0 };
+    let lx = round(l_limit * w as f64 / w.max(h) as f64).max(1);
+    let ly = round(l_limit * h as f64 / w.max(h) as f64).max(1);
+    let (mut l, mut p, mut q, mut a) = (vec![], vec![], vec![], vec![]);
+    for [r, g, b, alpha] in &pixels {
+        let alpha = alpha / 255.0;
+        let r = avg_r * (1.0 - alpha) + alpha / 255.0 * r;
+        let g = avg_g * (1.0 - alpha) + alpha / 255.0 * g;
+        let b = avg_b * (1.0 - alpha) + alpha / 255.0 * b;
+        l.push((r + g + b) / 3.0);
+        p.push((r + g) / 2.0 - b);
+        q.push(r - g);
+        a.push(alpha);
+    }
+
+    let encode_channel = |channel: &[f64], nx: u32, ny: u32| {
+        let (mut dc, mut ac, mut scale) = (0.0, vec![], 0.0f64);
+        for cy in 0..ny {
+            let mut cx = 0;
+            while cx * ny < nx * (ny - cy) {
+                let mut f = 0.0;
+                for y in 0..h {
+                    let fy = (PI / h as f64 * cy as f64 * (y as f64 + 0.5)).cos();
+                    for x in 0..w {
+                        let fx = (PI / w as f64 * cx as f64 * (x as f64 + 0.5)).cos();
+                        f += channel[x + y * w] * fx * fy;
+                    }
+                }
+                f /= (w * h) as f64;
+                if cx > 0 || cy > 0 {
+                    ac.push(f);
+                    scale = scale.max(f.abs());
+                } else {
+                    dc = f;
+                }
+                cx += 1;
+            }
+        }
+        if scale > 0.0 {
+            for value in ac.iter_mut() {
+                *value = 0.5 + 0.5 / scale * *value;
+            }
+        }
+        (dc, ac, scale)
+    };
+    let (l_dc, l_ac, l_scale) = encode_channel(&l, lx.max(3), ly.max(3));
+    let (p_dc, p_ac, p_scale) = encode_channel(&p, 3, 3);
+    let (q_dc, q_ac, q_scale) = encode_channel(&q, 3, 3);
+
+    let landscape = w > h;
+    let header24 = round(63.0 * l_dc)
+        | round(31.5 + 31.5 * p_dc) << 6
+        | round(31.5 + 31.5 * q_dc) << 12
+        | round(31.0 * l_scale) << 18
+        | (has_alpha as u32) << 23;
+    let header16 = (if landscape { ly } else { lx })
+        | round(63.0 * p_scale) << 3
+        | round(63.0 * q_scale) << 9
+        | (landscape as u32) << 15;
+    let mut hash = vec![
+        header24 as u8,
+        (header24 >> 8) as u8,
+        (header24 >> 16) as u8,
+        header16 as u8,
+        (header16 >> 8) as u8,
+    ];
+    let mut channels = vec![l_ac, p_ac, q_ac];
+    if has_alpha {
+        let (a_dc, a_ac, a_scale) = encode_channel(&a, 5, 5);
+        hash.push((round(15.0 * a_dc) | round(15.0 * a_scale) << 4) as u8);
+        channels.push(a_ac);
+    }
+    let ac_start = hash.len();
+    for (index, value) in channels.iter().flatten().enumerate() {
+        if ac_start + index / 2 == hash.len() {
+            hash.push(0);
+        }
+        hash[ac_start + index / 2] |= (round(15.0 * value) << ((index & 1) * 4)) as u8;
+    }
+    STANDARD.encode(hash)
+}
+
+/// Average color of a thumbhash, as RGBA, for placeholders.
+pub fn thumbhash_average_rgba(hash: &str) -> Result<[u8; 4], MetadataError> {
+    let bytes = STANDARD
+        .decode(hash)
+        .map_err(|_| MetadataError::Invalid(hash.to_string()))?;
+    if bytes.len() < 5 {
+        return Err(MetadataError::Invalid(hash.to_string()));
+    }
+    let header = bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16;
+    let l = (header & 63) as f64 / 63.0;
+    let p = ((header >> 6) & 63) as f64 / 31.5 - 1.0;
+    let q = ((header >> 12) & 63) as f64 / 31.5 - 1.0;
+    let a = match (header >> 23 == 1, bytes.get(5)) {
+        (true, Some(byte)) => (byte & 15) as f64 / 15.0,
+        _ => 1.0,
+    };
+    let b = l - 2.0 / 3.0 * p;
+    let r = (3.0 * l - b + q) / 2.0;
+    let g = r - q;
+    Ok([r, g, b, a].map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Diagonal gradient with a dark square, `shift` brightens everything.
+    fn sample(width: usize, height: usize, shift: u8) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            for x in 0..width {
+                let square = x > width / 4 && x < width / 2 && y > height / 3;
+                let value = if square {
+                    20
+                } else {
+                    ((x + y) * 200 / (width + height)) as u8
+                };
+                let value = value.saturating_add(shift);
+                pixels.extend_from_slice(&[value, value / 2, 255 - value, 255]);
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn perceptual_hash_is_stable() {
+        let pixels = sample(320, 240, 0);
+        let original = perceptual_hash(&RgbaImage::new(320, 240, &pixels).unwrap());
+        assert_eq!(original.len(), 16);
+
+        let small = sample(80, 60, 0);
+        let resized = perceptual_hash(&RgbaImage::new(80, 60, &small).unwrap());
+        assert!(phash_distance(&original, &resized).unwrap() <= 4);
+
+        let bright = sample(320, 240, 30);
+        let brighter = perceptual_hash(&RgbaImage::new(320, 240, &bright).unwrap());
+        assert!(phash_distance(&original, &brighter).unwrap() <= DUPLICATE_DISTANCE);
+
+        let mirrored: Vec<u8> = pixels
+            .chunks(320 * 4)
+            .flat_map(|row| row.chunks(4).rev().flatten().copied().collect::<Vec<_>>())
+            .collect();
+        let other = perceptual_hash(&RgbaImage::new(320, 240, &mirrored).unwrap());
+        assert!(phash_distance(&original, &other).unwrap() > DUPLICATE_DISTANCE);
+
+        assert!(RgbaImage::new(10, 10, &pixels[..10]).is_err());
+        assert!(phash_distance("00ff", "0000000000000000").is_err());
+        assert_eq!(
+            phash_distance("00000000000000ff", "000000000000000f").unwrap(),
+            4
+        );
+    }
+
+    #[test]
+    fn groups_near_duplicates() {
+        let media = |id: &str, phash: Option<&str>| Media {
+            id: id.to_string(),
+            phash: phash.map(|p| p.to_string()),
+            ..Default::default()
+        };
+        let medias = vec![
+            media("a", Some("ffff0000ffff0000")),
+            media("b", Some("0123456789abcdef")),
+            media("c", Some("ffff0000ffff0003")),
+            media("d", None),
+            media("e", Some("ffff0000ffff000f")),
+            media("f", Some("0123456789abcdee")),
+            media("g", Some("invalid")),
+        ];
+        assert_eq!(
+            group_near_duplicates(&medias, 2),
+            vec![vec!["a", "c", "e"], vec!["b", "f"]]
+        );
+        assert_eq!(group_near_duplicates(&medias, 1), vec![vec!["b", "f"]]);
+    }
+
+    #[test]
+    fn thumbhash_of_images() {
+        let red: Vec<u8> = [255, 0, 0, 255].repeat(16 * 16);
+        let hash = thumbhash(&RgbaImage::new(16, 16, &red).unwrap());
+        let [r, g, b, a] = thumbhash_average_rgba(&hash).unwrap();
+        assert!(
+            r > 230 && g < 30 && b < 30 && a == 255,
+            "{:?}",
+            [r, g, b, a]
+        );
+
+        let pixels = sample(320, 240, 0);
+        let large = thumbhash(&RgbaImage::new(320, 240, &pixels).unwrap());
+        assert_eq!(STANDARD.decode(&large).unwrap().len(), 5 + 16);
+        assert_ne!(large, hash);
+
+        let transparent: Vec<u8> = [0, 0, 255, 128].repeat(20 * 10);
+        let hash = thumbhash(&RgbaImage::new(20, 10, &transparent).unwrap());
+        let [_, _, b, a] = thumbhash_average_rgba(&hash).unwrap();
+        assert!(b > 230 && a < 255);
+        assert!(thumbhash_average_rgba("abc").is_err());
+    }
+
+    /// Irregular pattern, alpha varying when `alpha`: no coefficient falls on a rounding edge.
+    fn pattern(width: usize, height: usize, alpha: bool) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            for x in 0..width {
+                let a = if alpha {
+                    (x * 17 + y * y * 3) % 256
+                } else {
+                    255
+                };
+                pixels.extend(
+                    [x * x * 7 + y * 13, x * y * 3 + y * y, x * 11 + y * 5, a].map(|v| v as u8),
+                );
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn thumbhash_matches_reference() {
+        // hashes of the same pixels by the reference JavaScript `rgbaToThumbHash`
+        let opaque = pattern(32, 24, false);
+        assert_eq!(
+            thumbhash(&RgbaImage::new(32, 24, &opaque).unwrap()),
+            "HggGJYQngHZRk1lSd2RnhvSHo/iq"
+        );
+        let transparent = pattern(24, 32, true);
+        assert_eq!(
+            thumbhash(&RgbaImage::new(24, 32, &transparent).unwrap()),
+            "3weCFAIHCkZRLtdzWH9dWM99Mko1UDZ0Bg=="
+        );
+    }
+
+    #[test]
+    fn rejects_overflowing_sizes() {
+        assert!(matches!(
+            RgbaImage::new(usize::MAX, 2, &[0; 8]),
+            Err(MetadataError::Invalid(_))
+        ));
+        assert!(RgbaImage::new(2, 1, &[0; 8]).is_ok());
+    }
+}
@@ -0,0 +1,68 @@
+//! Extraction of technical metadata from file bytes into `MediaForUpdate`.
+//!
+//! Extractors are behind features so plugins only pull the parsers they use:
+//! - `exif`: EXIF and XMP of JPEG, HEIC, PNG and TIFF images (`photo`)
+//! - `probe`: MP4/MOV/M4V and Matroska/WebM headers (`container`)
+//! - `phash`: perceptual hash and thumbhash of decoded pixels (`hash`)
+
+use serde::Serialize;
+
+#[cfg(feature = "probe")]
+pub mod container;
+#[cfg(feature = "phash")]
+pub mod hash;
+#[cfg(feature = "exif")]
+pub mod photo;
+
+#[derive(Debug, Serialize, strum_macros::AsRefStr)]
+pub enum MetadataError {
+    /// The bytes contain no metadata the extractor understands
+    NotFound,
+    UnsupportedFormat(String),
+    Invalid(String),
+}
+
+// region:    --- Error Boilerplate
+
+impl core::fmt::Display for MetadataError {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for MetadataError {}
+
+// endregion: --- Error Boilerplate
+
+/// Milliseconds since epoch of a UTC civil date and time.
+#[cfg_attr(not(any(feature = "exif", feature = "probe")), allow(dead_code))]
+pub(crate) fn civil_to_millis(
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+) -> i64 {
+    // days_from_civil, H. Hinnant
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    (days * 86400 + hour as i64 * 3600 + minute as i64 * 60 + second as i64) * 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn civil_dates_to_millis() {
+        assert_eq!(civil_to_millis(1970, 1, 1, 0, 0, 0), 0);
+        assert_eq!(civil_to_millis(2000, 3, 1, 0, 0, 0), 951868800000);
+        assert_eq!(civil_to_millis(2024, 2, 29, 12, 30, 15), 1709209815000);
+    }
+}
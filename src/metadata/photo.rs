@@ -0,0 +1,537 @@
+//! EXIF and XMP metadata of JPEG, HEIC, PNG and TIFF images.
+//!
+//! EXIF is read with `kamadak-exif`. XMP packets are found by scanning the bytes for the
+//! `x:xmpmeta` envelope, which covers JPEG APP1, HEIC `mime` items, TIFF tag 700 and
+//! uncompressed PNG `iTXt` chunks. XMP is parsed assuming the usual prefixes (`dc`, `xmp`,
+//! `mwg-rs`, `MPRI`...).
+
+use std::{io::Cursor, sync::OnceLock};
+
+use exif::{DateTime, Exif, In, Reader, Tag, Value};
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader as XmlReader,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{gps::RsGpsPosition, media::MediaForUpdate, update::Patch},
+    metadata::{civil_to_millis, MetadataError},
+};
+
+/// Metadata of an XMP packet.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct XmpMetadata {
+    /// `dc:subject` keywords
+    pub keywords: Vec<String>,
+    /// Names of face regions (`mwg-rs` regions of type `Face`, Windows `MPRI` regions)
+    pub people: Vec<String>,
+    pub description: Option<String>,
+    /// `xmp:Rating`, negative values (rejected) are ignored
+    pub rating: Option<u16>,
+    /// Creation date in milliseconds (`exif:DateTimeOriginal`, `photoshop:DateCreated` or
+    /// `xmp:CreateDate`)
+    pub created: Option<i64>,
+}
+
+/// Extract EXIF and XMP of an image into a patch.
+///
+/// EXIF values win over XMP ones. Keywords go to `tags_lookup` and face region names to
+/// `people_lookup`. Fails with `NotFound` when the image has neither EXIF nor XMP.
+pub fn extract_photo_metadata(bytes: &[u8]) -> Result<MediaForUpdate, MetadataError> {
+    let exif = match Reader::new().read_from_container(&mut Cursor::new(bytes)) {
+        Ok(exif) => Ok(Some(exif)),
+        Err(exif::Error::NotFound(_)) => Ok(None),
+        Err(exif::Error::InvalidFormat(message)) if message == "Unknown image format" => {
+            Err(MetadataError::UnsupportedFormat(message.to_string()))
+        }
+        Err(err) => Err(MetadataError::Invalid(err.to_string())),
+    };
+    let xmp = match (find_xmp(bytes).map(parse_xmp), &exif) {
+        (Some(Ok(xmp)), _) => Some(xmp),
+        // broken XMP packets are common, the EXIF is enough
+        (Some(Err(_)), Ok(Some(_))) => None,
+        (Some(Err(err)), _) => return Err(err),
+        (None, _) => None,
+    };
+
+    let exif = match (exif, &xmp) {
+        (Ok(exif), _) => exif,
+        (Err(_), Some(_)) => None,
+        (Err(err), None) => return Err(err),
+    };
+    if exif.is_none() && xmp.is_none() {
+        return Err(MetadataError::NotFound);
+    }
+
+    let mut update = MediaForUpdate::default();
+    if let Some(exif) = exif {
+        apply_exif(&exif, &mut update);
+    }
+    if let Some(xmp) = xmp {
+        update.description = xmp.description.into();
+        update.rating = xmp.rating.into();
+        update.created = update.created.or(xmp.created);
+        if !xmp.keywords.is_empty() {
+            update.tags_lookup = Some(xmp.keywords);
+        }
+        if !xmp.people.is_empty() {
+            update.people_lookup = Some(xmp.people);
+        }
+    }
+    Ok(update)
+}
+
+/// XMP packet embedded in a file, if any.
+pub fn find_xmp(bytes: &[u8]) -> Option<&str> {
+    [
+        (&b"<x:xmpmeta"[..], &b"</x:xmpmeta>"[..]),
+        (b"<x:xapmeta", b"</x:xapmeta>"),
+        (b"<rdf:RDF", b"</rdf:RDF>"),
+    ]
+    .into_iter()
+    .find_map(|(start, end)| {
+        let from = find(bytes, start)?;
+        let to = from + find(&bytes[from..], end)? + end.len();
+        std::str::from_utf8(&bytes[from..to]).ok()
+    })
+}
+
+pub fn parse_xmp(xml: &str) -> Result<XmpMetadata, MetadataError> {
+    let mut reader = XmlReader::from_str(xml);
+    let mut metadata = XmpMetadata::default();
+    let mut stack: Vec<String> = vec![];
+    // Name and type of the mwg-rs region being read
+    let mut region: (Option<String>, Option<String>) = (None, None);
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|err| MetadataError::Invalid(err.to_string()))?;
+        match event {
+            Event::Start(element) => {
+                let name = qname(&element);
+                read_attributes(&element, &mut metadata, &mut region);
+                stack.push(name);
+            }
+            Event::Empty(element) => {
+                let name = qname(&element);
+                read_attributes(&element, &mut metadata, &mut region);
+                if name == "rdf:li" && in_element(&stack, "mwg-rs:RegionList") {
+                    push_region(&mut metadata, &mut region);
+                }
+            }
+            Event::Text(text) => {
+                let text = text
+                    .unescape()
+                    .map_err(|err| MetadataError::Invalid(err.to_string()))?;
+                let text = text.trim();
+                if text.is_empty() {
+                    continue;
+                }
+                match stack.last().map(String::as_str) {
+                    Some("rdf:li") if in_element(&stack, "dc:subject") => {
+                        push_unique(&mut metadata.keywords, text)
+                    }
+                    Some("rdf:li") if in_element(&stack, "dc:description") => {
+                        metadata.description.get_or_insert_with(|| text.to_string());
+                    }
+                    Some(name) => read_property(name, text, &mut metadata, &mut region),
+                    None => {}
+                }
+            }
+            Event::End(_) => {
+                if let Some(name) = stack.pop() {
+                    if name == "rdf:li" && in_element(&stack, "mwg-rs:RegionList") {
+                        push_region(&mut metadata, &mut region);
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(metadata)
+}
+
+fn qname(element: &BytesStart) -> String {
+    String::from_utf8_lossy(element.name().as_ref()).to_string()
+}
+
+fn in_element(stack: &[String], name: &str) -> bool {
+    stack.iter().any(|element| element == name)
+}
+
+fn read_attributes(
+    element: &BytesStart,
+    metadata: &mut XmpMetadata,
+    region: &mut (Option<String>, Option<String>),
+) {
+    for attribute in element.attributes().flatten() {
+        let name = String::from_utf8_lossy(attribute.key.as_ref()).to_string();
+        if let Ok(value) = attribute.unescape_value() {
+            read_property(&name, value.trim(), metadata, region);
+        }
+    }
+}
+
+/// Simple properties, written either as attribute or as element.
+fn read_property(
+    name: &str,
+    value: &str,
+    metadata: &mut XmpMetadata,
+    region: &mut (Option<String>, Option<String>),
+) {
+    match name {
+        "xmp:Rating" => {
+            metadata.rating = value
+                .parse::<f64>()
+                .ok()
+                .filter(|rating| *rating >= 0.0)
+                .map(|rating| rating.round() as u16)
+        }
+        "exif:DateTimeOriginal" | "photoshop:DateCreated" | "xmp:CreateDate" => {
+            if let Some(created) = parse_xmp_date(value) {
+                // photoshop:DateCreated and exif:DateTimeOriginal are the capture date
+                if name != "xmp:CreateDate" || metadata.created.is_none() {
+                    metadata.created = Some(created);
+                }
+            }
+        }
+        "mwg-rs:Name" => region.0 = Some(value.to_string()),
+        "mwg-rs:Type" => region.1 = Some(value.to_string()),
+        "MPRI:PersonDisplayName" => push_unique(&mut metadata.people, value),
+        _ => {}
+    }
+}
+
+fn push_region(metadata: &mut XmpMetadata, region: &mut (Option<String>, Option<String>)) {
+    if let (Some(name), kind) = std::mem::take(region) {
+        if kind.is_none_or(|kind| kind == "Face") {
+            push_unique(&mut metadata.people, &name);
+        }
+    }
+}
+
+fn push_unique(list: &mut Vec<String>, value: &str) {
+    if !value.is_empty() && !list.iter().any(|v| v == value) {
+        list.push(value.to_string());
+    }
+}
+
+/// XMP date (`2024-02-29T12:30:15+01:00`, `2024-02-29T12:30`, `2024-02-29`) in milliseconds.
+/// Dates without time zone are taken as UTC.
+fn parse_xmp_date(value: &str) -> Option<i64> {
+    static XMP_DATE: OnceLock<Regex> = OnceLock::new();
+    let re = XMP_DATE.get_or_init(|| {
+        Regex::new(
+            r"^(\d{4})-(\d{2})-(\d{2})(?:T(\d{2}):(\d{2})(?::(\d{2})(?:\.(\d+))?)?(Z|[+-]\d{2}:\d{2})?)?",
+        )
+        .unwrap()
+    });
+    let caps = re.captures(value)?;
+    let number = |index: usize| caps.get(index).and_then(|m| m.as_str().parse::<u32>().ok());
+    let mut millis = civil_to_millis(
+        number(1)? as i64,
+        number(2)?,
+        number(3)?,
+        number(4).unwrap_or(0),
+        number(5).unwrap_or(0),
+        number(6).unwrap_or(0),
+    );
+    if let Some(fraction) = caps.get(7) {
+        let digits: String = fraction
+            .as_str()
+            .chars()
+            .chain("000".chars())
+            .take(3)
+            .collect();
+        millis += digits.parse::<i64>().ok()?;
+    }
+    if let Some(offset) = caps.get(8).map(|m| m.as_str()).filter(|o| *o != "Z") {
+        let hours = offset[1..3].parse::<i64>().ok()?;
+        let minutes = offset[4..6].parse::<i64>().ok()?;
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        millis -= sign * (hours * 60 + minutes) * 60_000;
+    }
+    Some(millis)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn apply_exif(exif: &Exif, update: &mut MediaForUpdate) {
+    let field = |tag: Tag| exif.get_field(tag, In::PRIMARY).map(|field| &field.value);
+    let text = |tag: Tag| field(tag).and_then(ascii);
+    let number = |tag: Tag| field(tag).and_then(rational);
+    let uint = |tag: Tag| field(tag).and_then(|value| value.get_uint(0));
+
+    update.model = match (text(Tag::Make), text(Tag::Model)) {
+        (Some(make), Some(model)) if !model.to_lowercase().starts_with(&make.to_lowercase()) => {
+            Some(format!("{} {}", make, model))
+        }
+        (_, Some(model)) => Some(model),
+        (make, None) => make,
+    };
+    update.focal = number(Tag::FocalLength).map(|focal| focal.round() as u64);
+    update.iso = uint(Tag::PhotographicSensitivity).map(u64::from);
+    update.sspeed = number(Tag::ExposureTime).map(format_exposure);
+    update.f_number = number(Tag::FNumber);
+    update.orientation = uint(Tag::Orientation).and_then(|o| u8::try_from(o).ok());
+    update.color_space = uint(Tag::ColorSpace).map(|space| match space {
+        1 => "sRGB".to_string(),
+        2 => "Adobe RGB".to_string(),
+        0xffff => "Uncalibrated".to_string(),
+        other => other.to_string(),
+    });
+    update.width = uint(Tag::PixelXDimension).or(uint(Tag::ImageWidth));
+    update.height = uint(Tag::PixelYDimension).or(uint(Tag::ImageLength));
+
+    let rationals = |tag: Tag| match field(tag) {
+        Some(Value::Rational(parts)) => {
+            Some(parts.iter().map(|r| (r.num, r.denom)).collect::<Vec<_>>())
+        }
+        _ => None,
+    };
+    if let (Some(lat), Some(long)) = (rationals(Tag::GPSLatitude), rationals(Tag::GPSLongitude)) {
+        let lat_ref = text(Tag::GPSLatitudeRef).unwrap_or_default();
+        let long_ref = text(Tag::GPSLongitudeRef).unwrap_or_default();
+        if let Ok(position) = RsGpsPosition::from_exif(&lat, &lat_ref, &long, &long_ref) {
+            update.lat = Patch::Set(position.lat);
+            update.long = Patch::Set(position.long);
+        }
+    }
+
+    update.created = [
+        (
+            Tag::DateTimeOriginal,
+            Tag::OffsetTimeOriginal,
+            Tag::SubSecTimeOriginal,
+        ),
+        (Tag::DateTime, Tag::OffsetTime, Tag::SubSecTime),
+    ]
+    .into_iter()
+    .find_map(|(date, offset, subsec)| {
+        let mut datetime = match field(date)? {
+            Value::Ascii(values) => DateTime::from_ascii(values.first()?).ok()?,
+            _ => return None,
+        };
+        if let Some(Value::Ascii(values)) = field(offset) {
+            values.first().map(|v| datetime.parse_offset(v));
+        }
+        if let Some(Value::Ascii(values)) = field(subsec) {
+            values.first().map(|v| datetime.parse_subsec(v));
+        }
+        let millis = civil_to_millis(
+            datetime.year as i64,
+            datetime.month as u32,
+            datetime.day as u32,
+            datetime.hour as u32,
+            datetime.minute as u32,
+            datetime.second as u32,
+        );
+        Some(
+            millis - datetime.offset.unwrap_or(0) as i64 * 60_000
+                + datetime.nanosecond.unwrap_or(0) as i64 / 1_000_000,
+        )
+    });
+}
+
+fn ascii(value: &Value) -> Option<String> {
+    match value {
+        Value::Ascii(values) => values
+            .first()
+            .map(|v| String::from_utf8_lossy(v).trim().to_string())
+            .filter(|v| !v.is_empty()),
+        _ => None,
+    }
+}
+
+fn rational(value: &Value) -> Option<f64> {
+    let number = match value {
+        Value::Rational(values) => values.first()?.to_f64(),
+        Value::SRational(values) => values.first()?.to_f64(),
+        other => other.get_uint(0)? as f64,
+    };
+    number.is_finite().then_some(number)
+}
+
+/// `1/250` under a second, seconds otherwise.
+fn format_exposure(seconds: f64) -> String {
+    if seconds > 0.0 && seconds < 1.0 {
+        format!("1/{}", (1.0 / seconds).round())
+    } else {
+        format!("{}", (seconds * 10.0).round() / 10.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::{experimental::Writer, Field, Rational};
+
+    const XMP: &str = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about="" xmp:Rating="4" xmp:CreateDate="2024-02-29T13:30:15+01:00">
+   <dc:subject><rdf:Bag><rdf:li>Beach</rdf:li><rdf:li>Holidays &amp; Sun</rdf:li></rdf:Bag></dc:subject>
+   <dc:description><rdf:Alt><rdf:li xml:lang="x-default">At the beach</rdf:li></rdf:Alt></dc:description>
+   <mwg-rs:Regions rdf:parseType="Resource">
+    <mwg-rs:RegionList><rdf:Bag>
+     <rdf:li><rdf:Description mwg-rs:Name="Alice" mwg-rs:Type="Face"/></rdf:li>
+     <rdf:li rdf:parseType="Resource"><mwg-rs:Name>Bob</mwg-rs:Name><mwg-rs:Type>Face</mwg-rs:Type></rdf:li>
+     <rdf:li mwg-rs:Name="Dog" mwg-rs:Type="Pet"/>
+    </rdf:Bag></mwg-rs:RegionList>
+   </mwg-rs:Regions>
+   <MP:RegionInfo><MPRI:Regions><rdf:Bag>
+    <rdf:li MPRI:PersonDisplayName="Alice"/>
+   </rdf:Bag></MPRI:Regions></MP:RegionInfo>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+
+    fn ascii_field(tag: Tag, value: &str) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![value.as_bytes().to_vec()]),
+        }
+    }
+
+    fn rational_field(tag: Tag, values: &[(u32, u32)]) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Rational(values.iter().map(|v| Rational::from(*v)).collect()),
+        }
+    }
+
+    fn jpeg(xmp: Option<&str>) -> Vec<u8> {
+        let fields = vec![
+            ascii_field(Tag::Make, "Canon"),
+            ascii_field(Tag::Model, "EOS R5"),
+            Field {
+                tag: Tag::Orientation,
+                ifd_num: In::PRIMARY,
+                value: Value::Short(vec![6]),
+            },
+            rational_field(Tag::FocalLength, &[(50, 1)]),
+            rational_field(Tag::ExposureTime, &[(1, 250)]),
+            rational_field(Tag::FNumber, &[(28, 10)]),
+            Field {
+                tag: Tag::PhotographicSensitivity,
+                ifd_num: In::PRIMARY,
+                value: Value::Short(vec![400]),
+            },
+            Field {
+                tag: Tag::ColorSpace,
+                ifd_num: In::PRIMARY,
+                value: Value::Short(vec![1]),
+            },
+            ascii_field(Tag::DateTimeOriginal, "2024:02:29 14:30:15"),
+            ascii_field(Tag::OffsetTimeOriginal, "+02:00"),
+            ascii_field(Tag::GPSLatitudeRef, "N"),
+            rational_field(Tag::GPSLatitude, &[(48, 1), (51, 1), (0, 1)]),
+            ascii_field(Tag::GPSLongitudeRef, "W"),
+            rational_field(Tag::GPSLongitude, &[(2, 1), (30, 1), (0, 1)]),
+        ];
+        let mut writer = Writer::new();
+        for field in &fields {
+            writer.push_field(field);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let tiff = tiff.into_inner();
+
+        let mut bytes = vec![0xff, 0xd8];
+        let mut segment = |payload: &[u8]| {
+            bytes.extend([0xff, 0xe1]);
+            bytes.extend(((payload.len() + 2) as u16).to_be_bytes());
+            bytes.extend(payload);
+        };
+        segment(&[&b"Exif\0\0"[..], &tiff].concat());
+        if let Some(xmp) = xmp {
+            segment(&[&b"http://ns.adobe.com/xap/1.0/\0"[..], xmp.as_bytes()].concat());
+        }
+        bytes.extend([0xff, 0xd9]);
+        bytes
+    }
+
+    #[test]
+    fn parse_xmp_packet() {
+        let metadata = parse_xmp(XMP).unwrap();
+        assert_eq!(metadata.keywords, vec!["Beach", "Holidays & Sun"]);
+        assert_eq!(metadata.people, vec!["Alice", "Bob"]);
+        assert_eq!(metadata.description.as_deref(), Some("At the beach"));
+        assert_eq!(metadata.rating, Some(4));
+        assert_eq!(metadata.created, Some(1709209815000));
+        assert_eq!(parse_xmp_date("2024-02-29"), Some(1709164800000));
+        assert_eq!(
+            parse_xmp_date("2024-02-29T12:30:15.5Z"),
+            Some(1709209815500)
+        );
+    }
+
+    #[test]
+    fn extract_exif_and_xmp_from_jpeg() {
+        let update = extract_photo_metadata(&jpeg(Some(XMP))).unwrap();
+        assert_eq!(update.model.as_deref(), Some("Canon EOS R5"));
+        assert_eq!(update.focal, Some(50));
+        assert_eq!(update.iso, Some(400));
+        assert_eq!(update.sspeed.as_deref(), Some("1/250"));
+        assert_eq!(update.f_number, Some(2.8));
+        assert_eq!(update.orientation, Some(6));
+        assert_eq!(update.color_space.as_deref(), Some("sRGB"));
+        assert_eq!(update.lat, Patch::Set(48.85));
+        assert_eq!(update.long, Patch::Set(-2.5));
+        // EXIF date wins over XMP
+        assert_eq!(update.created, Some(1709209815000));
+        assert_eq!(update.rating, Patch::Set(4));
+        assert_eq!(update.description, Patch::Set("At the beach".to_string()));
+        assert_eq!(
+            update.tags_lookup,
+            Some(vec!["Beach".to_string(), "Holidays & Sun".to_string()])
+        );
+        assert_eq!(
+            update.people_lookup,
+            Some(vec!["Alice".to_string(), "Bob".to_string()])
+        );
+
+        let update = extract_photo_metadata(&jpeg(None)).unwrap();
+        assert_eq!(update.rating, Patch::Unchanged);
+        assert_eq!(update.tags_lookup, None);
+
+        // a truncated XMP packet is ignored when the EXIF is valid
+        let truncated = format!("{}</x:xmpmeta>", &XMP[..XMP.find("<dc:subject>").unwrap()]);
+        assert!(parse_xmp(&truncated).is_err());
+        let update = extract_photo_metadata(&jpeg(Some(&truncated))).unwrap();
+        assert_eq!(update.model.as_deref(), Some("Canon EOS R5"));
+        assert_eq!(update.rating, Patch::Unchanged);
+        let xmp_only = [&b"not an image "[..], truncated.as_bytes()].concat();
+        assert!(matches!(
+            extract_photo_metadata(&xmp_only),
+            Err(MetadataError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn extract_errors() {
+        assert!(matches!(
+            extract_photo_metadata(&[0xff, 0xd8, 0xff, 0xd9]),
+            Err(MetadataError::NotFound)
+        ));
+        assert!(matches!(
+            extract_photo_metadata(b"not an image"),
+            Err(MetadataError::UnsupportedFormat(_))
+        ));
+        let xmp_only = [&b"not an image "[..], XMP.as_bytes()].concat();
+        assert_eq!(
+            extract_photo_metadata(&xmp_only).unwrap().rating,
+            Patch::Set(4)
+        );
+    }
+}
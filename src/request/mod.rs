@@ -1,6 +1,7 @@
 use std::{collections::HashMap, str::FromStr};
 
 use crate::domain::media::{FileEpisode, Media, MediaForUpdate};
+use crate::domain::numbering::EpisodeNumbering;
 use crate::lookup::RsLookupMatchType;
 use crate::{CustomParamTypes, PluginCredential, RsFileType, RsVideoFormat};
 use crate::{RsAudio, RsResolution, RsVideoCodec};
@@ -311,6 +312,29 @@ impl RsRequest {
         }
     }
 
+    /// Resolve `season`/`episode` with the serie numbering once the serie is known: absolute
+    /// numbers (`One.Piece.1071.1080p`) and scene `SxxEyy` become provider season/episode.
+    /// Files are resolved too. Returns true when the request filename resolved.
+    pub fn apply_numbering(&mut self, numbering: &EpisodeNumbering) -> bool {
+        if let Some(ref mut files) = self.files {
+            for file in files {
+                file.apply_numbering(numbering);
+            }
+        }
+        match self
+            .filename
+            .as_deref()
+            .and_then(|filename| numbering.resolve_filename(filename))
+        {
+            Some((season, episode)) => {
+                self.season = Some(season);
+                self.episode = Some(episode);
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn parse_subfilenames(&mut self) {
         if let Some(ref mut files) = self.files {
             for file in files {
@@ -452,6 +476,18 @@ impl RsRequestFiles {
             self.episode = caps[2].parse::<u32>().ok();
         }
     }
+
+    /// See [`RsRequest::apply_numbering`].
+    pub fn apply_numbering(&mut self, numbering: &EpisodeNumbering) -> bool {
+        match numbering.resolve_filename(&self.name) {
+            Some((season, episode)) => {
+                self.season = Some(season);
+                self.episode = Some(episode);
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
@@ -691,4 +727,43 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_absolute_numbering() {
+        use crate::domain::{episode::Episode, numbering::EpisodeNumbering};
+
+        let episodes: Vec<Episode> = (1..=20)
+            .map(|number| Episode {
+                serie: "one-piece".to_string(),
+                season: 21,
+                number,
+                abs: Some(1060 + number),
+                ..Default::default()
+            })
+            .collect();
+        let numbering = EpisodeNumbering::from_episodes("one-piece", &episodes);
+        let mut req = RsRequest {
+            filename: Some("One.Piece.1071.1080p.WEB.x264.mkv".to_owned()),
+            files: Some(vec![RsRequestFiles {
+                name: "One.Piece.1072.1080p.WEB.x264.mkv".to_owned(),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        req.parse_filename();
+        req.parse_subfilenames();
+        assert_eq!(req.episode, None);
+        assert!(req.apply_numbering(&numbering));
+        assert_eq!((req.season, req.episode), (Some(21), Some(11)));
+        let file = &req.files.as_ref().unwrap()[0];
+        assert_eq!((file.season, file.episode), (Some(21), Some(12)));
+        assert_eq!(req.resolution, Some(RsResolution::FullHD));
+
+        let mut unknown = RsRequest {
+            filename: Some("One.Piece.0999.1080p.mkv".to_owned()),
+            ..Default::default()
+        };
+        assert!(!unknown.apply_numbering(&numbering));
+        assert_eq!(unknown.episode, None);
+    }
 }